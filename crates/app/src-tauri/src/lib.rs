@@ -1,4 +1,6 @@
 mod manifest_parser;
+mod oidc_loopback;
+mod po_export;
 mod save_system;
 
 use tauri::{Emitter, Manager, WindowEvent};
@@ -48,13 +50,28 @@ pub fn run() {
         })
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .manage(manifest_parser::progress::JobRegistry::default())
         .invoke_handler(tauri::generate_handler![
             manifest_parser::commands::parse_manifest_file,
+            manifest_parser::commands::read_manifest_file,
             manifest_parser::commands::validate_column_mapping,
             manifest_parser::commands::write_manifest_csv,
+            manifest_parser::commands::write_manifest_xlsx,
+            manifest_parser::commands::validate_manifest_rows,
+            manifest_parser::commands::export_validation_report,
+            manifest_parser::commands::summarize_manifest,
+            manifest_parser::commands::merge_manifests,
+            manifest_parser::commands::cancel_manifest_job,
+            manifest_parser::commands::suggest_prices,
+            manifest_parser::commands::load_manifest_rows,
+            manifest_parser::commands::set_manifest_cell,
+            manifest_parser::commands::get_manifest_lines,
+            po_export::export_edi_850,
+            po_export::export_po_pdf,
             save_system::save,
             save_system::load,
             save_system::update_save,
+            oidc_loopback::start_oidc_loopback,
             get_api_url
         ])
         .run(tauri::generate_context!())
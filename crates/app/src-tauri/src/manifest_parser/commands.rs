@@ -1,22 +1,55 @@
-use super::parser::{ManifestData, parse_manifest};
-use serde::{Deserialize, Serialize};
+use super::parser::{ManifestData, parse_manifest, read_manifest_with_progress};
+use super::progress::{JobRegistry, PROGRESS_EVENT, Phase, Progress};
+use super::xlsx_writer;
+pub use po_tracker_share_lib::manifest::ManifestRow;
+use po_tracker_share_lib::manifest::merge::{
+    DuplicateStrategy, ManifestSource, MergeResult, merge_manifests as merge_sources,
+};
+use po_tracker_share_lib::manifest::validation::{
+    Severity, ValidationContext, ValidationReport, validate_rows,
+};
+use po_tracker_share_lib::pricing::{FillMode, PricedManifest, PricingConfig};
+use po_tracker_share_lib::save_file::ManifestItem;
+use po_tracker_share_lib::summary::{PoSummary, summarize};
 use std::collections::HashMap;
+use tauri::{AppHandle, Emitter, State};
 
-#[derive(Deserialize, Serialize, Debug)]
-pub struct ManifestRow {
-    pub item_number: String,
-    pub upc: String,
-    pub description: String,
-    pub case_pack: String,
-    pub cases: String,
-    pub mardens_cost: String,
-    pub mardens_price: String,
-    pub comp_retail: String,
-    pub department: String,
-    pub category: String,
-    pub sub_category: String,
-    pub season: String,
-    pub notes: String,
+/// Runs a blocking manifest job, emitting progress events when the frontend passed a job id.
+///
+/// A job with an id can be stopped with `cancel_manifest_job`, in which case it fails with
+/// [`super::progress::CANCELLED`].
+async fn run_job<T: Send + 'static>(
+    app: AppHandle,
+    jobs: &JobRegistry,
+    job_id: Option<String>,
+    task: impl FnOnce(&mut Progress) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    let mut progress = match &job_id {
+        Some(job_id) => Progress::new(job_id.clone(), jobs.start(job_id), move |event| {
+            if let Err(e) = app.emit(PROGRESS_EVENT, event) {
+                eprintln!("Failed to emit progress event: {}", e);
+            }
+        }),
+        None => Progress::none(),
+    };
+
+    let result = tokio::task::spawn_blocking(move || {
+        let value = task(&mut progress)?;
+        progress.done();
+        Ok(value)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e));
+
+    if let Some(job_id) = &job_id {
+        jobs.finish(job_id);
+    }
+    result?
+}
+
+#[tauri::command]
+pub fn cancel_manifest_job(job_id: String, jobs: State<'_, JobRegistry>) -> bool {
+    jobs.cancel(&job_id)
 }
 
 #[tauri::command]
@@ -27,12 +60,29 @@ pub async fn parse_manifest_file(path: String) -> Result<ManifestData, String> {
         .map_err(|e| format!("Task join error: {}", e))?
 }
 
+/// Parses every row of a manifest file.
+#[tauri::command]
+pub async fn read_manifest_file(
+    path: String,
+    job_id: Option<String>,
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+) -> Result<ManifestData, String> {
+    run_job(app, &jobs, job_id, move |progress| {
+        read_manifest_with_progress(path, progress)
+    })
+    .await
+}
+
 #[tauri::command]
 pub async fn write_manifest_csv(
     rows: Vec<ManifestRow>,
     filename: String,
+    job_id: Option<String>,
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
 ) -> Result<String, String> {
-    tokio::task::spawn_blocking(move || {
+    run_job(app, &jobs, job_id, move |progress| {
         // Create temp directory for CSV
         let temp_dir = std::env::temp_dir().join("po_tracker_manifests");
         std::fs::create_dir_all(&temp_dir)
@@ -61,11 +111,18 @@ pub async fn write_manifest_csv(
                 "Sub Category",
                 "Season",
                 "Notes",
+                "UPC Issue",
             ])
             .map_err(|e| format!("Failed to write CSV header: {}", e))?;
 
         // Write data rows
-        for row in rows {
+        progress.phase(Phase::Writing, Some(rows.len()))?;
+        for (index, mut row) in rows.into_iter().enumerate() {
+            // Flag codes that don't pass check digit validation instead of dropping them
+            let upc_issue = match row.normalize_upc() {
+                Ok(()) => String::new(),
+                Err(e) => e.to_string(),
+            };
             writer
                 .write_record([
                     &row.item_number,
@@ -81,8 +138,10 @@ pub async fn write_manifest_csv(
                     &row.sub_category,
                     &row.season,
                     &row.notes,
+                    &upc_issue,
                 ])
                 .map_err(|e| format!("Failed to write CSV row: {}", e))?;
+            progress.rows(index + 1)?;
         }
 
         writer
@@ -96,7 +155,21 @@ pub async fn write_manifest_csv(
             .map(|s| s.to_string())
     })
     .await
-    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn write_manifest_xlsx(
+    rows: Vec<ManifestRow>,
+    path: String,
+    job_id: Option<String>,
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+) -> Result<String, String> {
+    run_job(app, &jobs, job_id, move |progress| {
+        xlsx_writer::write_manifest_xlsx(rows, &path, progress)?;
+        Ok(path)
+    })
+    .await
 }
 
 #[tauri::command]
@@ -145,3 +218,162 @@ pub async fn validate_column_mapping(
 
     Ok(errors)
 }
+
+#[tauri::command]
+pub async fn validate_manifest_rows(
+    path: String,
+    mappings: HashMap<String, String>,
+    context: ValidationContext,
+    job_id: Option<String>,
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+) -> Result<ValidationReport, String> {
+    run_job(app, &jobs, job_id, move |progress| {
+        let manifest_data = read_manifest_with_progress(path, progress)?;
+        let rows = manifest_data.to_rows(&mappings);
+        Ok(validate_rows(&rows, &context))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn summarize_manifest(
+    path: String,
+    mappings: HashMap<String, String>,
+    job_id: Option<String>,
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+) -> Result<PoSummary, String> {
+    run_job(app, &jobs, job_id, move |progress| {
+        let manifest_data = read_manifest_with_progress(path, progress)?;
+        let rows = manifest_data.to_rows(&mappings);
+        Ok(summarize(&rows))
+    })
+    .await
+}
+
+/// Merges every manifest of a PO into one line list.
+///
+/// Manifests with stored lines are merged with the buyer's edits applied; the others are read
+/// from their source file with their own column mappings.
+#[tauri::command]
+pub async fn merge_manifests(
+    manifests: Vec<ManifestItem>,
+    strategy: DuplicateStrategy,
+    job_id: Option<String>,
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+) -> Result<MergeResult, String> {
+    run_job(app, &jobs, job_id, move |progress| {
+        let sources = manifests
+            .into_iter()
+            .map(|manifest| {
+                if !manifest.rows.is_empty() {
+                    return Ok(ManifestSource {
+                        rows: manifest.lines(),
+                        filename: manifest.filename,
+                        path: manifest.path,
+                    });
+                }
+                let manifest_data = read_manifest_with_progress(manifest.path.clone(), progress)
+                    .map_err(|e| format!("{}: {}", manifest.filename, e))?;
+                Ok(ManifestSource {
+                    rows: manifest_data.to_rows(&manifest.mappings),
+                    filename: manifest.filename,
+                    path: manifest.path,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(merge_sources(sources, strategy))
+    })
+    .await
+}
+
+/// Reads the manifest's source file and stores its mapped rows on the item.
+///
+/// Existing overrides are kept for rows that still exist, so this can be rerun after the
+/// mappings change.
+#[tauri::command]
+pub async fn load_manifest_rows(
+    mut item: ManifestItem,
+    job_id: Option<String>,
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+) -> Result<ManifestItem, String> {
+    run_job(app, &jobs, job_id, move |progress| {
+        let manifest_data = read_manifest_with_progress(item.path.clone(), progress)?;
+        item.set_rows(manifest_data.to_rows(&item.mappings));
+        Ok(item)
+    })
+    .await
+}
+
+/// Sets the buyer's value for one cell of a stored manifest line, or reverts it with `None`.
+#[tauri::command]
+pub fn set_manifest_cell(
+    mut item: ManifestItem,
+    row: usize,
+    field: String,
+    value: Option<String>,
+) -> Result<ManifestItem, String> {
+    item.set_override(row, &field, value)
+        .map_err(|e| e.to_string())?;
+    Ok(item)
+}
+
+/// Returns the stored lines of a manifest with the buyer's edits applied.
+#[tauri::command]
+pub fn get_manifest_lines(item: ManifestItem) -> Vec<ManifestRow> {
+    item.lines()
+}
+
+/// Suggests a Mardens price for every row from the pricing rules and fills them per `fill`.
+#[tauri::command]
+pub async fn suggest_prices(
+    mut rows: Vec<ManifestRow>,
+    config: PricingConfig,
+    fill: FillMode,
+) -> Result<PricedManifest, String> {
+    tokio::task::spawn_blocking(move || {
+        let suggestions = config.suggest_prices(&mut rows, fill);
+        PricedManifest { rows, suggestions }
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))
+}
+
+#[tauri::command]
+pub async fn export_validation_report(
+    report: ValidationReport,
+    path: String,
+) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        let mut writer = csv::Writer::from_path(&path)
+            .map_err(|e| format!("Failed to create CSV writer: {}", e))?;
+
+        writer
+            .write_record(["Row", "Field", "Severity", "Message"])
+            .map_err(|e| format!("Failed to write CSV header: {}", e))?;
+
+        for issue in report.issues {
+            let severity = match issue.severity {
+                Severity::Error => "Error",
+                Severity::Warning => "Warning",
+            };
+            writer
+                .write_record([
+                    issue.row.to_string().as_str(),
+                    issue.field.as_str(),
+                    severity,
+                    issue.message.as_str(),
+                ])
+                .map_err(|e| format!("Failed to write CSV row: {}", e))?;
+        }
+
+        writer
+            .flush()
+            .map_err(|e| format!("Failed to flush CSV writer: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
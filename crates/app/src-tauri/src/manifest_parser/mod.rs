@@ -1,2 +1,5 @@
 pub mod commands;
 mod parser;
+mod pdf_table;
+pub mod progress;
+mod xlsx_writer;
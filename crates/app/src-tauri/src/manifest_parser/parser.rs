@@ -1,5 +1,9 @@
+use super::pdf_table::extract_table;
+use super::progress::{Phase, Progress};
 use calamine::{Data, Reader, open_workbook_auto};
+use po_tracker_share_lib::manifest::ManifestRow;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -9,7 +13,57 @@ pub struct ManifestData {
     pub total_rows: usize,
 }
 
+impl ManifestData {
+    /// Maps the raw rows onto template fields using `mappings` (template field -> column name).
+    ///
+    /// Fields that are not mapped, or mapped to a column that doesn't exist, are left empty.
+    /// Valid UPCs are normalized with their stripped leading zeros restored.
+    pub fn to_rows(&self, mappings: &HashMap<String, String>) -> Vec<ManifestRow> {
+        let indexes: Vec<(&str, usize)> = mappings
+            .iter()
+            .filter_map(|(field, column)| {
+                self.columns
+                    .iter()
+                    .position(|c| c == column)
+                    .map(|index| (field.as_str(), index))
+            })
+            .collect();
+
+        self.rows
+            .iter()
+            .map(|raw| {
+                let mut row = ManifestRow::default();
+                for (field, index) in &indexes {
+                    if let Some(value) = raw.get(*index) {
+                        row.set_field(field, value.trim().to_string());
+                    }
+                }
+                // Invalid codes are kept as-is so the validation report can flag them
+                let _ = row.normalize_upc();
+                row
+            })
+            .collect()
+    }
+}
+
+/// Parses the header and a preview of the first rows of a manifest file.
 pub fn parse_manifest(path: String) -> Result<ManifestData, String> {
+    load_manifest(path, true, &mut Progress::none())
+}
+
+/// Parses every data row of a manifest file, reporting progress and stopping if cancelled.
+pub fn read_manifest_with_progress(
+    path: String,
+    progress: &mut Progress,
+) -> Result<ManifestData, String> {
+    load_manifest(path, false, progress)
+}
+
+fn load_manifest(
+    path: String,
+    preview: bool,
+    progress: &mut Progress,
+) -> Result<ManifestData, String> {
     let path_obj = Path::new(&path);
 
     if !path_obj.exists() {
@@ -23,14 +77,19 @@ pub fn parse_manifest(path: String) -> Result<ManifestData, String> {
         .to_lowercase();
 
     match extension.as_str() {
-        "xlsx" | "xls" | "xlsm" | "xlsb" => parse_excel(path),
-        "csv" => parse_csv(path),
-        "pdf" => parse_pdf(path),
+        "xlsx" | "xls" | "xlsm" | "xlsb" => parse_excel(path, preview.then_some(3), progress),
+        "csv" => parse_csv(path, preview.then_some(10), progress),
+        "pdf" => parse_pdf(path, preview.then_some(10), progress),
         _ => Err(format!("Unsupported file format: .{}", extension)),
     }
 }
 
-fn parse_excel(path: String) -> Result<ManifestData, String> {
+fn parse_excel(
+    path: String,
+    limit: Option<usize>,
+    progress: &mut Progress,
+) -> Result<ManifestData, String> {
+    progress.phase(Phase::Opening, None)?;
     let mut workbook =
         open_workbook_auto(&path).map_err(|e| format!("Failed to open Excel file: {}", e))?;
 
@@ -55,13 +114,16 @@ fn parse_excel(path: String) -> Result<ManifestData, String> {
         return Err("Excel file has no columns".to_string());
     }
 
-    // Get up to 10 data rows
+    // Get the requested data rows
     let mut rows: Vec<Vec<String>> = Vec::new();
     let total_rows = range.height() - 1; // Subtract header row
+    let limit = limit.unwrap_or(usize::MAX);
+    progress.phase(Phase::Reading, Some(total_rows.min(limit)))?;
 
-    for row in rows_iter.take(3) {
+    for row in rows_iter.take(limit) {
         let row_data: Vec<String> = row.iter().map(cell_to_string).collect();
         rows.push(row_data);
+        progress.rows(rows.len())?;
     }
 
     Ok(ManifestData {
@@ -71,7 +133,14 @@ fn parse_excel(path: String) -> Result<ManifestData, String> {
     })
 }
 
-fn parse_csv(path: String) -> Result<ManifestData, String> {
+fn parse_csv(
+    path: String,
+    limit: Option<usize>,
+    progress: &mut Progress,
+) -> Result<ManifestData, String> {
+    progress.phase(Phase::Opening, None)?;
+    // The row count isn't known without reading the whole file, so percent is by bytes read
+    let file_size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
     let mut reader = csv::ReaderBuilder::new()
         .flexible(true)
         .from_path(&path)
@@ -89,17 +158,23 @@ fn parse_csv(path: String) -> Result<ManifestData, String> {
         return Err("CSV file has no columns".to_string());
     }
 
-    // Get up to 10 data rows
+    // Get the requested data rows
     let mut rows: Vec<Vec<String>> = Vec::new();
     let mut total_rows = 0;
+    let limit = limit.unwrap_or(usize::MAX);
+    progress.phase(Phase::Reading, None)?;
 
-    for result in reader.records() {
+    let mut records = reader.records();
+    while let Some(result) = records.next() {
         total_rows += 1;
-        if rows.len() < 10 {
+        if rows.len() < limit {
             let record = result.map_err(|e| format!("Failed to read CSV row: {}", e))?;
             let row_data: Vec<String> = record.iter().map(|s| s.to_string()).collect();
             rows.push(row_data);
         }
+        let percent = (file_size > 0)
+            .then(|| records.reader().position().byte() as f32 / file_size as f32 * 100.0);
+        progress.rows_with_percent(total_rows, percent)?;
     }
 
     Ok(ManifestData {
@@ -109,9 +184,33 @@ fn parse_csv(path: String) -> Result<ManifestData, String> {
     })
 }
 
-fn parse_pdf(path: String) -> Result<ManifestData, String> {
+fn parse_pdf(
+    path: String,
+    limit: Option<usize>,
+    progress: &mut Progress,
+) -> Result<ManifestData, String> {
+    // Text extraction is a single call, so progress is only reported once rows are split out
+    progress.phase(Phase::Opening, None)?;
+
+    // Prefer the layout-aware extractor, it handles column positioned invoices and wrapped rows
+    if let Some(table) = extract_table(&path)? {
+        let total_rows = table.rows.len();
+        let rows: Vec<Vec<String>> = table
+            .rows
+            .into_iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+        progress.phase(Phase::Reading, Some(rows.len()))?;
+        return Ok(ManifestData {
+            columns: table.columns,
+            rows,
+            total_rows,
+        });
+    }
+
     let text = pdf_extract::extract_text(&path)
         .map_err(|e| format!("Failed to extract text from PDF: {}", e))?;
+    progress.check_cancelled()?;
 
     if text.trim().is_empty() {
         return Err("PDF file contains no text".to_string());
@@ -134,7 +233,7 @@ fn parse_pdf(path: String) -> Result<ManifestData, String> {
         ','
     } else {
         // Use multiple spaces as delimiter
-        return parse_pdf_whitespace_delimited(lines);
+        return parse_pdf_whitespace_delimited(lines, limit);
     };
 
     // Parse with detected delimiter
@@ -151,7 +250,7 @@ fn parse_pdf(path: String) -> Result<ManifestData, String> {
     let mut rows: Vec<Vec<String>> = Vec::new();
     let total_rows = lines.len() - 1;
 
-    for line in lines.iter().skip(1).take(10) {
+    for line in lines.iter().skip(1).take(limit.unwrap_or(usize::MAX)) {
         let row_data: Vec<String> = line
             .split(delimiter)
             .map(|s| s.trim().to_string())
@@ -170,7 +269,10 @@ fn parse_pdf(path: String) -> Result<ManifestData, String> {
     })
 }
 
-fn parse_pdf_whitespace_delimited(lines: Vec<&str>) -> Result<ManifestData, String> {
+fn parse_pdf_whitespace_delimited(
+    lines: Vec<&str>,
+    limit: Option<usize>,
+) -> Result<ManifestData, String> {
     let first_line = lines[0];
 
     // Split on multiple spaces (2 or more)
@@ -187,7 +289,7 @@ fn parse_pdf_whitespace_delimited(lines: Vec<&str>) -> Result<ManifestData, Stri
     let mut rows: Vec<Vec<String>> = Vec::new();
     let total_rows = lines.len() - 1;
 
-    for line in lines.iter().skip(1).take(10) {
+    for line in lines.iter().skip(1).take(limit.unwrap_or(usize::MAX)) {
         let row_data: Vec<String> = line
             .split("  ")
             .map(|s| s.trim().to_string())
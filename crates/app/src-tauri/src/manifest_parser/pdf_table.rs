@@ -0,0 +1,379 @@
+use pdf_extract::{Document, MediaBox, OutputDev, OutputError, Transform};
+
+/// Horizontal gap (in multiples of the font size) that separates two cells on the same line.
+/// Regular word spacing is roughly a quarter of an em, column gutters are noticeably wider.
+const COLUMN_GAP_EM: f64 = 0.8;
+/// Vertical distance (in multiples of the font size) under which two glyphs share a line.
+const LINE_TOLERANCE_EM: f64 = 0.5;
+/// Minimum number of labelled columns required to accept a line as a table header.
+const MIN_HEADER_COLUMNS: usize = 2;
+
+/// A table reconstructed from the glyph layout of a PDF document.
+#[derive(Debug, Clone)]
+pub struct PdfTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+struct Glyph {
+    x: f64,
+    y: f64,
+    end: f64,
+    font_size: f64,
+    text: String,
+}
+
+/// A run of glyphs on a single line that belongs to one cell.
+#[derive(Debug, Clone)]
+struct Segment {
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+impl Segment {
+    fn center(&self) -> f64 {
+        (self.start + self.end) / 2.0
+    }
+
+    fn overlap(&self, start: f64, end: f64) -> f64 {
+        (self.end.min(end) - self.start.max(start)).max(0.0)
+    }
+}
+
+/// Horizontal extent of a column, seeded from the header label and widened by its data.
+#[derive(Debug, Clone)]
+struct ColumnBounds {
+    label: String,
+    start: f64,
+    end: f64,
+}
+
+/// Collects positioned glyphs page by page while `pdf_extract` walks the document.
+#[derive(Default)]
+struct GlyphCollector {
+    pages: Vec<Vec<Glyph>>,
+    current: Vec<Glyph>,
+    page_height: f64,
+}
+
+impl OutputDev for GlyphCollector {
+    fn begin_page(
+        &mut self,
+        _page_num: u32,
+        media_box: &MediaBox,
+        _art_box: Option<(f64, f64, f64, f64)>,
+    ) -> Result<(), OutputError> {
+        self.page_height = media_box.ury - media_box.lly;
+        self.current = Vec::new();
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        self.pages.push(std::mem::take(&mut self.current));
+        Ok(())
+    }
+
+    fn output_character(
+        &mut self,
+        trm: &Transform,
+        width: f64,
+        _spacing: f64,
+        font_size: f64,
+        char: &str,
+    ) -> Result<(), OutputError> {
+        // Flip the y axis so that lines are ordered top to bottom
+        let x = trm.m31;
+        let y = self.page_height - trm.m32;
+        let scaled_x = font_size * (trm.m11 + trm.m21);
+        let scaled_y = font_size * (trm.m12 + trm.m22);
+        let size = (scaled_x * scaled_y).abs().sqrt();
+        if size <= 0.0 {
+            return Ok(());
+        }
+        self.current.push(Glyph {
+            x,
+            y,
+            end: x + width * size,
+            font_size: size,
+            text: char.to_string(),
+        });
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+}
+
+/// Extracts a table from a PDF using the position of every glyph.
+///
+/// Glyphs are grouped into lines by their y coordinate and into cells by the horizontal gap
+/// between them. The header is detected on every page (vendors usually repeat it), and the
+/// header labels seed the column boundaries that data cells are assigned to. Lines that leave
+/// the first column empty and only fill a few cells are treated as wrapped text and merged into
+/// the row above.
+///
+/// Returns `Ok(None)` when no header could be detected, so the caller can fall back to a
+/// plain text heuristic.
+pub fn extract_table(path: &str) -> Result<Option<PdfTable>, String> {
+    let mut doc = Document::load(path).map_err(|e| format!("Failed to open PDF file: {}", e))?;
+    let mut collector = GlyphCollector::default();
+    if doc.is_encrypted() {
+        pdf_extract::output_doc_encrypted(&mut doc, &mut collector, "")
+    } else {
+        pdf_extract::output_doc(&doc, &mut collector)
+    }
+    .map_err(|e| format!("Failed to read PDF layout: {}", e))?;
+
+    let pages: Vec<Vec<Vec<Segment>>> = collector
+        .pages
+        .into_iter()
+        .map(|glyphs| group_lines(glyphs).into_iter().map(segment_line).collect())
+        .collect();
+
+    Ok(build_table(pages))
+}
+
+fn build_table(pages: Vec<Vec<Vec<Segment>>>) -> Option<PdfTable> {
+    let mut bounds: Option<Vec<ColumnBounds>> = None;
+    let mut rows: Vec<Vec<String>> = Vec::new();
+
+    for lines in pages {
+        // Look for the header again on every page; fall back to the previous page's columns
+        // when the table simply continues without repeating it.
+        let header_index = match &bounds {
+            None => find_header(&lines),
+            Some(known) => find_repeated_header(&lines, known),
+        };
+
+        let data_start = match header_index {
+            Some(index) => {
+                let mut page_bounds: Vec<ColumnBounds> = lines[index]
+                    .iter()
+                    .map(|segment| ColumnBounds {
+                        label: segment.text.clone(),
+                        start: segment.start,
+                        end: segment.end,
+                    })
+                    .collect();
+                // Keep the labels from the first page so repeated headers can't rename columns
+                if let Some(known) = &bounds {
+                    for (column, previous) in page_bounds.iter_mut().zip(known) {
+                        column.label = previous.label.clone();
+                    }
+                }
+                bounds = Some(page_bounds);
+                index + 1
+            }
+            None if bounds.is_some() => 0,
+            None => continue,
+        };
+
+        let Some(columns) = bounds.as_mut() else {
+            continue;
+        };
+
+        for line in &lines[data_start..] {
+            if is_page_footer(line) {
+                continue;
+            }
+            let cells = assign_cells(line, columns);
+            let filled = cells.iter().filter(|c| !c.is_empty()).count();
+            if filled == 0 {
+                continue;
+            }
+
+            let is_continuation = cells[0].is_empty() && filled * 2 <= columns.len();
+            match rows.last_mut() {
+                Some(previous) if is_continuation => {
+                    for (target, extra) in previous.iter_mut().zip(cells) {
+                        if extra.is_empty() {
+                            continue;
+                        }
+                        if !target.is_empty() {
+                            target.push(' ');
+                        }
+                        target.push_str(&extra);
+                    }
+                }
+                _ => rows.push(cells),
+            }
+        }
+    }
+
+    let columns = bounds?;
+    Some(PdfTable {
+        columns: columns.into_iter().map(|c| c.label).collect(),
+        rows,
+    })
+}
+
+/// Groups glyphs into lines sorted top to bottom, each line sorted left to right.
+fn group_lines(mut glyphs: Vec<Glyph>) -> Vec<Vec<Glyph>> {
+    glyphs.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+
+    let mut lines: Vec<Vec<Glyph>> = Vec::new();
+    for glyph in glyphs {
+        match lines.last_mut() {
+            Some(line) if (glyph.y - line[0].y).abs() <= line[0].font_size * LINE_TOLERANCE_EM => {
+                line.push(glyph)
+            }
+            _ => lines.push(vec![glyph]),
+        }
+    }
+
+    for line in &mut lines {
+        line.sort_by(|a, b| a.x.total_cmp(&b.x));
+    }
+    lines
+}
+
+/// Splits a line into cells wherever the gap between glyphs is wider than a column gutter.
+fn segment_line(line: Vec<Glyph>) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut last_end = f64::MIN;
+    let mut last_size = 0.0;
+
+    for glyph in line {
+        if glyph.text.trim().is_empty() {
+            continue;
+        }
+        let gap = glyph.x - last_end;
+        let starts_new = segments.is_empty() || gap > last_size * COLUMN_GAP_EM;
+        if starts_new {
+            segments.push(Segment {
+                start: glyph.x,
+                end: glyph.end,
+                text: glyph.text.clone(),
+            });
+        } else if let Some(segment) = segments.last_mut() {
+            // Anything wider than a hairline between glyphs is a word break
+            if gap > last_size * 0.1 {
+                segment.text.push(' ');
+            }
+            segment.text.push_str(&glyph.text);
+            segment.end = segment.end.max(glyph.end);
+        }
+        last_end = glyph.end;
+        last_size = glyph.font_size;
+    }
+
+    for segment in &mut segments {
+        segment.text = segment.text.trim().to_string();
+    }
+    segments
+}
+
+/// Picks the header of the first page: the line with the most purely textual cells.
+fn find_header(lines: &[Vec<Segment>]) -> Option<usize> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.len() >= MIN_HEADER_COLUMNS && line.iter().all(is_label))
+        .fold(
+            None,
+            |best: Option<(usize, usize)>, (index, line)| match best {
+                Some((_, count)) if count >= line.len() => best,
+                _ => Some((index, line.len())),
+            },
+        )
+        .map(|(index, _)| index)
+}
+
+/// Finds a header on a following page by matching at least half of the known column labels.
+fn find_repeated_header(lines: &[Vec<Segment>], known: &[ColumnBounds]) -> Option<usize> {
+    lines.iter().position(|line| {
+        if line.len() < MIN_HEADER_COLUMNS || !line.iter().all(is_label) {
+            return false;
+        }
+        let matching = line
+            .iter()
+            .filter(|segment| {
+                let text = normalize_label(&segment.text);
+                known
+                    .iter()
+                    .any(|column| normalize_label(&column.label) == text)
+            })
+            .count();
+        matching * 2 >= known.len()
+    })
+}
+
+fn is_label(segment: &Segment) -> bool {
+    let has_letters = segment.text.chars().any(|c| c.is_alphabetic());
+    let digits = segment.text.chars().filter(|c| c.is_ascii_digit()).count();
+    has_letters && digits * 2 < segment.text.chars().count()
+}
+
+fn normalize_label(label: &str) -> String {
+    label
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+fn is_page_footer(line: &[Segment]) -> bool {
+    line.len() == 1 && {
+        let text = line[0].text.to_lowercase();
+        text.starts_with("page ") && text.chars().any(|c| c.is_ascii_digit())
+    }
+}
+
+/// Assigns every segment of a line to the column it overlaps the most, or to the nearest column
+/// when it overlaps none, and widens the column so later pages line up with it.
+fn assign_cells(line: &[Segment], columns: &mut [ColumnBounds]) -> Vec<String> {
+    let mut cells = vec![String::new(); columns.len()];
+
+    for segment in line {
+        let best_overlap = columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| (index, segment.overlap(column.start, column.end)))
+            .filter(|(_, overlap)| *overlap > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index);
+
+        let index = best_overlap.unwrap_or_else(|| {
+            columns
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| {
+                    let distance_a = (segment.center() - (a.start + a.end) / 2.0).abs();
+                    let distance_b = (segment.center() - (b.start + b.end) / 2.0).abs();
+                    distance_a.total_cmp(&distance_b)
+                })
+                .map(|(index, _)| index)
+                .unwrap_or(0)
+        });
+
+        // Widen the column without letting it run into its neighbours
+        let lower = if index > 0 {
+            columns[index - 1].end
+        } else {
+            f64::MIN
+        };
+        let upper = columns.get(index + 1).map_or(f64::MAX, |next| next.start);
+        let column = &mut columns[index];
+        column.start = column.start.min(segment.start).max(lower);
+        column.end = column.end.max(segment.end).min(upper);
+
+        let cell = &mut cells[index];
+        if !cell.is_empty() {
+            cell.push(' ');
+        }
+        cell.push_str(&segment.text);
+    }
+
+    cells
+}
@@ -0,0 +1,175 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Name of the Tauri event progress updates are emitted on.
+pub const PROGRESS_EVENT: &str = "manifest-progress";
+
+/// Error returned by a job that was cancelled through `cancel_manifest_job`.
+pub const CANCELLED: &str = "Cancelled";
+
+/// Emit at most one row update per this many rows, so large files don't flood the frontend.
+const REPORT_EVERY: usize = 250;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Opening,
+    Reading,
+    Writing,
+    Done,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProgressEvent {
+    pub job_id: String,
+    pub phase: Phase,
+    pub rows_processed: usize,
+    pub total_rows: Option<usize>,
+    pub percent: Option<f32>,
+}
+
+/// Cancellation flags of the running jobs, keyed by the job id the frontend chose.
+#[derive(Default)]
+pub struct JobRegistry {
+    jobs: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl JobRegistry {
+    pub fn start(&self, job_id: &str) -> Arc<AtomicBool> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.insert(job_id.to_string(), cancelled.clone());
+        }
+        cancelled
+    }
+
+    /// Flags a job as cancelled. Returns `false` if no job with that id is running.
+    pub fn cancel(&self, job_id: &str) -> bool {
+        match self.jobs.lock() {
+            Ok(jobs) => match jobs.get(job_id) {
+                Some(cancelled) => {
+                    cancelled.store(true, Ordering::Relaxed);
+                    true
+                }
+                None => false,
+            },
+            Err(_) => false,
+        }
+    }
+
+    pub fn finish(&self, job_id: &str) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.remove(job_id);
+        }
+    }
+}
+
+type Sink = Box<dyn Fn(&ProgressEvent) + Send>;
+
+/// Reports progress of a parse or write and checks for cancellation between rows.
+///
+/// The reporting itself is a callback, so the parser doesn't depend on the Tauri app handle.
+pub struct Progress {
+    job_id: String,
+    cancelled: Arc<AtomicBool>,
+    sink: Option<Sink>,
+    phase: Phase,
+    total_rows: Option<usize>,
+    rows_processed: usize,
+    last_reported: usize,
+}
+
+impl Progress {
+    pub fn new(
+        job_id: String,
+        cancelled: Arc<AtomicBool>,
+        sink: impl Fn(&ProgressEvent) + Send + 'static,
+    ) -> Self {
+        Self {
+            job_id,
+            cancelled,
+            sink: Some(Box::new(sink)),
+            phase: Phase::Opening,
+            total_rows: None,
+            rows_processed: 0,
+            last_reported: 0,
+        }
+    }
+
+    /// A progress that reports nothing and can't be cancelled.
+    pub fn none() -> Self {
+        Self {
+            job_id: String::new(),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            sink: None,
+            phase: Phase::Opening,
+            total_rows: None,
+            rows_processed: 0,
+            last_reported: 0,
+        }
+    }
+
+    /// Starts a new phase; `total_rows` is `None` when the row count isn't known up front.
+    pub fn phase(&mut self, phase: Phase, total_rows: Option<usize>) -> Result<(), String> {
+        self.check_cancelled()?;
+        self.phase = phase;
+        self.total_rows = total_rows;
+        self.rows_processed = 0;
+        self.last_reported = 0;
+        self.emit(0, total_rows.map(|_| 0.0));
+        Ok(())
+    }
+
+    /// Records that `rows_processed` rows of the current phase are done.
+    pub fn rows(&mut self, rows_processed: usize) -> Result<(), String> {
+        let percent = self
+            .total_rows
+            .filter(|total| *total > 0)
+            .map(|total| rows_processed as f32 / total as f32 * 100.0);
+        self.rows_with_percent(rows_processed, percent)
+    }
+
+    /// Like [`Progress::rows`], for readers that measure progress some other way (e.g. bytes read).
+    pub fn rows_with_percent(
+        &mut self,
+        rows_processed: usize,
+        percent: Option<f32>,
+    ) -> Result<(), String> {
+        self.rows_processed = rows_processed;
+        if rows_processed < self.last_reported + REPORT_EVERY {
+            return Ok(());
+        }
+        self.check_cancelled()?;
+        self.last_reported = rows_processed;
+        self.emit(rows_processed, percent.map(|percent| percent.min(100.0)));
+        Ok(())
+    }
+
+    /// Reports the job as finished with the row count of its last phase.
+    pub fn done(&mut self) {
+        self.phase = Phase::Done;
+        self.total_rows = Some(self.rows_processed);
+        self.emit(self.rows_processed, Some(100.0));
+    }
+
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(CANCELLED.to_string());
+        }
+        Ok(())
+    }
+
+    fn emit(&self, rows_processed: usize, percent: Option<f32>) {
+        if let Some(sink) = &self.sink {
+            sink(&ProgressEvent {
+                job_id: self.job_id.clone(),
+                phase: self.phase,
+                rows_processed,
+                total_rows: self.total_rows,
+                percent,
+            });
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use super::progress::{Phase, Progress};
+use po_tracker_share_lib::manifest::ManifestRow;
+use po_tracker_share_lib::summary::{GroupSummary, SummaryTotals, summarize};
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+
+const CURRENCY_FORMAT: &str = "$#,##0.00";
+const QUANTITY_FORMAT: &str = "#,##0";
+const PERCENT_FORMAT: &str = "0.00\"%\"";
+
+#[derive(Clone, Copy)]
+enum ColumnKind {
+    Text,
+    Quantity,
+    Currency,
+}
+
+struct Column {
+    header: &'static str,
+    field: &'static str,
+    kind: ColumnKind,
+    width: f64,
+}
+
+const COLUMNS: [Column; 13] = [
+    column("Item Number", "item_number", ColumnKind::Text, 16.0),
+    column("UPC", "upc", ColumnKind::Text, 16.0),
+    column("Description", "description", ColumnKind::Text, 40.0),
+    column("Case Pack", "case_pack", ColumnKind::Quantity, 11.0),
+    column("Cases", "cases", ColumnKind::Quantity, 9.0),
+    column("Mardens Cost", "mardens_cost", ColumnKind::Currency, 14.0),
+    column("Mardens Price", "mardens_price", ColumnKind::Currency, 14.0),
+    column("Comp Retail", "comp_retail", ColumnKind::Currency, 14.0),
+    column("Department", "department", ColumnKind::Text, 16.0),
+    column("Category", "category", ColumnKind::Text, 16.0),
+    column("Sub Category", "sub_category", ColumnKind::Text, 16.0),
+    column("Season", "season", ColumnKind::Text, 12.0),
+    column("Notes", "notes", ColumnKind::Text, 30.0),
+];
+
+const fn column(header: &'static str, field: &'static str, kind: ColumnKind, width: f64) -> Column {
+    Column {
+        header,
+        field,
+        kind,
+        width,
+    }
+}
+
+struct Formats {
+    header: Format,
+    text: Format,
+    quantity: Format,
+    currency: Format,
+    percent: Format,
+}
+
+impl Formats {
+    fn new() -> Self {
+        Self {
+            header: Format::new().set_bold(),
+            // Text format keeps Excel from dropping leading zeros on UPCs and item numbers
+            text: Format::new().set_num_format("@"),
+            quantity: Format::new().set_num_format(QUANTITY_FORMAT),
+            currency: Format::new().set_num_format(CURRENCY_FORMAT),
+            percent: Format::new().set_num_format(PERCENT_FORMAT),
+        }
+    }
+}
+
+/// Writes the normalized manifest rows to an `.xlsx` workbook at `path`.
+///
+/// The "Manifest" sheet has typed cells (text UPC/item columns, currency costs and prices),
+/// a frozen header row and an autofilter. A "Totals" sheet holds the PO summary overall and
+/// by department, category and season.
+pub fn write_manifest_xlsx(
+    mut rows: Vec<ManifestRow>,
+    path: &str,
+    progress: &mut Progress,
+) -> Result<(), String> {
+    let formats = Formats::new();
+    let mut workbook = Workbook::new();
+
+    let upc_issues: Vec<String> = rows
+        .iter_mut()
+        .map(|row| match row.normalize_upc() {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        })
+        .collect();
+
+    let sheet_error = |e: XlsxError| format!("Failed to write manifest sheet: {}", e);
+    progress.phase(Phase::Writing, Some(rows.len()))?;
+    let sheet = workbook.add_worksheet();
+    write_manifest_header(sheet, &formats).map_err(sheet_error)?;
+    for (index, row) in rows.iter().enumerate() {
+        write_manifest_row(sheet, index as u32 + 1, row, &upc_issues[index], &formats)
+            .map_err(sheet_error)?;
+        progress.rows(index + 1)?;
+    }
+    sheet.set_freeze_panes(1, 0).map_err(sheet_error)?;
+    sheet
+        .autofilter(0, 0, rows.len() as u32, COLUMNS.len() as u16)
+        .map_err(sheet_error)?;
+
+    let sheet = workbook.add_worksheet();
+    write_totals_sheet(sheet, &rows, &formats)
+        .map_err(|e| format!("Failed to write totals sheet: {}", e))?;
+
+    progress.check_cancelled()?;
+    workbook
+        .save(path)
+        .map_err(|e| format!("Failed to save workbook: {}", e))
+}
+
+fn write_manifest_header(sheet: &mut Worksheet, formats: &Formats) -> Result<(), XlsxError> {
+    sheet.set_name("Manifest")?;
+    let issue_column = COLUMNS.len() as u16;
+
+    for (index, column) in COLUMNS.iter().enumerate() {
+        let col = index as u16;
+        sheet.write_string_with_format(0, col, column.header, &formats.header)?;
+        sheet.set_column_width(col, column.width)?;
+    }
+    sheet.write_string_with_format(0, issue_column, "UPC Issue", &formats.header)?;
+    sheet.set_column_width(issue_column, 30)?;
+    Ok(())
+}
+
+fn write_manifest_row(
+    sheet: &mut Worksheet,
+    row_index: u32,
+    row: &ManifestRow,
+    upc_issue: &str,
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    let numbers = row.parse_numbers();
+
+    for (col, column) in COLUMNS.iter().enumerate() {
+        let col = col as u16;
+        let value = row.field(column.field).unwrap_or_default();
+        let number = match column.field {
+            "case_pack" => numbers.case_pack,
+            "cases" => numbers.cases,
+            "mardens_cost" => numbers.mardens_cost,
+            "mardens_price" => numbers.mardens_price,
+            "comp_retail" => numbers.comp_retail,
+            _ => None,
+        }
+        .and_then(|number| f64::try_from(number).ok());
+
+        // Values that don't parse are kept as text so nothing the vendor sent is lost
+        match (column.kind, number) {
+            (ColumnKind::Quantity, Some(number)) => {
+                sheet.write_number_with_format(row_index, col, number, &formats.quantity)?;
+            }
+            (ColumnKind::Currency, Some(number)) => {
+                sheet.write_number_with_format(row_index, col, number, &formats.currency)?;
+            }
+            _ if value.is_empty() => {}
+            _ => {
+                sheet.write_string_with_format(row_index, col, value, &formats.text)?;
+            }
+        }
+    }
+
+    if !upc_issue.is_empty() {
+        sheet.write_string(row_index, COLUMNS.len() as u16, upc_issue)?;
+    }
+    Ok(())
+}
+
+fn write_totals_sheet(
+    sheet: &mut Worksheet,
+    rows: &[ManifestRow],
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    sheet.set_name("Totals")?;
+    let summary = summarize(rows);
+
+    sheet.set_column_width(0, 24)?;
+    for col in 1..=6 {
+        sheet.set_column_width(col, 16)?;
+    }
+
+    let mut row = 0;
+    write_totals_header(sheet, row, "PO Totals", formats)?;
+    row += 1;
+    write_totals_row(sheet, row, "All Lines", &summary.totals, formats)?;
+    row += 2;
+
+    for (title, groups) in [
+        ("Department", &summary.by_department),
+        ("Category", &summary.by_category),
+        ("Season", &summary.by_season),
+    ] {
+        row = write_group_section(sheet, row, title, groups, formats)?;
+        row += 1;
+    }
+
+    sheet.set_freeze_panes(1, 0)?;
+    Ok(())
+}
+
+fn write_group_section(
+    sheet: &mut Worksheet,
+    mut row: u32,
+    title: &str,
+    groups: &[GroupSummary],
+    formats: &Formats,
+) -> Result<u32, XlsxError> {
+    write_totals_header(sheet, row, title, formats)?;
+    row += 1;
+    for group in groups {
+        write_totals_row(sheet, row, &group.key, &group.totals, formats)?;
+        row += 1;
+    }
+    Ok(row)
+}
+
+fn write_totals_header(
+    sheet: &mut Worksheet,
+    row: u32,
+    title: &str,
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    let headers = [
+        title,
+        "Lines",
+        "Units",
+        "Extended Cost",
+        "Extended Retail",
+        "Extended Comp Retail",
+        "Margin %",
+    ];
+    for (col, header) in headers.iter().enumerate() {
+        sheet.write_string_with_format(row, col as u16, *header, &formats.header)?;
+    }
+    Ok(())
+}
+
+fn write_totals_row(
+    sheet: &mut Worksheet,
+    row: u32,
+    label: &str,
+    totals: &SummaryTotals,
+    formats: &Formats,
+) -> Result<(), XlsxError> {
+    let to_f64 = |value| f64::try_from(value).unwrap_or_default();
+
+    sheet.write_string_with_format(row, 0, label, &formats.text)?;
+    sheet.write_number_with_format(row, 1, totals.lines as f64, &formats.quantity)?;
+    sheet.write_number_with_format(row, 2, to_f64(totals.units), &formats.quantity)?;
+    sheet.write_number_with_format(row, 3, to_f64(totals.extended_cost), &formats.currency)?;
+    sheet.write_number_with_format(row, 4, to_f64(totals.extended_retail), &formats.currency)?;
+    sheet.write_number_with_format(
+        row,
+        5,
+        to_f64(totals.extended_comp_retail),
+        &formats.currency,
+    )?;
+    if let Some(margin) = totals.margin_percent {
+        sheet.write_number_with_format(row, 6, to_f64(margin), &formats.percent)?;
+    }
+    Ok(())
+}
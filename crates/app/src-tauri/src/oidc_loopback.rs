@@ -0,0 +1,108 @@
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Url};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// How long the user has to finish signing in in the browser.
+const REDIRECT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const REDIRECT_PATH: &str = "/callback";
+const REDIRECT_EVENT: &str = "oidc-redirect";
+const DONE_PAGE: &str = "<!DOCTYPE html><html><head><title>PO Tracker</title></head><body style=\"font-family: sans-serif; text-align: center; margin-top: 4rem\"><h1>You're signed in</h1><p>You can close this window and return to PO Tracker.</p></body></html>";
+const FAILED_PAGE: &str = "<!DOCTYPE html><html><head><title>PO Tracker</title></head><body style=\"font-family: sans-serif; text-align: center; margin-top: 4rem\"><h1>Sign in failed</h1><p>Return to PO Tracker to try again.</p></body></html>";
+
+/// What the identity provider sent the browser back with.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OidcRedirect {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+impl OidcRedirect {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            error: Some(message.into()),
+            ..Self::default()
+        }
+    }
+}
+
+/// Listens on a free loopback port for the identity provider to send the browser back to
+/// (RFC 8252), and returns the redirect URI to sign in with. What the browser brings back is
+/// emitted as an `oidc-redirect` event.
+#[tauri::command]
+pub async fn start_oidc_loopback(app: AppHandle) -> Result<String, String> {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .map_err(|e| format!("Failed to listen for the sign-in redirect: {}", e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+
+    tauri::async_runtime::spawn(async move {
+        let redirect =
+            match tokio::time::timeout(REDIRECT_TIMEOUT, wait_for_redirect(&listener)).await {
+                Ok(Ok(redirect)) => redirect,
+                Ok(Err(e)) => OidcRedirect::error(format!("Failed to receive the sign-in: {}", e)),
+                Err(_) => OidcRedirect::error("Timed out waiting for the sign-in to finish"),
+            };
+        if let Err(e) = app.emit(REDIRECT_EVENT, redirect) {
+            eprintln!("Failed to emit oidc-redirect event: {}", e);
+        }
+    });
+
+    Ok(format!("http://127.0.0.1:{}{}", port, REDIRECT_PATH))
+}
+
+/// Answers requests until the browser comes back to the redirect path.
+async fn wait_for_redirect(listener: &TcpListener) -> std::io::Result<OidcRedirect> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let mut buffer = vec![0u8; 8192];
+        let read = stream.read(&mut buffer).await?;
+        let request = String::from_utf8_lossy(&buffer[..read]);
+
+        // The request line looks like `GET /callback?code=...&state=... HTTP/1.1`.
+        let target = request
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .unwrap_or_default();
+        let url = Url::parse(&format!("http://127.0.0.1{}", target)).ok();
+        let Some(url) = url.filter(|url| url.path() == REDIRECT_PATH) else {
+            // Browsers ask for a favicon as well.
+            stream
+                .write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await?;
+            continue;
+        };
+
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        let redirect = OidcRedirect {
+            code: param("code"),
+            state: param("state"),
+            error: param("error_description").or_else(|| param("error")),
+        };
+        let page = if redirect.error.is_some() {
+            FAILED_PAGE
+        } else {
+            DONE_PAGE
+        };
+        stream
+            .write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    page.len(),
+                    page
+                )
+                .as_bytes(),
+            )
+            .await?;
+        return Ok(redirect);
+    }
+}
@@ -0,0 +1,32 @@
+use po_tracker_share_lib::edi::{EdiConfig, render_850};
+use po_tracker_share_lib::po_pdf::render_po_pdf;
+use po_tracker_share_lib::save_file::SaveFile;
+
+/// Writes the PO as an X12 850 to `path` and returns the path.
+#[tauri::command]
+pub async fn export_edi_850(
+    item: SaveFile,
+    config: EdiConfig,
+    path: String,
+) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        let created = chrono::Local::now().naive_local();
+        let edi = render_850(&item, &item.lines(), &config, created).map_err(|e| e.to_string())?;
+        std::fs::write(&path, edi).map_err(|e| format!("Failed to write EDI file: {}", e))?;
+        Ok(path)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Writes the printable PO as a PDF to `path` and returns the path.
+#[tauri::command]
+pub async fn export_po_pdf(item: SaveFile, path: String) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        let pdf = render_po_pdf(&item, &item.lines());
+        std::fs::write(&path, pdf).map_err(|e| format!("Failed to write PDF file: {}", e))?;
+        Ok(path)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
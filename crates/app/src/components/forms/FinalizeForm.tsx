@@ -4,14 +4,22 @@ import {useNavigate} from "react-router-dom";
 import {save} from "@tauri-apps/plugin-dialog";
 import {InfoCard} from "../InfoCard.tsx";
 import {useFormDataStore} from "../../stores/useFormDataStore.ts";
-import {REQUIRED_FIELDS} from "../../types/manifest.ts";
+import {REQUIRED_FIELDS, ValidationReport} from "../../types/manifest.ts";
 import {UploadFileType} from "./po-information";
 import {getLocalTimeZone} from "@internationalized/date";
+import {invoke} from "@tauri-apps/api/core";
+import {useState} from "react";
+import {useDepartments} from "../../hooks/useFieldOptions.ts";
+import {ManifestValidation, ValidationReportModal} from "./ValidationReportModal.tsx";
 
 export function FinalizeForm()
 {
     const {uploadForm, manifestMappings, saveToFile, saveCurrentFile, currentFilePath, isSaving} = useFormDataStore();
     const navigate = useNavigate();
+    const departmentOptions = useDepartments();
+    const [validations, setValidations] = useState<ManifestValidation[]>([]);
+    const [isValidating, setIsValidating] = useState(false);
+    const [showReport, setShowReport] = useState(false);
 
     // Validate that all required manifest fields are mapped
     const validateMappings = (): boolean =>
@@ -38,7 +46,7 @@ export function FinalizeForm()
         return !hasErrors;
     };
 
-    // Handle save button click
+    // Check every mapped row and show the report before anything is saved
     const handleSave = async () =>
     {
         // Validate all required mappings first
@@ -47,6 +55,73 @@ export function FinalizeForm()
             return;
         }
 
+        setIsValidating(true);
+        try
+        {
+            const results: ManifestValidation[] = [];
+            for (const mapping of manifestMappings)
+            {
+                const report = await invoke<ValidationReport>("validate_manifest_rows", {
+                    path: mapping.path,
+                    mappings: mapping.mappings,
+                    context: {departments: departmentOptions.map(d => d.name)}
+                });
+                results.push({filename: mapping.filename, report});
+            }
+            setValidations(results);
+            setShowReport(true);
+        } catch (error)
+        {
+            const errorMessage = error instanceof Error ? error.message : String(error);
+            addToast({
+                title: "Validation Error",
+                description: errorMessage,
+                color: "danger"
+            });
+        } finally
+        {
+            setIsValidating(false);
+        }
+    };
+
+    const handleExportReport = async (validation: ManifestValidation) =>
+    {
+        const path = await save({
+            filters: [{
+                name: "CSV Files",
+                extensions: ["csv"]
+            }],
+            defaultPath: `${validation.filename.replace(/\.[^.]+$/, "")}_validation.csv`
+        });
+
+        if (!path)
+        {
+            return;
+        }
+
+        try
+        {
+            await invoke("export_validation_report", {report: validation.report, path});
+            addToast({
+                title: "Success",
+                description: "Validation report exported",
+                color: "success"
+            });
+        } catch (error)
+        {
+            const errorMessage = error instanceof Error ? error.message : String(error);
+            addToast({
+                title: "Export Error",
+                description: errorMessage,
+                color: "danger"
+            });
+        }
+    };
+
+    // Save once the buyer has seen the validation report
+    const confirmSave = async () =>
+    {
+        setShowReport(false);
         try
         {
             if (currentFilePath)
@@ -217,13 +292,21 @@ export function FinalizeForm()
                     size="lg"
                     endContent={isSaving ? <Spinner size="sm" color="white"/> : <Icon icon="mdi:content-save"/>}
                     onPress={handleSave}
-                    isDisabled={!allMapped || isSaving}
-                    isLoading={isSaving}
+                    isDisabled={!allMapped || isSaving || isValidating}
+                    isLoading={isSaving || isValidating}
                 >
-                    {isSaving ? "Saving..." : (currentFilePath ? "Update Purchase Order" : "Save Purchase Order")}
+                    {isSaving ? "Saving..." : isValidating ? "Validating..." : (currentFilePath ? "Update Purchase Order" : "Save Purchase Order")}
                 </Button>
             </div>
 
+            <ValidationReportModal
+                isOpen={showReport}
+                validations={validations}
+                onExport={handleExportReport}
+                onSave={confirmSave}
+                onClose={() => setShowReport(false)}
+            />
+
             {/* Saving Progress Modal */}
             <Modal
                 isOpen={isSaving}
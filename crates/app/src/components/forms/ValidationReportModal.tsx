@@ -0,0 +1,103 @@
+import {Button, Chip, Modal, ModalBody, ModalContent, ModalFooter, ModalHeader} from "@heroui/react";
+import {Icon} from "@iconify-icon/react";
+import {ValidationReport} from "../../types/manifest.ts";
+
+export type ManifestValidation = {
+    filename: string;
+    report: ValidationReport;
+}
+
+type ValidationReportModalProps = {
+    isOpen: boolean;
+    validations: ManifestValidation[];
+    onExport: (validation: ManifestValidation) => void;
+    onSave: () => void;
+    onClose: () => void;
+}
+
+export function ValidationReportModal({isOpen, validations, onExport, onSave, onClose}: ValidationReportModalProps)
+{
+    const errorCount = validations.reduce((acc, v) => acc + v.report.error_count, 0);
+    const warningCount = validations.reduce((acc, v) => acc + v.report.warning_count, 0);
+
+    return (
+        <Modal
+            isOpen={isOpen}
+            onClose={onClose}
+            size="4xl"
+            scrollBehavior="inside"
+            radius="none"
+            classNames={{
+                base: "bg-white border-2 border-primary"
+            }}
+        >
+            <ModalContent>
+                <ModalHeader className="flex flex-row items-center gap-3 font-headers uppercase">
+                    <span>Manifest Validation</span>
+                    <Chip color={errorCount > 0 ? "danger" : "success"} size="sm">{errorCount} errors</Chip>
+                    <Chip color={warningCount > 0 ? "warning" : "success"} size="sm">{warningCount} warnings</Chip>
+                </ModalHeader>
+                <ModalBody>
+                    <div className="flex flex-col gap-6">
+                        {validations.map((validation) => (
+                            <div key={validation.filename} className="flex flex-col gap-2">
+                                <div className="flex items-center justify-between">
+                                    <div className="flex items-center gap-3">
+                                        <Icon icon="mdi:file-excel" className="text-2xl text-primary"/>
+                                        <div className="flex flex-col">
+                                            <span className="font-headers font-bold text-sm">{validation.filename}</span>
+                                            <span className="font-text text-xs text-gray-600">
+                                                {validation.report.rows_checked} rows checked
+                                            </span>
+                                        </div>
+                                    </div>
+                                    <Button
+                                        radius="none"
+                                        size="sm"
+                                        variant="light"
+                                        startContent={<Icon icon="mdi:file-export"/>}
+                                        onPress={() => onExport(validation)}
+                                        isDisabled={validation.report.issues.length === 0}
+                                    >
+                                        Export Report
+                                    </Button>
+                                </div>
+                                {validation.report.issues.length === 0 ? (
+                                    <p className="font-text text-sm text-gray-500 py-2">No issues found</p>
+                                ) : (
+                                    <div className="flex flex-col border-2 border-primary/20">
+                                        {validation.report.issues.map((issue, index) => (
+                                            <div
+                                                key={index}
+                                                className="grid grid-cols-[4rem_8rem_1fr] items-center gap-3 px-3 py-2 font-text text-sm odd:bg-secondary/10"
+                                            >
+                                                <span className="text-gray-600">Row {issue.row}</span>
+                                                <Chip color={issue.severity === "error" ? "danger" : "warning"} size="sm">
+                                                    {issue.field}
+                                                </Chip>
+                                                <span>{issue.message}</span>
+                                            </div>
+                                        ))}
+                                    </div>
+                                )}
+                            </div>
+                        ))}
+                    </div>
+                </ModalBody>
+                <ModalFooter>
+                    <Button radius="none" variant="light" onPress={onClose}>
+                        Back to Editing
+                    </Button>
+                    <Button
+                        radius="none"
+                        color={errorCount > 0 ? "danger" : "primary"}
+                        endContent={<Icon icon="mdi:content-save"/>}
+                        onPress={onSave}
+                    >
+                        {errorCount > 0 ? "Save Anyway" : "Save"}
+                    </Button>
+                </ModalFooter>
+            </ModalContent>
+        </Modal>
+    );
+}
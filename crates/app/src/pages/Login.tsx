@@ -2,7 +2,7 @@ import {addToast, Button, Form, Input, Link, Spinner} from "@heroui/react";
 import {Icon} from "@iconify-icon/react";
 import {FormEvent, useEffect, useState} from "react";
 import {Navigate, useNavigate} from "react-router-dom";
-import {useAuthentication} from "../providers/AuthenticationProvider.tsx";
+import {LoginProviders, useAuthentication} from "../providers/AuthenticationProvider.tsx";
 import {useRemoteServerConnection} from "../providers/RemoteServerConnectionProvider.tsx";
 
 const validateEmail = (value: string) =>
@@ -18,14 +18,20 @@ const validateEmail = (value: string) =>
 export function Login()
 {
     const navigate = useNavigate();
-    const {login, isLoading: authLoading, isAuthenticated} = useAuthentication();
+    const {login, loginWithOidc, getLoginProviders, isLoading: authLoading, isAuthenticated} = useAuthentication();
 
     const [email, setEmail] = useState("");
     const [password, setPassword] = useState("");
     const [showPassword, setShowPassword] = useState(false);
     const [isSubmitting, setIsSubmitting] = useState(false);
+    const [providers, setProviders] = useState<LoginProviders | null>(null);
     const {isConnected: isConnectedToRemote} = useRemoteServerConnection();
 
+    useEffect(() =>
+    {
+        getLoginProviders().then(setProviders).catch(() => setProviders(null));
+    }, [getLoginProviders]);
+
     // Redirect authenticated users to main app
     useEffect(() =>
     {
@@ -78,6 +84,29 @@ export function Login()
         }
     };
 
+    const handleOidcLogin = async () =>
+    {
+        setIsSubmitting(true);
+        try
+        {
+            const user = await loginWithOidc();
+            if (user)
+            {
+                navigate("/po-number", {replace: true});
+            }
+        } catch (err)
+        {
+            addToast({
+                title: "Login Error",
+                description: err instanceof Error ? err.message : "An unexpected error occurred",
+                color: "danger"
+            });
+        } finally
+        {
+            setIsSubmitting(false);
+        }
+    };
+
     if (authLoading)
     {
         return (
@@ -192,6 +221,23 @@ export function Login()
                         {isSubmitting ? "Signing In..." : "Sign In"}
                     </Button>
 
+                    {/* Single Sign-On Button */}
+                    {providers?.oidc && (
+                        <Button
+                            radius="none"
+                            variant="bordered"
+                            color="primary"
+                            size="lg"
+                            className="font-headers font-bold text-lg uppercase"
+                            isDisabled={isSubmitting}
+                            startContent={<Icon icon="tabler:building" width={20} height={20}/>}
+                            onPress={handleOidcLogin}
+                            fullWidth
+                        >
+                            Sign in with {providers.oidc_name || "Single Sign-On"}
+                        </Button>
+                    )}
+
                     {/* Register Link */}
                     <div className="flex justify-center mt-4">
                         <p className="font-text text-foreground/70">
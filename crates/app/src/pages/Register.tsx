@@ -1,8 +1,8 @@
-import {addToast, Button, Form, Input, Link, Select, SelectItem, Spinner} from "@heroui/react";
+import {addToast, Button, Form, Input, Link, Spinner} from "@heroui/react";
 import {Icon} from "@iconify-icon/react";
 import {FormEvent, useEffect, useState} from "react";
 import {Navigate, useNavigate} from "react-router-dom";
-import {useAuthentication, UserRegistrationRequest, UserRole} from "../providers/AuthenticationProvider.tsx";
+import {RegistrationResult, useAuthentication, UserRegistrationRequest} from "../providers/AuthenticationProvider.tsx";
 import {useRemoteServerConnection} from "../providers/RemoteServerConnectionProvider.tsx";
 
 const validateEmail = (value: string) =>
@@ -18,7 +18,7 @@ const validateEmail = (value: string) =>
 const validatePassword = (value: string) =>
 {
     if (!value) return "Password is required";
-    if (value.length < 8) return "Password must be at least 8 characters";
+    if (value.length < 10) return "Password must be at least 10 characters";
     return null;
 };
 
@@ -32,11 +32,10 @@ export function Register()
     const [email, setEmail] = useState("");
     const [password, setPassword] = useState("");
     const [confirmPassword, setConfirmPassword] = useState("");
-    const [role, setRole] = useState<UserRole>(UserRole.Buyer);
     const [showPassword, setShowPassword] = useState(false);
     const [showConfirmPassword, setShowConfirmPassword] = useState(false);
     const [isSubmitting, setIsSubmitting] = useState(false);
-    const [success, setSuccess] = useState(false);
+    const [result, setResult] = useState<RegistrationResult | null>(null);
     const {isConnected: isConnectedToRemote} = useRemoteServerConnection();
 
     // Redirect authenticated users to main app
@@ -77,12 +76,10 @@ export function Register()
                 firstName: (formData.firstName as string).trim(),
                 lastName: (formData.lastName as string).trim(),
                 email: (formData.email as string).trim(),
-                password: formData.password as string,
-                role
+                password: formData.password as string
             };
 
-            await register(userData);
-            setSuccess(true);
+            setResult(await register(userData));
         } catch (err)
         {
             addToast({
@@ -106,7 +103,7 @@ export function Register()
     }
 
     // Success state
-    if (success)
+    if (result)
     {
         return (
             <div className="flex flex-col items-center justify-center w-full min-h-[calc(100vh-2rem)] p-8">
@@ -120,7 +117,7 @@ export function Register()
                         Registration Successful
                     </h1>
                     <p className="font-text text-lg text-foreground/70 mb-8">
-                        Your account has been created. Please check your email to confirm your account before signing in.
+                        {result.message}
                     </p>
                     <Button
                         radius="none"
@@ -233,42 +230,6 @@ export function Register()
                         isDisabled={isSubmitting}
                     />
 
-                    {/* Role Select */}
-                    <Select
-                        name="role"
-                        label="Role"
-                        labelPlacement="outside"
-                        radius="none"
-                        size="lg"
-                        placeholder="Select your role"
-                        selectedKeys={[role.toString()]}
-                        onSelectionChange={keys =>
-                        {
-                            const key = [...keys][0] as string | undefined;
-                            if (key !== undefined)
-                            {
-                                setRole(parseInt(key) as UserRole);
-                            }
-                        }}
-                        isRequired
-                        classNames={{
-                            label: "font-headers font-bold text-sm uppercase",
-                            innerWrapper: "font-text text-lg",
-                            trigger: "border-2 border-primary/50 hover:border-primary transition-colors",
-                            popoverContent: "rounded-none"
-                        }}
-                        listboxProps={{
-                            itemClasses: {
-                                base: "rounded-none"
-                            }
-                        }}
-                        isDisabled={isSubmitting}
-                    >
-                        <SelectItem key={UserRole.Buyer.toString()}>Buyer</SelectItem>
-                        <SelectItem key={UserRole.Warehouse.toString()}>Warehouse</SelectItem>
-                        <SelectItem key={UserRole.Admin.toString()}>Admin</SelectItem>
-                    </Select>
-
                     {/* Password Input */}
                     <Input
                         name="password"
@@ -277,7 +238,7 @@ export function Register()
                         labelPlacement="outside"
                         radius="none"
                         size="lg"
-                        placeholder="At least 8 characters"
+                        placeholder="At least 10 characters"
                         value={password}
                         onValueChange={setPassword}
                         autoComplete="one-time-code"
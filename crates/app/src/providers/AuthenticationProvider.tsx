@@ -1,6 +1,9 @@
 import {createContext, ReactNode, useCallback, useContext, useEffect, useState} from "react";
 import {getApiRoute} from "../api_route.ts";
 import {fetch} from "@tauri-apps/plugin-http";
+import {open} from "@tauri-apps/plugin-shell";
+import {invoke} from "@tauri-apps/api/core";
+import {listen, UnlistenFn} from "@tauri-apps/api/event";
 
 // ============== Types ==============
 
@@ -24,7 +27,12 @@ export type UserRegistrationRequest = {
     lastName: string;
     email: string;
     password: string;
-    role: UserRole;
+}
+
+export type RegistrationResult = {
+    message: string;
+    // Self-registered users can't sign in until an administrator approves them
+    pending_approval: boolean;
 }
 
 type LoginResponse = {
@@ -32,6 +40,27 @@ type LoginResponse = {
     token_type: string;
 }
 
+// Which ways of signing in the server has turned on
+export type LoginProviders = {
+    password: boolean;
+    directory: boolean;
+    oidc: boolean;
+    // Shown on the single sign-on button
+    oidc_name?: string | null;
+}
+
+type OidcAuthorization = {
+    authorization_url: string;
+    state: string;
+}
+
+// What the identity provider sent the browser back to the loopback listener with
+type OidcRedirect = {
+    code?: string;
+    state?: string;
+    error?: string;
+}
+
 type MeResponse = {
     user: number;
 }
@@ -115,7 +144,9 @@ interface AuthenticationContextType
     isLoading: boolean;
     currentUser: User | null;
     login: (email: string, password: string) => Promise<User | undefined>;
-    register: (user: UserRegistrationRequest) => Promise<void>;
+    loginWithOidc: () => Promise<User | undefined>;
+    getLoginProviders: () => Promise<LoginProviders>;
+    register: (user: UserRegistrationRequest) => Promise<RegistrationResult>;
     logout: () => void;
     me: () => Promise<User | undefined>;
     getToken: () => string | null;
@@ -224,26 +255,120 @@ export function AuthenticationProvider({children}: { children: ReactNode })
                 throw new Error(errorData.message || "Login failed");
             }
 
-            const data: LoginResponse = await response.json();
-            storeToken(data.token);
+            return applyLoginResponse(await response.json());
+        } catch (error)
+        {
+            console.error("Login error:", error);
+            throw error;
+        }
+    }, [apiUrl]);
 
-            const user = getUserFromToken(data.token);
-            if (user)
+    // Signs in with the identity provider in the system browser, which comes back to a
+    // listener on the loopback address
+    const loginWithOidc = useCallback(async (): Promise<User | undefined> =>
+    {
+        if (!apiUrl) return undefined;
+
+        let unlisten: UnlistenFn | undefined;
+        try
+        {
+            // Listen before the listener starts, so a quick redirect isn't missed
+            let resolveRedirect: (redirect: OidcRedirect) => void = () => {};
+            const redirected = new Promise<OidcRedirect>(resolve => resolveRedirect = resolve);
+            unlisten = await listen<OidcRedirect>("oidc-redirect", event => resolveRedirect(event.payload));
+            const redirectUri = await invoke<string>("start_oidc_loopback");
+
+            const authorizeResponse = await fetch(`${apiUrl}/auth/oidc/authorize`, {
+                method: "POST",
+                headers: {
+                    "Content-Type": "application/json"
+                },
+                danger: {
+                    acceptInvalidCerts: true,
+                    acceptInvalidHostnames: true
+                },
+                body: JSON.stringify({redirect_uri: redirectUri})
+            });
+            if (!authorizeResponse.ok)
             {
-                setCurrentUser(user);
-                setIsAuthenticated(true);
-                return user;
+                const errorData = await authorizeResponse.json().catch(() => ({}));
+                throw new Error(errorData.error || "Failed to start single sign-on");
             }
+            const authorization: OidcAuthorization = await authorizeResponse.json();
 
-            return undefined;
+            await open(authorization.authorization_url);
+            const redirect = await redirected;
+            if (redirect.error || !redirect.code || redirect.state !== authorization.state)
+            {
+                throw new Error(redirect.error || "The sign-in didn't come back as expected, please try again.");
+            }
+
+            const response = await fetch(`${apiUrl}/auth/login/oidc`, {
+                method: "POST",
+                headers: {
+                    "Content-Type": "application/json"
+                },
+                danger: {
+                    acceptInvalidCerts: true,
+                    acceptInvalidHostnames: true
+                },
+                body: JSON.stringify({code: redirect.code, state: redirect.state})
+            });
+            if (!response.ok)
+            {
+                const errorData = await response.json().catch(() => ({}));
+                throw new Error(errorData.error || "Login failed");
+            }
+
+            return applyLoginResponse(await response.json());
         } catch (error)
         {
-            console.error("Login error:", error);
+            console.error("Single sign-on error:", error);
             throw error;
+        } finally
+        {
+            unlisten?.();
         }
     }, [apiUrl]);
 
-    const register = useCallback(async (userData: UserRegistrationRequest): Promise<void> =>
+    const getLoginProviders = useCallback(async (): Promise<LoginProviders> =>
+    {
+        const fallback = {password: true, directory: false, oidc: false};
+        if (!apiUrl) return fallback;
+
+        const response = await fetch(`${apiUrl}/auth/providers`, {
+            method: "GET",
+            danger: {
+                acceptInvalidCerts: true,
+                acceptInvalidHostnames: true
+            }
+        });
+        if (!response.ok) return fallback;
+        return await response.json();
+    }, [apiUrl]);
+
+    // Shared by the password and single sign-on logins, which answer the same way
+    function applyLoginResponse(data: LoginResponse | { challenge_token: string }): User | undefined
+    {
+        if ("challenge_token" in data)
+        {
+            // The app can't take a second factor yet
+            throw new Error("This account uses two-factor authentication. Please sign in on the web dashboard.");
+        }
+        storeToken(data.token);
+
+        const user = getUserFromToken(data.token);
+        if (user)
+        {
+            setCurrentUser(user);
+            setIsAuthenticated(true);
+            return user;
+        }
+
+        return undefined;
+    }
+
+    const register = useCallback(async (userData: UserRegistrationRequest): Promise<RegistrationResult> =>
     {
         if (!apiUrl) throw new Error("API not initialized");
 
@@ -262,18 +387,18 @@ export function AuthenticationProvider({children}: { children: ReactNode })
                     first_name: userData.firstName,
                     last_name: userData.lastName,
                     email: userData.email,
-                    password: userData.password,
-                    role: userData.role
+                    password: userData.password
                 })
             });
 
             if (!response.ok)
             {
                 const errorData = await response.json().catch(() => ({}));
-                throw new Error(errorData.message || "Registration failed");
+                throw new Error(errorData.error || errorData.message || "Registration failed");
             }
 
-            // Registration successful, user must confirm email before logging in
+            // Registration successful, user must confirm email and wait for approval unless invited
+            return await response.json();
         } catch (error)
         {
             console.error("Registration error:", error);
@@ -355,6 +480,8 @@ export function AuthenticationProvider({children}: { children: ReactNode })
             isLoading,
             currentUser,
             login,
+            loginWithOidc,
+            getLoginProviders,
             register,
             logout,
             me,
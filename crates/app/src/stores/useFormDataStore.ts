@@ -1,7 +1,7 @@
 import {create} from "zustand";
 import {FOBType, POInformationFormData, UploadFileItem, UploadFileType} from "../components/forms/po-information";
 import {getLocalTimeZone, parseDate, today} from "@internationalized/date";
-import {ManifestData} from "../types/manifest.ts";
+import {CellOverride, ManifestData} from "../types/manifest.ts";
 import {invoke} from "@tauri-apps/api/core";
 import {ManifestRow} from "../components/forms/CreateManifestTable.tsx";
 
@@ -12,6 +12,8 @@ export type ManifestMapping = {
     parsedData: ManifestData | null;
     isLoading: boolean;
     error: string | null;
+    rows?: ManifestRow[];
+    overrides?: CellOverride[];
 }
 
 export type HistoryItem = {
@@ -42,6 +44,8 @@ type SaveItemData = {
         filename: string;
         path: string;
         mappings: Record<string, string>;
+        rows?: ManifestRow[];
+        overrides?: CellOverride[];
     }>;
     assets: Array<{
         filename: string;
@@ -70,6 +74,7 @@ type FormDataActions = {
     setManifestParsedData: (path: string, data: ManifestData | null) => void;
     setManifestLoading: (path: string, isLoading: boolean) => void;
     setManifestError: (path: string, error: string | null) => void;
+    setManifestLines: (path: string, rows: ManifestRow[], overrides: CellOverride[]) => void;
     initializeManifestMappings: (files: UploadFileItem[]) => void;
     setCreatedManifest: (data: ManifestRow[]) => void;
     clearCreatedManifest: () => void;
@@ -157,7 +162,9 @@ export const useFormDataStore = create<FormDataStore & FormDataActions>((set, ge
                 manifests: state.manifestMappings.map(m => ({
                     filename: m.filename,
                     path: m.path,
-                    mappings: m.mappings
+                    mappings: m.mappings,
+                    rows: m.rows,
+                    overrides: m.overrides
                 })),
                 assets: state.uploadForm.files.map(f => ({
                     filename: f.filename,
@@ -268,6 +275,7 @@ export const useFormDataStore = create<FormDataStore & FormDataActions>((set, ge
             saveData.manifests.forEach(m =>
             {
                 get().setManifestMapping(m.path, m.mappings);
+                if (m.rows) get().setManifestLines(m.path, m.rows, m.overrides ?? []);
             });
 
             // Set current file path and mark as saved
@@ -302,6 +310,15 @@ export const useFormDataStore = create<FormDataStore & FormDataActions>((set, ge
             m.path === path ? {...m, error, isLoading: false} : m
         )
     })),
+    setManifestLines: (path: string, rows: ManifestRow[], overrides: CellOverride[]) =>
+    {
+        set((state) => ({
+            manifestMappings: state.manifestMappings.map(m =>
+                m.path === path ? {...m, rows, overrides} : m
+            )
+        }));
+        get().markAsModified();
+    },
     initializeManifestMappings: (files: UploadFileItem[]) => set((state) =>
     {
         const manifestFiles = files.filter(f => f.asset_type === UploadFileType.Manifest);
@@ -379,7 +396,8 @@ export const useFormDataStore = create<FormDataStore & FormDataActions>((set, ge
             uploadForm: state.uploadForm,
             manifestMappings: state.manifestMappings.map(m => ({
                 filename: m.filename,
-                mappings: m.mappings
+                mappings: m.mappings,
+                overrides: m.overrides
             })),
             createdManifest: state.createdManifest
         });
@@ -438,7 +456,9 @@ export const useFormDataStore = create<FormDataStore & FormDataActions>((set, ge
                 manifests: state.manifestMappings.map(m => ({
                     filename: m.filename,
                     path: m.path,
-                    mappings: m.mappings
+                    mappings: m.mappings,
+                    rows: m.rows,
+                    overrides: m.overrides
                 })),
                 assets: state.uploadForm.files.map(f => ({
                     filename: f.filename,
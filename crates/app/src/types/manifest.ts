@@ -39,3 +39,101 @@ export const TEMPLATE_FIELD_LABELS: Record<TemplateField, string> = {
 };
 
 export const REQUIRED_FIELDS: TemplateField[] = ['item_number', 'description', 'department'];
+
+export type ValidationSeverity = 'error' | 'warning';
+
+export type ValidationIssue = {
+    row: number;
+    field: string;
+    severity: ValidationSeverity;
+    message: string;
+}
+
+export type ValidationReport = {
+    rows_checked: number;
+    error_count: number;
+    warning_count: number;
+    issues: ValidationIssue[];
+}
+
+export type DuplicateStrategy = 'sum' | 'keep_separate';
+
+export type LineSource = {
+    filename: string;
+    path: string;
+    row: number;
+}
+
+export type MergedLine = Record<TemplateField, string> & {
+    source: string;
+    sources: LineSource[];
+}
+
+export type DuplicateGroup = {
+    field: 'item_number' | 'upc';
+    value: string;
+    sources: LineSource[];
+    merged: boolean;
+    note: string | null;
+}
+
+export type MergeResult = {
+    lines: MergedLine[];
+    duplicates: DuplicateGroup[];
+}
+
+export const MANIFEST_PROGRESS_EVENT = 'manifest-progress';
+
+export type ManifestJobPhase = 'opening' | 'reading' | 'writing' | 'done';
+
+export type ManifestProgress = {
+    job_id: string;
+    phase: ManifestJobPhase;
+    rows_processed: number;
+    total_rows: number | null;
+    percent: number | null;
+}
+
+export type PriceBasis =
+    | { kind: 'markup'; percent: string }
+    | { kind: 'percent_off_comp'; percent: string };
+
+export type PriceEnding = '.99' | '.49' | '.00';
+
+export type PricingRule = {
+    bases: PriceBasis[];
+    endings: PriceEnding[];
+    min_margin_percent: string | null;
+    cap_at_comp: boolean;
+}
+
+export type PricingConfig = {
+    default_rule: PricingRule;
+    departments: Record<string, PricingRule>;
+}
+
+export type FillMode = 'none' | 'empty' | 'all';
+
+export type SuggestionStatus = 'filled' | 'matches' | 'differs' | 'conflict' | 'no_suggestion';
+
+export type PriceSuggestion = {
+    row: number;
+    item_number: string;
+    rule: string;
+    current_price: string | null;
+    suggested_price: string | null;
+    status: SuggestionStatus;
+    explanation: string[];
+}
+
+export type PricedManifest = {
+    rows: Record<TemplateField, string>[];
+    suggestions: PriceSuggestion[];
+}
+
+export type CellOverride = {
+    item_number: string;
+    occurrence: number;
+    field: TemplateField;
+    value: string;
+}
@@ -0,0 +1,14 @@
+export type UsageIndicator = 'Production' | 'Test';
+
+export type EdiConfig = {
+    sender_qualifier: string;
+    sender_id: string;
+    receiver_qualifier: string;
+    receiver_id: string;
+    application_sender_code?: string;
+    application_receiver_code?: string;
+    interchange_control_number: number;
+    group_control_number: number;
+    usage?: UsageIndicator;
+    ship_to_name: string;
+}
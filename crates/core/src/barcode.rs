@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarcodeKind {
+    UpcA,
+    UpcE,
+    Ean8,
+    Ean13,
+    Gtin14,
+}
+
+/// A barcode that passed check digit validation.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Barcode {
+    pub kind: BarcodeKind,
+    /// The code in its canonical length for `kind`, leading zeros included.
+    pub code: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BarcodeError {
+    Empty,
+    InvalidCharacters(String),
+    InvalidLength(usize),
+    InvalidCheckDigit { expected: u8, found: u8 },
+}
+
+impl Display for BarcodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BarcodeError::Empty => write!(f, "Barcode is empty"),
+            BarcodeError::InvalidCharacters(value) => {
+                write!(f, "Barcode '{}' contains non-numeric characters", value)
+            }
+            BarcodeError::InvalidLength(length) => {
+                write!(f, "Barcode has an unsupported length of {} digits", length)
+            }
+            BarcodeError::InvalidCheckDigit { expected, found } => write!(
+                f,
+                "Invalid check digit: expected {} but found {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BarcodeError {}
+
+impl Barcode {
+    /// Returns the code padded to a 14 digit GTIN.
+    ///
+    /// UPC-E codes are expanded to UPC-A first, so equal products compare equal.
+    pub fn gtin14(&self) -> String {
+        let code = match self.kind {
+            BarcodeKind::UpcE => expand_upc_e(&self.code).unwrap_or_else(|| self.code.clone()),
+            _ => self.code.clone(),
+        };
+        format!("{:0>14}", code)
+    }
+}
+
+/// Normalizes a raw UPC/EAN/GTIN value and validates its check digit.
+///
+/// Accepts values as they typically come out of vendor spreadsheets: with spaces or dashes,
+/// with a trailing `.0`, in scientific notation (`7.12345678905E+11`), or with leading zeros
+/// stripped by a numeric cell. Codes of 9 to 11 digits are left-padded to a UPC-A and 7 digit
+/// codes to an EAN-8 before validation.
+///
+/// Eight digit codes starting with `0` or `1` are tried as UPC-E first, then as EAN-8.
+pub fn normalize(input: &str) -> Result<Barcode, BarcodeError> {
+    let digits = clean(input)?;
+
+    match digits.len() {
+        7 => validate(BarcodeKind::Ean8, format!("{:0>8}", digits)),
+        8 => {
+            if matches!(digits.as_bytes()[0], b'0' | b'1')
+                && let Ok(barcode) = validate(BarcodeKind::UpcE, digits.clone())
+            {
+                return Ok(barcode);
+            }
+            validate(BarcodeKind::Ean8, digits)
+        }
+        9..=12 => validate(BarcodeKind::UpcA, format!("{:0>12}", digits)),
+        13 => validate(BarcodeKind::Ean13, digits),
+        14 => validate(BarcodeKind::Gtin14, digits),
+        length => Err(BarcodeError::InvalidLength(length)),
+    }
+}
+
+/// Computes the GS1 mod-10 check digit for the payload (all digits except the check digit).
+pub fn check_digit(payload: &str) -> u8 {
+    let sum: u32 = payload
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(index, byte)| {
+            let digit = (byte - b'0') as u32;
+            if index % 2 == 0 { digit * 3 } else { digit }
+        })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+/// Expands an 8 digit UPC-E (number system, six digits, check digit) to a 12 digit UPC-A.
+pub fn expand_upc_e(code: &str) -> Option<String> {
+    if code.len() != 8 || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let number_system = &code[0..1];
+    let d = &code.as_bytes()[1..7];
+    let check = &code[7..8];
+    let s = |range: std::ops::Range<usize>| std::str::from_utf8(&d[range]).unwrap_or_default();
+
+    let body = match d[5] {
+        b'0'..=b'2' => format!("{}{}0000{}", s(0..2), s(5..6), s(2..5)),
+        b'3' => format!("{}00000{}", s(0..3), s(3..5)),
+        b'4' => format!("{}00000{}", s(0..4), s(4..5)),
+        _ => format!("{}0000{}", s(0..5), s(5..6)),
+    };
+    Some(format!("{}{}{}", number_system, body, check))
+}
+
+fn validate(kind: BarcodeKind, code: String) -> Result<Barcode, BarcodeError> {
+    let full = match kind {
+        BarcodeKind::UpcE => expand_upc_e(&code).ok_or(BarcodeError::InvalidLength(code.len()))?,
+        _ => code.clone(),
+    };
+    let (payload, check) = full.split_at(full.len() - 1);
+    let found = check.as_bytes()[0] - b'0';
+    let expected = check_digit(payload);
+    if expected != found {
+        return Err(BarcodeError::InvalidCheckDigit { expected, found });
+    }
+    Ok(Barcode { kind, code })
+}
+
+/// Strips formatting from a raw value and returns the bare digits.
+fn clean(input: &str) -> Result<String, BarcodeError> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err(BarcodeError::Empty);
+    }
+
+    // Excel likes to turn long numbers into scientific notation
+    if trimmed.contains(['e', 'E'])
+        && let Ok(value) = trimmed.parse::<f64>()
+        && value.fract() == 0.0
+        && (0.0..1e15).contains(&value)
+    {
+        return Ok(format!("{:.0}", value));
+    }
+
+    let mut value: String = trimmed
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect();
+    if let Some(stripped) = value.strip_suffix(".0") {
+        value = stripped.to_string();
+    }
+
+    if !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(BarcodeError::InvalidCharacters(trimmed.to_string()));
+    }
+    Ok(value)
+}
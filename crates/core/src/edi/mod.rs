@@ -0,0 +1,542 @@
+mod parser;
+
+pub use parser::{ParsedLine, ParsedPurchaseOrder, parse_850};
+
+use crate::manifest::ManifestRow;
+use crate::save_file::SaveFile;
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+const X12_VERSION: &str = "004010";
+const ISA_VERSION: &str = "00401";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UsageIndicator {
+    #[default]
+    Production,
+    Test,
+}
+
+/// Trading partner settings for the interchange envelope.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EdiConfig {
+    /// ISA05, e.g. `ZZ` (mutually defined) or `01` (DUNS).
+    pub sender_qualifier: String,
+    pub sender_id: String,
+    /// ISA07
+    pub receiver_qualifier: String,
+    pub receiver_id: String,
+    /// GS02 and GS03; the ISA ids are used when empty.
+    #[serde(default)]
+    pub application_sender_code: String,
+    #[serde(default)]
+    pub application_receiver_code: String,
+    pub interchange_control_number: u32,
+    pub group_control_number: u32,
+    #[serde(default)]
+    pub usage: UsageIndicator,
+    /// Name used on the N1 ship-to segment.
+    pub ship_to_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdiError {
+    MissingPoNumber,
+    MissingShipToName,
+    /// ISA ids are fixed width, so they have to be ASCII.
+    NonAsciiId {
+        field: String,
+        value: String,
+    },
+    /// ISA qualifiers hold 2 characters and ids 15; longer ones would be cut off.
+    IdTooLong {
+        field: String,
+        value: String,
+        max_length: usize,
+    },
+    QuantityTooLarge {
+        row: usize,
+    },
+    InvalidDate {
+        field: String,
+        value: String,
+    },
+    InvalidControlNumber(u32),
+    Parse(String),
+}
+
+impl Display for EdiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EdiError::MissingPoNumber => write!(f, "The PO has no PO number"),
+            EdiError::MissingShipToName => write!(f, "The EDI settings have no ship-to name"),
+            EdiError::NonAsciiId { field, value } => {
+                write!(f, "The {} '{}' can only use ASCII characters", field, value)
+            }
+            EdiError::IdTooLong {
+                field,
+                value,
+                max_length,
+            } => write!(
+                f,
+                "The {} '{}' can be at most {} characters",
+                field, value, max_length
+            ),
+            EdiError::QuantityTooLarge { row } => {
+                write!(f, "The quantity on row {} is too large", row)
+            }
+            EdiError::InvalidDate { field, value } => {
+                write!(f, "Invalid {} '{}', expected YYYY-MM-DD", field, value)
+            }
+            EdiError::InvalidControlNumber(number) => {
+                write!(f, "Control number {} does not fit in 9 digits", number)
+            }
+            EdiError::Parse(message) => write!(f, "Invalid X12 850: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for EdiError {}
+
+const ELEMENT_SEPARATOR: char = '*';
+const SUB_ELEMENT_SEPARATOR: char = ':';
+const SEGMENT_TERMINATOR: char = '~';
+
+/// Renders a PO and its lines as an X12 850 (version 004010) interchange.
+///
+/// One transaction set is written inside one functional group. Quantities are sent in each
+/// (`EA`) with the unit cost; the case pack goes on PO4 and the Mardens price on CTP. Lines
+/// without an item number or UPC are skipped since the vendor can't identify them.
+/// `created` is the interchange date and time.
+pub fn render_850(
+    save_file: &SaveFile,
+    lines: &[ManifestRow],
+    config: &EdiConfig,
+    created: NaiveDateTime,
+) -> Result<String, EdiError> {
+    let po_number = clean(&save_file.po_number);
+    if po_number.is_empty() {
+        return Err(EdiError::MissingPoNumber);
+    }
+    for number in [
+        config.interchange_control_number,
+        config.group_control_number,
+    ] {
+        if number > 999_999_999 {
+            return Err(EdiError::InvalidControlNumber(number));
+        }
+    }
+
+    let order_date = x12_date("order date", &save_file.order_date)?;
+    let ship_date = optional_date("ship date", &save_file.ship_date)?;
+    let cancel_date = optional_date("cancel date", &save_file.cancel_date)?;
+
+    let sender_qualifier = isa_id("sender qualifier", &config.sender_qualifier, 2)?;
+    let sender = isa_id("sender id", &config.sender_id, 15)?;
+    let receiver_qualifier = isa_id("receiver qualifier", &config.receiver_qualifier, 2)?;
+    let receiver = isa_id("receiver id", &config.receiver_id, 15)?;
+    let ship_to_name = clean(&config.ship_to_name);
+    if ship_to_name.is_empty() {
+        return Err(EdiError::MissingShipToName);
+    }
+    let gs_sender = or_default(clean(&config.application_sender_code), &sender);
+    let gs_receiver = or_default(clean(&config.application_receiver_code), &receiver);
+    let usage = match config.usage {
+        UsageIndicator::Production => "P",
+        UsageIndicator::Test => "T",
+    };
+
+    let mut out = String::new();
+    segment(
+        &mut out,
+        &[
+            "ISA",
+            "00",
+            &" ".repeat(10),
+            "00",
+            &" ".repeat(10),
+            &format!("{:<2}", sender_qualifier),
+            &format!("{:<15}", sender),
+            &format!("{:<2}", receiver_qualifier),
+            &format!("{:<15}", receiver),
+            &created.format("%y%m%d").to_string(),
+            &created.format("%H%M").to_string(),
+            "U",
+            ISA_VERSION,
+            &format!("{:09}", config.interchange_control_number),
+            "0",
+            usage,
+            &SUB_ELEMENT_SEPARATOR.to_string(),
+        ],
+    );
+    segment(
+        &mut out,
+        &[
+            "GS",
+            "PO",
+            &gs_sender,
+            &gs_receiver,
+            &created.format("%Y%m%d").to_string(),
+            &created.format("%H%M").to_string(),
+            &config.group_control_number.to_string(),
+            "X",
+            X12_VERSION,
+        ],
+    );
+
+    let mut transaction = Vec::new();
+    transaction.push(vec!["ST".to_string(), "850".into(), "0001".into()]);
+    transaction.push(elements(&["BEG", "00", "SA", &po_number, "", &order_date]));
+    transaction.push(elements(&["DTM", "004", &order_date]));
+    if let Some(ship_date) = ship_date {
+        transaction.push(elements(&["DTM", "010", &ship_date]));
+    }
+    if let Some(cancel_date) = cancel_date {
+        transaction.push(elements(&["DTM", "001", &cancel_date]));
+    }
+
+    let fob_point = clean(&save_file.fob_point);
+    let (payment, location) = match save_file.fob_type.trim().to_lowercase().as_str() {
+        "delivered" => ("PP", "DE"),
+        _ => ("CC", "OR"),
+    };
+    if fob_point.is_empty() {
+        transaction.push(elements(&["FOB", payment]));
+    } else {
+        transaction.push(elements(&["FOB", payment, location, &fob_point]));
+    }
+
+    transaction.push(elements(&["N1", "ST", &ship_to_name]));
+    transaction.extend(address_segments(&save_file.ship_to_address));
+    let vendor = clean(&save_file.vendor);
+    if !vendor.is_empty() {
+        transaction.push(elements(&["N1", "VN", &vendor]));
+    }
+
+    let mut line_count = 0;
+    let mut quantity_total = Decimal::ZERO;
+    for (index, row) in lines.iter().enumerate() {
+        let item_number = clean(&row.item_number);
+        let upc = clean(&row.upc);
+        if item_number.is_empty() && upc.is_empty() {
+            continue;
+        }
+        let numbers = row.parse_numbers();
+        let case_pack = numbers.case_pack.unwrap_or(Decimal::ONE);
+        let too_large = || EdiError::QuantityTooLarge { row: index + 1 };
+        let quantity = case_pack
+            .checked_mul(numbers.cases.unwrap_or_default())
+            .ok_or_else(too_large)?
+            .normalize();
+        line_count += 1;
+        quantity_total = quantity_total.checked_add(quantity).ok_or_else(too_large)?;
+
+        let mut po1 = elements(&[
+            "PO1",
+            &line_count.to_string(),
+            &quantity.to_string(),
+            "EA",
+            &numbers
+                .mardens_cost
+                .map(|cost| cost.round_dp(4).normalize().to_string())
+                .unwrap_or_default(),
+            "",
+        ]);
+        if !upc.is_empty() {
+            po1.extend(["UP".to_string(), upc]);
+        }
+        if !item_number.is_empty() {
+            po1.extend(["VN".to_string(), item_number]);
+        }
+        transaction.push(po1);
+
+        if numbers.case_pack.is_some() {
+            transaction.push(elements(&["PO4", &case_pack.normalize().to_string()]));
+        }
+        if let Some(price) = numbers.mardens_price {
+            transaction.push(elements(&["CTP", "", "RES", &format!("{:.2}", price)]));
+        }
+        let description = clean(&row.description);
+        if !description.is_empty() {
+            transaction.push(elements(&["PID", "F", "", "", "", &description]));
+        }
+    }
+
+    transaction.push(elements(&[
+        "CTT",
+        &line_count.to_string(),
+        &quantity_total.normalize().to_string(),
+    ]));
+    let segment_count = transaction.len() + 1;
+    transaction.push(elements(&["SE", &segment_count.to_string(), "0001"]));
+
+    for elements in &transaction {
+        let elements: Vec<&str> = elements.iter().map(String::as_str).collect();
+        segment(&mut out, &elements);
+    }
+    segment(
+        &mut out,
+        &["GE", "1", &config.group_control_number.to_string()],
+    );
+    segment(
+        &mut out,
+        &[
+            "IEA",
+            "1",
+            &format!("{:09}", config.interchange_control_number),
+        ],
+    );
+    Ok(out)
+}
+
+fn elements(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+/// Writes a segment, dropping trailing empty elements as X12 requires.
+fn segment(out: &mut String, elements: &[&str]) {
+    let used = elements
+        .iter()
+        .rposition(|element| !element.is_empty())
+        .map_or(0, |index| index + 1);
+    out.push_str(&elements[..used].join(&ELEMENT_SEPARATOR.to_string()));
+    out.push(SEGMENT_TERMINATOR);
+    out.push('\n');
+}
+
+/// Removes delimiter characters from a data value so they can't break the segment structure.
+fn clean(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            ELEMENT_SEPARATOR | SUB_ELEMENT_SEPARATOR | SEGMENT_TERMINATOR | '\r' | '\n' => ' ',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Cleans an ISA id, which is padded to a fixed width and so has to be ASCII.
+fn isa_id(field: &str, value: &str, max_length: usize) -> Result<String, EdiError> {
+    let id = clean(value);
+    if !id.is_ascii() {
+        return Err(EdiError::NonAsciiId {
+            field: field.to_string(),
+            value: value.to_string(),
+        });
+    }
+    if id.len() > max_length {
+        return Err(EdiError::IdTooLong {
+            field: field.to_string(),
+            value: value.to_string(),
+            max_length,
+        });
+    }
+    Ok(id)
+}
+
+fn or_default(value: String, default: &str) -> String {
+    if value.is_empty() {
+        default.to_string()
+    } else {
+        value
+    }
+}
+
+fn x12_date(field: &str, value: &str) -> Result<String, EdiError> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map(|date| date.format("%Y%m%d").to_string())
+        .map_err(|_| EdiError::InvalidDate {
+            field: field.to_string(),
+            value: value.to_string(),
+        })
+}
+
+fn optional_date(field: &str, value: &Option<String>) -> Result<Option<String>, EdiError> {
+    match value.as_deref().map(str::trim) {
+        Some(value) if !value.is_empty() => x12_date(field, value).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// N3/N4 segments for an address written as `street, city, ST zip`.
+///
+/// Addresses that don't follow that form are sent as a single N3 line.
+fn address_segments(address: &str) -> Vec<Vec<String>> {
+    let address = clean(address);
+    if address.is_empty() {
+        return Vec::new();
+    }
+
+    let parts: Vec<&str> = address.split(',').map(str::trim).collect();
+    if let [street @ .., city, state_zip] = parts.as_slice()
+        && !street.is_empty()
+        && let Some((state, zip)) = state_zip.split_once(' ')
+        && state.len() == 2
+    {
+        return vec![
+            elements(&["N3", &street.join(", ")]),
+            elements(&["N4", city, state, zip.trim()]),
+        ];
+    }
+    vec![elements(&["N3", &address])]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save_file() -> SaveFile {
+        SaveFile {
+            version: "1.0.0".to_string(),
+            po_number: "12345".to_string(),
+            buyer_id: 1,
+            vendor: "Acme Wholesale".to_string(),
+            order_date: "2026-10-01".to_string(),
+            ship_date: Some("2026-10-10".to_string()),
+            cancel_date: None,
+            shipping_notes: String::new(),
+            description: String::new(),
+            terms: "Net 30".to_string(),
+            ship_to_address: "1 Main St, Waterville, ME 04901".to_string(),
+            fob_type: "Delivered".to_string(),
+            fob_point: "Waterville".to_string(),
+            notes: String::new(),
+            manifests: Vec::new(),
+            assets: Vec::new(),
+            summary: None,
+        }
+    }
+
+    fn config() -> EdiConfig {
+        EdiConfig {
+            sender_qualifier: "ZZ".to_string(),
+            sender_id: "MARDENS".to_string(),
+            receiver_qualifier: "ZZ".to_string(),
+            receiver_id: "ACME".to_string(),
+            application_sender_code: String::new(),
+            application_receiver_code: String::new(),
+            interchange_control_number: 42,
+            group_control_number: 7,
+            usage: UsageIndicator::Test,
+            ship_to_name: "Mardens Surplus & Salvage".to_string(),
+        }
+    }
+
+    fn created() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 1)
+            .unwrap()
+            .and_hms_opt(9, 30, 0)
+            .unwrap()
+    }
+
+    fn line(item_number: &str, case_pack: &str, cases: &str) -> ManifestRow {
+        ManifestRow {
+            item_number: item_number.to_string(),
+            upc: "036000291452".to_string(),
+            description: "Blue widget".to_string(),
+            case_pack: case_pack.to_string(),
+            cases: cases.to_string(),
+            mardens_cost: "$1.50".to_string(),
+            mardens_price: "2.99".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rendered_850_parses_back() {
+        let lines = [line("A100", "6", "2"), line("B200", "", "3")];
+        let edi = render_850(&save_file(), &lines, &config(), created()).unwrap();
+        let po = parse_850(&edi).unwrap();
+
+        assert_eq!(po.sender_id, "MARDENS");
+        assert_eq!(po.receiver_id, "ACME");
+        assert_eq!(po.interchange_control_number, 42);
+        assert_eq!(po.group_control_number, 7);
+        assert_eq!(po.po_number, "12345");
+        assert_eq!(po.order_date, "20261001");
+        assert_eq!(po.ship_date.as_deref(), Some("20261010"));
+        assert_eq!(po.fob_point.as_deref(), Some("Waterville"));
+        assert_eq!(
+            po.ship_to_name.as_deref(),
+            Some("Mardens Surplus & Salvage")
+        );
+        assert_eq!(po.vendor.as_deref(), Some("Acme Wholesale"));
+        assert_eq!(po.lines.len(), 2);
+        assert_eq!(po.lines[0].quantity, Decimal::from(12));
+        assert_eq!(po.lines[0].pack, Some(Decimal::from(6)));
+        assert_eq!(po.lines[0].unit_price, Some(Decimal::new(15, 1)));
+        assert_eq!(po.lines[0].retail_price, Some(Decimal::new(299, 2)));
+        assert_eq!(po.lines[0].vendor_item_number.as_deref(), Some("A100"));
+        assert_eq!(po.lines[0].upc.as_deref(), Some("036000291452"));
+        assert_eq!(po.lines[1].quantity, Decimal::from(3));
+        assert_eq!(po.lines[1].pack, None);
+    }
+
+    #[test]
+    fn isa_is_fixed_width() {
+        let edi = render_850(&save_file(), &[], &config(), created()).unwrap();
+        let isa = edi.lines().next().unwrap();
+        assert_eq!(isa.len(), 106);
+    }
+
+    #[test]
+    fn non_ascii_id_is_rejected() {
+        let mut config = config();
+        config.sender_id = "MARDÉNS".to_string();
+        assert!(matches!(
+            render_850(&save_file(), &[], &config, created()),
+            Err(EdiError::NonAsciiId { .. })
+        ));
+    }
+
+    #[test]
+    fn long_qualifier_is_rejected() {
+        let mut config = config();
+        config.receiver_qualifier = "ZZZ".to_string();
+        assert!(matches!(
+            render_850(&save_file(), &[], &config, created()),
+            Err(EdiError::IdTooLong { max_length: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn long_id_is_rejected() {
+        let mut config = config();
+        config.sender_id = "MARDENS-SURPLUS-1".to_string();
+        assert!(matches!(
+            render_850(&save_file(), &[], &config, created()),
+            Err(EdiError::IdTooLong { max_length: 15, .. })
+        ));
+        config.sender_id = "MARDENS-SURPLUS".to_string();
+        assert!(render_850(&save_file(), &[], &config, created()).is_ok());
+    }
+
+    #[test]
+    fn empty_ship_to_name_is_rejected() {
+        let mut config = config();
+        config.ship_to_name = " ".to_string();
+        assert_eq!(
+            render_850(&save_file(), &[], &config, created()),
+            Err(EdiError::MissingShipToName)
+        );
+    }
+
+    #[test]
+    fn overflowing_quantity_is_rejected() {
+        let lines = [line("A100", "79228162514264337593543950335", "2")];
+        assert_eq!(
+            render_850(&save_file(), &lines, &config(), created()),
+            Err(EdiError::QuantityTooLarge { row: 1 })
+        );
+    }
+
+    #[test]
+    fn multibyte_isa_is_a_parse_error() {
+        let input = format!("ISA*é{}", "x".repeat(110));
+        assert!(matches!(parse_850(&input), Err(EdiError::Parse(_))));
+    }
+}
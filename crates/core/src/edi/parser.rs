@@ -0,0 +1,234 @@
+use super::EdiError;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// A PO1 loop read back from an 850.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ParsedLine {
+    pub line_number: String,
+    pub quantity: Decimal,
+    pub unit_of_measure: String,
+    pub unit_price: Option<Decimal>,
+    pub upc: Option<String>,
+    pub vendor_item_number: Option<String>,
+    pub pack: Option<Decimal>,
+    pub retail_price: Option<Decimal>,
+    pub description: Option<String>,
+}
+
+/// The parts of an X12 850 the renderer writes, as read back from an interchange.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ParsedPurchaseOrder {
+    pub sender_id: String,
+    pub receiver_id: String,
+    pub interchange_control_number: u32,
+    pub group_control_number: u32,
+    pub po_number: String,
+    /// Dates as `YYYYMMDD`.
+    pub order_date: String,
+    pub ship_date: Option<String>,
+    pub cancel_date: Option<String>,
+    pub fob_payment: Option<String>,
+    pub fob_point: Option<String>,
+    pub ship_to_name: Option<String>,
+    pub ship_to_address: Vec<String>,
+    pub vendor: Option<String>,
+    pub lines: Vec<ParsedLine>,
+}
+
+/// Parses a single-transaction X12 850 interchange.
+///
+/// The delimiters are read from the ISA segment. Segment counts, control numbers and the
+/// CTT line count are checked against the envelope, so a parse of rendered output proves the
+/// envelope is consistent.
+pub fn parse_850(input: &str) -> Result<ParsedPurchaseOrder, EdiError> {
+    let input = input.trim_start();
+    if !input.starts_with("ISA") || input.len() < 106 {
+        return Err(parse_error(
+            "Interchange does not start with an ISA segment",
+        ));
+    }
+    // The ISA is fixed width, so the separators sit at fixed byte offsets.
+    let isa = &input.as_bytes()[..106];
+    if !isa.is_ascii() {
+        return Err(parse_error("ISA segment contains non-ASCII characters"));
+    }
+    let element_separator = char::from(isa[3]);
+    let segment_terminator = char::from(isa[105]);
+
+    let segments: Vec<Vec<&str>> = input
+        .split(segment_terminator)
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| segment.split(element_separator).collect())
+        .collect();
+
+    let mut po = ParsedPurchaseOrder::default();
+    let mut transaction_segments = 0;
+    let mut in_transaction = false;
+    let mut transaction_control = String::new();
+    let mut line_count = None;
+    let mut in_ship_to = false;
+
+    for elements in &segments {
+        let id = elements[0];
+        let element = |index: usize| elements.get(index).copied().unwrap_or_default().trim();
+
+        if id == "ST" {
+            in_transaction = true;
+            transaction_control = element(2).to_string();
+            if element(1) != "850" {
+                return Err(parse_error(&format!(
+                    "Expected an 850, found {}",
+                    element(1)
+                )));
+            }
+        }
+        if in_transaction {
+            transaction_segments += 1;
+        }
+
+        match id {
+            "ISA" => {
+                po.sender_id = element(6).to_string();
+                po.receiver_id = element(8).to_string();
+                po.interchange_control_number = number(element(13), "ISA13")?;
+            }
+            "GS" => po.group_control_number = number(element(6), "GS06")?,
+            "BEG" => {
+                po.po_number = element(3).to_string();
+                po.order_date = element(5).to_string();
+            }
+            "DTM" => {
+                let date = Some(element(2).to_string());
+                match element(1) {
+                    "004" => po.order_date = element(2).to_string(),
+                    "010" => po.ship_date = date,
+                    "001" => po.cancel_date = date,
+                    _ => {}
+                }
+            }
+            "FOB" => {
+                po.fob_payment = non_empty(element(1));
+                po.fob_point = non_empty(element(3));
+            }
+            "N1" => {
+                in_ship_to = element(1) == "ST";
+                match element(1) {
+                    "ST" => po.ship_to_name = non_empty(element(2)),
+                    "VN" => po.vendor = non_empty(element(2)),
+                    _ => {}
+                }
+            }
+            "N3" | "N4" if in_ship_to => {
+                let address = elements[1..]
+                    .iter()
+                    .map(|value| value.trim())
+                    .filter(|value| !value.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                po.ship_to_address.push(address);
+            }
+            "PO1" => {
+                in_ship_to = false;
+                let mut line = ParsedLine {
+                    line_number: element(1).to_string(),
+                    quantity: decimal(element(2), "PO102")?,
+                    unit_of_measure: element(3).to_string(),
+                    unit_price: optional_decimal(element(4), "PO104")?,
+                    ..Default::default()
+                };
+                for pair in elements.get(6..).unwrap_or_default().chunks(2) {
+                    let value = pair.get(1).and_then(|value| non_empty(value));
+                    match pair[0] {
+                        "UP" => line.upc = value,
+                        "VN" => line.vendor_item_number = value,
+                        _ => {}
+                    }
+                }
+                po.lines.push(line);
+            }
+            "PO4" => current_line(&mut po, "PO4")?.pack = optional_decimal(element(1), "PO401")?,
+            "CTP" if element(2) == "RES" => {
+                current_line(&mut po, "CTP")?.retail_price = optional_decimal(element(3), "CTP03")?;
+            }
+            "PID" => current_line(&mut po, "PID")?.description = non_empty(element(5)),
+            "CTT" => line_count = Some(number(element(1), "CTT01")?),
+            "SE" => {
+                let count: usize = number(element(1), "SE01")? as usize;
+                if count != transaction_segments {
+                    return Err(parse_error(&format!(
+                        "SE declares {} segments but the transaction has {}",
+                        count, transaction_segments
+                    )));
+                }
+                if element(2) != transaction_control {
+                    return Err(parse_error("SE control number does not match ST"));
+                }
+                in_transaction = false;
+            }
+            "GE" if number(element(2), "GE02")? != po.group_control_number => {
+                return Err(parse_error("GE control number does not match GS"));
+            }
+            "IEA" if number(element(2), "IEA02")? != po.interchange_control_number => {
+                return Err(parse_error("IEA control number does not match ISA"));
+            }
+            _ => {}
+        }
+    }
+
+    if po.po_number.is_empty() {
+        return Err(parse_error("Missing BEG segment"));
+    }
+    if in_transaction {
+        return Err(parse_error("Missing SE segment"));
+    }
+    if let Some(count) = line_count
+        && count as usize != po.lines.len()
+    {
+        return Err(parse_error(&format!(
+            "CTT declares {} lines but the transaction has {}",
+            count,
+            po.lines.len()
+        )));
+    }
+    Ok(po)
+}
+
+fn current_line<'a>(
+    po: &'a mut ParsedPurchaseOrder,
+    segment: &str,
+) -> Result<&'a mut ParsedLine, EdiError> {
+    po.lines
+        .last_mut()
+        .ok_or_else(|| parse_error(&format!("{} segment outside a PO1 loop", segment)))
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn number(value: &str, element: &str) -> Result<u32, EdiError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| parse_error(&format!("{} '{}' is not a number", element, value)))
+}
+
+fn decimal(value: &str, element: &str) -> Result<Decimal, EdiError> {
+    Decimal::from_str(value.trim())
+        .map_err(|_| parse_error(&format!("{} '{}' is not a number", element, value)))
+}
+
+fn optional_decimal(value: &str, element: &str) -> Result<Option<Decimal>, EdiError> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    decimal(value, element).map(Some)
+}
+
+fn parse_error(message: &str) -> EdiError {
+    EdiError::Parse(message.to_string())
+}
@@ -1 +1,8 @@
-pub mod save_file;
\ No newline at end of file
+pub mod barcode;
+pub mod edi;
+pub mod manifest;
+pub mod po_pdf;
+pub mod pricing;
+pub mod save_file;
+pub mod summary;
+pub mod taxonomy;
\ No newline at end of file
@@ -0,0 +1,265 @@
+use super::ManifestRow;
+use super::numbers::{parse_amount, parse_quantity};
+use crate::barcode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// What to do with lines that share an item number or UPC across manifest files.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateStrategy {
+    /// Combine duplicates into one line by adding up their cases.
+    Sum,
+    /// Keep every line and only report the duplicates.
+    #[default]
+    KeepSeparate,
+}
+
+/// The rows of one manifest file, already mapped onto the template fields.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestSource {
+    pub filename: String,
+    /// Full path of the file, which tells apart different files that share a name.
+    pub path: String,
+    pub rows: Vec<ManifestRow>,
+}
+
+/// Where a merged line came from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LineSource {
+    pub filename: String,
+    pub path: String,
+    /// 1-based index of the data row within the file.
+    pub row: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MergedLine {
+    #[serde(flatten)]
+    pub row: ManifestRow,
+    /// Provenance column, e.g. `vendor_a.xlsx:12; vendor_b.csv:3`.
+    pub source: String,
+    pub sources: Vec<LineSource>,
+}
+
+/// Lines from different files that share an item number or UPC.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DuplicateGroup {
+    /// `item_number` or `upc`
+    pub field: String,
+    pub value: String,
+    pub sources: Vec<LineSource>,
+    /// Whether the lines were summed into one.
+    pub merged: bool,
+    /// Why the lines were kept separate when summing was requested.
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MergeResult {
+    pub lines: Vec<MergedLine>,
+    pub duplicates: Vec<DuplicateGroup>,
+}
+
+impl MergedLine {
+    fn new(row: ManifestRow, source: LineSource) -> Self {
+        let mut line = Self {
+            row,
+            source: String::new(),
+            sources: vec![source],
+        };
+        line.update_source();
+        line
+    }
+
+    fn update_source(&mut self) {
+        self.source = self
+            .sources
+            .iter()
+            .map(|source| format!("{}:{}", source.filename, source.row))
+            .collect::<Vec<_>>()
+            .join("; ");
+    }
+}
+
+/// Concatenates the rows of several manifest files into one PO line list.
+///
+/// Lines are matched across files by UPC (compared as GTIN-14, so a UPC-A and its EAN-13 form
+/// match) or by item number. With [`DuplicateStrategy::Sum`] matching lines are combined by
+/// adding their cases, as long as the case pack and cost agree; otherwise they are kept separate
+/// and the group says why. Duplicates within a single file are left to row validation.
+pub fn merge_manifests(sources: Vec<ManifestSource>, strategy: DuplicateStrategy) -> MergeResult {
+    let mut result = MergeResult::default();
+    let mut by_key: HashMap<(&'static str, String), usize> = HashMap::new();
+    let mut groups: HashMap<(&'static str, String), usize> = HashMap::new();
+
+    for source in sources {
+        for (index, row) in source.rows.into_iter().enumerate() {
+            let line_source = LineSource {
+                filename: source.filename.clone(),
+                path: source.path.clone(),
+                row: index + 1,
+            };
+            let keys = duplicate_keys(&row);
+            let existing = keys.iter().find_map(|key| {
+                by_key
+                    .get(key)
+                    .copied()
+                    .filter(|&line| is_other_file(&result.lines[line], &source.path))
+                    .map(|line| (key.clone(), line))
+            });
+
+            let Some((key, line_index)) = existing else {
+                let line_index = result.lines.len();
+                for key in keys {
+                    by_key.entry(key).or_insert(line_index);
+                }
+                result.lines.push(MergedLine::new(row, line_source));
+                continue;
+            };
+
+            let group_index = *groups.entry(key.clone()).or_insert_with(|| {
+                result.duplicates.push(DuplicateGroup {
+                    field: key.0.to_string(),
+                    value: key.1.clone(),
+                    sources: result.lines[line_index].sources.clone(),
+                    merged: false,
+                    note: None,
+                });
+                result.duplicates.len() - 1
+            });
+            let group = &mut result.duplicates[group_index];
+            group.sources.push(line_source.clone());
+
+            if strategy == DuplicateStrategy::Sum {
+                match sum_cases(&result.lines[line_index].row, &row) {
+                    Ok(cases) => {
+                        let line = &mut result.lines[line_index];
+                        line.row.cases = cases;
+                        line.sources.push(line_source);
+                        line.update_source();
+                        group.merged = true;
+                        continue;
+                    }
+                    Err(note) => group.note = Some(note),
+                }
+            }
+
+            let line_index = result.lines.len();
+            for key in keys {
+                by_key.entry(key).or_insert(line_index);
+            }
+            result.lines.push(MergedLine::new(row, line_source));
+        }
+    }
+
+    result
+}
+
+/// Keys a row can be matched on: its UPC as GTIN-14 and its item number.
+fn duplicate_keys(row: &ManifestRow) -> Vec<(&'static str, String)> {
+    let mut keys = Vec::new();
+    if let Ok(barcode) = barcode::normalize(&row.upc) {
+        keys.push(("upc", barcode.gtin14()));
+    }
+    let item_number = row.item_number.trim();
+    if !item_number.is_empty() {
+        keys.push(("item_number", item_number.to_uppercase()));
+    }
+    keys
+}
+
+fn is_other_file(line: &MergedLine, path: &str) -> bool {
+    line.sources.iter().all(|source| source.path != path)
+}
+
+/// Adds up the cases of two matching lines, or explains why they can't be combined.
+fn sum_cases(existing: &ManifestRow, row: &ManifestRow) -> Result<String, String> {
+    let case_pack = |row: &ManifestRow| parse_quantity(&row.case_pack).ok();
+    if case_pack(existing) != case_pack(row) {
+        return Err(format!(
+            "Case packs differ ({} vs {})",
+            existing.case_pack, row.case_pack
+        ));
+    }
+
+    let cost = |row: &ManifestRow| parse_amount(&row.mardens_cost).ok();
+    if cost(existing) != cost(row) {
+        return Err(format!(
+            "Costs differ ({} vs {})",
+            existing.mardens_cost, row.mardens_cost
+        ));
+    }
+
+    let cases = |row: &ManifestRow| {
+        parse_quantity(&row.cases).map_err(|e| format!("Cases can't be summed: {}", e))
+    };
+    let total = cases(existing)?
+        .checked_add(cases(row)?)
+        .ok_or_else(|| "Cases can't be summed: too large".to_string())?;
+    Ok(total.normalize().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(path: &str, item_numbers: &[&str]) -> ManifestSource {
+        ManifestSource {
+            filename: "manifest.csv".to_string(),
+            path: path.to_string(),
+            rows: item_numbers
+                .iter()
+                .map(|item_number| ManifestRow {
+                    item_number: item_number.to_string(),
+                    case_pack: "6".to_string(),
+                    cases: "2".to_string(),
+                    mardens_cost: "1.50".to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn files_with_the_same_name_are_merged() {
+        let result = merge_manifests(
+            vec![
+                source("/vendor_a/manifest.csv", &["A100"]),
+                source("/vendor_b/manifest.csv", &["A100"]),
+            ],
+            DuplicateStrategy::Sum,
+        );
+        assert_eq!(result.lines.len(), 1);
+        assert_eq!(result.lines[0].row.cases, "4");
+        assert!(result.duplicates[0].merged);
+    }
+
+    #[test]
+    fn duplicates_within_one_file_are_kept() {
+        let result = merge_manifests(
+            vec![source("/vendor_a/manifest.csv", &["A100", "A100"])],
+            DuplicateStrategy::Sum,
+        );
+        assert_eq!(result.lines.len(), 2);
+        assert!(result.duplicates.is_empty());
+    }
+
+    #[test]
+    fn cases_too_large_to_sum_are_kept_apart() {
+        let mut sources = vec![
+            source("/vendor_a/manifest.csv", &["A100"]),
+            source("/vendor_b/manifest.csv", &["A100"]),
+        ];
+        for source in &mut sources {
+            source.rows[0].cases = "50000000000000000000000000000".to_string();
+        }
+        let result = merge_manifests(sources, DuplicateStrategy::Sum);
+        assert_eq!(result.lines.len(), 2);
+        assert!(!result.duplicates[0].merged);
+        assert_eq!(
+            result.duplicates[0].note.as_deref(),
+            Some("Cases can't be summed: too large")
+        );
+    }
+}
@@ -0,0 +1,98 @@
+pub mod merge;
+pub mod numbers;
+pub mod validation;
+
+use crate::barcode::{self, BarcodeError};
+use serde::{Deserialize, Serialize};
+
+/// The template fields a vendor manifest column can be mapped to, in export order.
+pub const MANIFEST_FIELDS: [&str; 13] = [
+    "item_number",
+    "upc",
+    "description",
+    "case_pack",
+    "cases",
+    "mardens_cost",
+    "mardens_price",
+    "comp_retail",
+    "department",
+    "category",
+    "sub_category",
+    "season",
+    "notes",
+];
+
+/// A single manifest line after the vendor's columns have been mapped onto the template fields.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ManifestRow {
+    pub item_number: String,
+    pub upc: String,
+    pub description: String,
+    pub case_pack: String,
+    pub cases: String,
+    pub mardens_cost: String,
+    pub mardens_price: String,
+    pub comp_retail: String,
+    pub department: String,
+    pub category: String,
+    pub sub_category: String,
+    pub season: String,
+    pub notes: String,
+}
+
+impl ManifestRow {
+    /// Returns the value of a template field by name, or `None` for an unknown field.
+    pub fn field(&self, field: &str) -> Option<&str> {
+        let value = match field {
+            "item_number" => &self.item_number,
+            "upc" => &self.upc,
+            "description" => &self.description,
+            "case_pack" => &self.case_pack,
+            "cases" => &self.cases,
+            "mardens_cost" => &self.mardens_cost,
+            "mardens_price" => &self.mardens_price,
+            "comp_retail" => &self.comp_retail,
+            "department" => &self.department,
+            "category" => &self.category,
+            "sub_category" => &self.sub_category,
+            "season" => &self.season,
+            "notes" => &self.notes,
+            _ => return None,
+        };
+        Some(value.as_str())
+    }
+
+    /// Sets a template field by name. Returns `false` if the field does not exist.
+    pub fn set_field(&mut self, field: &str, value: String) -> bool {
+        let target = match field {
+            "item_number" => &mut self.item_number,
+            "upc" => &mut self.upc,
+            "description" => &mut self.description,
+            "case_pack" => &mut self.case_pack,
+            "cases" => &mut self.cases,
+            "mardens_cost" => &mut self.mardens_cost,
+            "mardens_price" => &mut self.mardens_price,
+            "comp_retail" => &mut self.comp_retail,
+            "department" => &mut self.department,
+            "category" => &mut self.category,
+            "sub_category" => &mut self.sub_category,
+            "season" => &mut self.season,
+            "notes" => &mut self.notes,
+            _ => return false,
+        };
+        *target = value;
+        true
+    }
+
+    /// Rewrites the UPC in its canonical form (leading zeros restored) if it is a valid barcode.
+    ///
+    /// An empty UPC is left alone; an invalid one is kept as-is and the error is returned.
+    pub fn normalize_upc(&mut self) -> Result<(), BarcodeError> {
+        if self.upc.trim().is_empty() {
+            return Ok(());
+        }
+        let barcode = barcode::normalize(&self.upc)?;
+        self.upc = barcode.code;
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use super::ManifestRow;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NumberError {
+    Empty,
+    Invalid(String),
+}
+
+impl Display for NumberError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NumberError::Empty => write!(f, "Value is empty"),
+            NumberError::Invalid(value) => write!(f, "'{}' is not a number", value),
+        }
+    }
+}
+
+impl std::error::Error for NumberError {}
+
+/// A numeric column that could not be parsed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FieldError {
+    pub field: String,
+    pub value: String,
+    pub message: String,
+}
+
+/// The numeric columns of a manifest row parsed into exact decimals.
+///
+/// Empty cells are `None`; cells that could not be parsed are `None` and listed in `errors`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RowNumbers {
+    pub case_pack: Option<Decimal>,
+    pub cases: Option<Decimal>,
+    pub mardens_cost: Option<Decimal>,
+    pub mardens_price: Option<Decimal>,
+    pub comp_retail: Option<Decimal>,
+    pub errors: Vec<FieldError>,
+}
+
+impl ManifestRow {
+    /// Parses the cost, price and quantity columns of this row.
+    pub fn parse_numbers(&self) -> RowNumbers {
+        let mut numbers = RowNumbers::default();
+        numbers.case_pack = numbers.collect("case_pack", &self.case_pack, parse_quantity);
+        numbers.cases = numbers.collect("cases", &self.cases, parse_quantity);
+        numbers.mardens_cost = numbers.collect("mardens_cost", &self.mardens_cost, parse_amount);
+        numbers.mardens_price = numbers.collect("mardens_price", &self.mardens_price, parse_amount);
+        numbers.comp_retail = numbers.collect("comp_retail", &self.comp_retail, parse_amount);
+        numbers
+    }
+}
+
+impl RowNumbers {
+    fn collect(
+        &mut self,
+        field: &str,
+        value: &str,
+        parse: fn(&str) -> Result<Decimal, NumberError>,
+    ) -> Option<Decimal> {
+        match parse(value) {
+            Ok(number) => Some(number),
+            Err(NumberError::Empty) => None,
+            Err(e) => {
+                self.errors.push(FieldError {
+                    field: field.to_string(),
+                    value: value.to_string(),
+                    message: e.to_string(),
+                });
+                None
+            }
+        }
+    }
+}
+
+/// Parses a currency amount as written in vendor manifests.
+///
+/// Handles currency symbols and codes (`$1,234.50`, `USD 12`), accounting negatives
+/// (`(12.00)`), trailing signs (`12.00-`), European separators (`1.234,50`) and a trailing
+/// per-unit suffix (`1.50 ea`, `2.00/cs`).
+pub fn parse_amount(value: &str) -> Result<Decimal, NumberError> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Err(NumberError::Empty);
+    }
+
+    let mut text = trimmed.to_string();
+    let mut negative = false;
+
+    if text.starts_with('(') && text.ends_with(')') {
+        negative = true;
+        text = text[1..text.len() - 1].to_string();
+    }
+
+    let text: String = strip_currency(&text);
+    let text = text.trim();
+    let (number, _unit) = split_unit(text);
+    let mut number = number.trim().to_string();
+
+    if let Some(rest) = number.strip_prefix('-') {
+        negative = !negative;
+        number = rest.trim().to_string();
+    } else if let Some(rest) = number.strip_suffix('-') {
+        negative = !negative;
+        number = rest.trim().to_string();
+    }
+
+    let parsed = parse_decimal(&number).ok_or_else(|| NumberError::Invalid(trimmed.to_string()))?;
+    Ok(if negative { -parsed } else { parsed })
+}
+
+/// Parses a quantity such as a case pack or case count.
+///
+/// Accepts a number followed by an optional unit (`12 ea`, `6/cs`, `24pk`) and thousands
+/// separators (`1,200`).
+pub fn parse_quantity(value: &str) -> Result<Decimal, NumberError> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Err(NumberError::Empty);
+    }
+
+    let (number, _unit) = split_unit(trimmed);
+    let number = number.trim();
+    let (negative, number) = match number.strip_prefix('-') {
+        Some(rest) => (true, rest.trim()),
+        None => (false, number),
+    };
+
+    let parsed = parse_decimal(number).ok_or_else(|| NumberError::Invalid(trimmed.to_string()))?;
+    Ok(if negative { -parsed } else { parsed })
+}
+
+fn strip_currency(text: &str) -> String {
+    let mut text = text.to_string();
+    for code in ["USD", "US$", "CAD", "EUR"] {
+        if let Some(index) = text.find(code) {
+            text.replace_range(index..index + code.len(), "");
+        }
+    }
+    text.chars()
+        .filter(|c| !matches!(c, '$' | '€' | '£' | '¥'))
+        .collect()
+}
+
+/// Splits a trailing unit (`ea`, `/cs`, `pk`) from the number in front of it.
+///
+/// If the text after the number contains more digits (e.g. `2x6`) nothing is split off and the
+/// whole text is returned, so the value is reported as invalid instead of guessed at.
+fn split_unit(text: &str) -> (&str, &str) {
+    let end = text
+        .char_indices()
+        .find(|(_, c)| c.is_alphabetic() || *c == '/' || *c == '@')
+        .map(|(index, _)| index)
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(end);
+    if unit.chars().any(|c| c.is_ascii_digit()) {
+        return (text, "");
+    }
+    (number, unit)
+}
+
+/// Parses digits with either `.` or `,` as the decimal separator.
+///
+/// When both separators are present the last one is the decimal separator. A lone `,` is a
+/// decimal separator only when followed by one or two digits (`12,5`, `12,50`); otherwise it
+/// separates thousands (`1,234`). Spaces, apostrophes and underscores are always grouping.
+fn parse_decimal(text: &str) -> Option<Decimal> {
+    let text: String = text
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\'' | '_' | '\u{a0}'))
+        .collect();
+    if text.is_empty()
+        || !text
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+    {
+        return None;
+    }
+
+    let last_dot = text.rfind('.');
+    let last_comma = text.rfind(',');
+    let decimal_separator = match (last_dot, last_comma) {
+        (Some(dot), Some(comma)) => Some(if dot > comma { '.' } else { ',' }),
+        (Some(_), None) => (text.matches('.').count() == 1).then_some('.'),
+        (None, Some(comma)) => {
+            let decimals = text.len() - comma - 1;
+            (text.matches(',').count() == 1 && (1..=2).contains(&decimals)).then_some(',')
+        }
+        (None, None) => None,
+    };
+
+    let normalized: String = text
+        .chars()
+        .filter_map(|c| match c {
+            '0'..='9' => Some(c),
+            c if Some(c) == decimal_separator => Some('.'),
+            _ => None,
+        })
+        .collect();
+
+    Decimal::from_str(&normalized).ok()
+}
@@ -0,0 +1,301 @@
+use super::ManifestRow;
+use crate::barcode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ValidationIssue {
+    /// 1-based index of the data row (the header is not counted).
+    pub row: usize,
+    pub field: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub rows_checked: usize,
+    pub error_count: usize,
+    pub warning_count: usize,
+    pub issues: Vec<ValidationIssue>,
+}
+
+/// Reference data the rows are checked against.
+///
+/// Each list holds the accepted names and codes (e.g. `Clothing` and `CLO`), compared
+/// case-insensitively. An empty list disables the corresponding check.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ValidationContext {
+    #[serde(default)]
+    pub departments: Vec<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+impl ValidationReport {
+    pub fn has_errors(&self) -> bool {
+        self.error_count > 0
+    }
+
+    fn push(&mut self, row: usize, field: &str, severity: Severity, message: String) {
+        match severity {
+            Severity::Error => self.error_count += 1,
+            Severity::Warning => self.warning_count += 1,
+        }
+        self.issues.push(ValidationIssue {
+            row,
+            field: field.to_string(),
+            severity,
+            message,
+        });
+    }
+}
+
+/// Validates every mapped manifest row and collects the problems found into a report.
+///
+/// Checks performed per row:
+/// * the item number is present
+/// * cost, price, comp retail, case pack and cases are numeric and not negative
+/// * the Mardens price is not below the Mardens cost
+/// * UPCs are valid UPC/EAN/GTIN codes with a correct check digit
+/// * item numbers and UPCs are unique across the manifest
+/// * department and category values exist in the `context`
+pub fn validate_rows(rows: &[ManifestRow], context: &ValidationContext) -> ValidationReport {
+    let mut report = ValidationReport {
+        rows_checked: rows.len(),
+        ..Default::default()
+    };
+
+    let departments = normalize_all(&context.departments);
+    let categories = normalize_all(&context.categories);
+    let mut seen_items: HashMap<String, usize> = HashMap::new();
+    let mut seen_upcs: HashMap<String, usize> = HashMap::new();
+
+    for (index, row) in rows.iter().enumerate() {
+        let row_number = index + 1;
+
+        let item_number = row.item_number.trim();
+        if item_number.is_empty() {
+            report.push(
+                row_number,
+                "item_number",
+                Severity::Error,
+                "Item number is missing".to_string(),
+            );
+        } else if let Some(first) =
+            first_seen(&mut seen_items, item_number.to_lowercase(), row_number)
+        {
+            report.push(
+                row_number,
+                "item_number",
+                Severity::Error,
+                format!("Duplicate item number '{item_number}' (first seen on row {first})"),
+            );
+        }
+
+        let upc = row.upc.trim();
+        if !upc.is_empty() {
+            // Compare valid codes by GTIN so a UPC-E and its UPC-A expansion are duplicates
+            let key = match barcode::normalize(upc) {
+                Ok(barcode) => barcode.gtin14(),
+                Err(e) => {
+                    report.push(
+                        row_number,
+                        "upc",
+                        Severity::Error,
+                        format!("UPC '{upc}' is invalid: {e}"),
+                    );
+                    upc.to_string()
+                }
+            };
+            if let Some(first) = first_seen(&mut seen_upcs, key, row_number) {
+                report.push(
+                    row_number,
+                    "upc",
+                    Severity::Error,
+                    format!("Duplicate UPC '{upc}' (first seen on row {first})"),
+                );
+            }
+        }
+
+        let numbers = row.parse_numbers();
+        for error in &numbers.errors {
+            report.push(
+                row_number,
+                &error.field,
+                Severity::Error,
+                format!(
+                    "{} '{}' is not a number",
+                    field_label(&error.field),
+                    error.value.trim()
+                ),
+            );
+        }
+        for (field, value) in [
+            ("mardens_cost", &row.mardens_cost),
+            ("case_pack", &row.case_pack),
+        ] {
+            if value.trim().is_empty() {
+                report.push(
+                    row_number,
+                    field,
+                    Severity::Warning,
+                    format!("{} is empty", field_label(field)),
+                );
+            }
+        }
+        for (field, value) in [
+            ("mardens_cost", numbers.mardens_cost),
+            ("mardens_price", numbers.mardens_price),
+            ("comp_retail", numbers.comp_retail),
+            ("case_pack", numbers.case_pack),
+            ("cases", numbers.cases),
+        ] {
+            if let Some(value) = value
+                && value.is_sign_negative()
+                && !value.is_zero()
+            {
+                report.push(
+                    row_number,
+                    field,
+                    Severity::Error,
+                    format!("{} cannot be negative ({value})", field_label(field)),
+                );
+            }
+        }
+
+        if let (Some(cost), Some(price)) = (numbers.mardens_cost, numbers.mardens_price)
+            && price < cost
+        {
+            report.push(
+                row_number,
+                "mardens_price",
+                Severity::Warning,
+                format!("Mardens price {price} is below Mardens cost {cost}"),
+            );
+        }
+
+        check_reference(
+            &mut report,
+            row_number,
+            "department",
+            &row.department,
+            &departments,
+        );
+        check_reference(
+            &mut report,
+            row_number,
+            "category",
+            &row.category,
+            &categories,
+        );
+    }
+
+    report
+}
+
+/// The row `key` was first seen on, or `None` when this is its first row.
+fn first_seen(seen: &mut HashMap<String, usize>, key: String, row: usize) -> Option<usize> {
+    match seen.entry(key) {
+        Entry::Occupied(entry) => Some(*entry.get()),
+        Entry::Vacant(entry) => {
+            entry.insert(row);
+            None
+        }
+    }
+}
+
+fn check_reference(
+    report: &mut ValidationReport,
+    row: usize,
+    field: &str,
+    value: &str,
+    known: &[String],
+) {
+    let value = value.trim();
+    if value.is_empty() || known.is_empty() {
+        return;
+    }
+    if !known.contains(&value.to_lowercase()) {
+        report.push(
+            row,
+            field,
+            Severity::Error,
+            format!("{} '{value}' does not exist", field_label(field)),
+        );
+    }
+}
+
+fn normalize_all(values: &[String]) -> Vec<String> {
+    values.iter().map(|v| v.trim().to_lowercase()).collect()
+}
+
+fn field_label(field: &str) -> &str {
+    match field {
+        "item_number" => "Item number",
+        "upc" => "UPC",
+        "mardens_cost" => "Mardens cost",
+        "mardens_price" => "Mardens price",
+        "comp_retail" => "Comp retail",
+        "case_pack" => "Case pack",
+        "cases" => "Cases",
+        "department" => "Department",
+        "category" => "Category",
+        _ => field,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(item_number: &str, upc: &str) -> ManifestRow {
+        ManifestRow {
+            item_number: item_number.to_string(),
+            upc: upc.to_string(),
+            mardens_cost: "1.00".to_string(),
+            case_pack: "1".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn duplicates_point_at_the_first_row() {
+        let rows = [
+            row("1001", "036000291452"),
+            row("1001", "036000291452"),
+            row("1001", "036000291452"),
+        ];
+        let report = validate_rows(&rows, &ValidationContext::default());
+        assert_eq!(report.error_count, 4);
+        for issue in &report.issues {
+            assert!(issue.row > 1);
+            assert!(
+                issue.message.ends_with("(first seen on row 1)"),
+                "{}",
+                issue.message
+            );
+        }
+    }
+
+    #[test]
+    fn reports_unknown_departments() {
+        let mut department_row = row("1001", "");
+        department_row.department = "Toys".to_string();
+        let context = ValidationContext {
+            departments: vec!["Clothing".to_string(), "CLO".to_string()],
+            ..Default::default()
+        };
+        let report = validate_rows(&[department_row], &context);
+        assert_eq!(report.error_count, 1);
+        assert_eq!(report.issues[0].field, "department");
+    }
+}
@@ -0,0 +1,480 @@
+use crate::manifest::ManifestRow;
+use crate::save_file::SaveFile;
+use crate::summary::summarize;
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use rust_decimal::Decimal;
+
+/// US Letter in points.
+const PAGE_WIDTH: f32 = 612.0;
+const PAGE_HEIGHT: f32 = 792.0;
+const MARGIN: f32 = 36.0;
+/// Lowest baseline for body text; the footer sits below it.
+const BOTTOM: f32 = 54.0;
+
+const HELVETICA: Name = Name(b"F1");
+const HELVETICA_BOLD: Name = Name(b"F2");
+const COURIER: Name = Name(b"F3");
+const COURIER_BOLD: Name = Name(b"F4");
+const FONTS: [(Name, &[u8]); 4] = [
+    (HELVETICA, b"Helvetica"),
+    (HELVETICA_BOLD, b"Helvetica-Bold"),
+    (COURIER, b"Courier"),
+    (COURIER_BOLD, b"Courier-Bold"),
+];
+
+const TABLE_SIZE: f32 = 8.0;
+const TABLE_LEADING: f32 = 11.0;
+/// Courier advances 0.6 em per character, so the table columns line up exactly.
+const COURIER_ADVANCE: f32 = 0.6;
+/// Rough Helvetica advance used to wrap header text.
+const HELVETICA_ADVANCE: f32 = 0.55;
+
+/// Line table columns as (title, minimum width in characters, right aligned). Columns other
+/// than the description grow to fit their longest value.
+const COLUMNS: [(&str, usize, bool); 9] = [
+    ("Line", 4, true),
+    ("Item #", 14, false),
+    ("UPC", 14, false),
+    ("Description", 30, false),
+    ("Pack", 6, true),
+    ("Cases", 6, true),
+    ("Units", 7, true),
+    ("Unit Cost", 10, true),
+    ("Ext. Cost", 11, true),
+];
+const DESCRIPTION_COLUMN: usize = 3;
+/// Characters of Courier that fit across the page at `TABLE_SIZE`.
+const TABLE_CHARACTERS: f32 = (PAGE_WIDTH - 2.0 * MARGIN) / (TABLE_SIZE * COURIER_ADVANCE);
+
+/// Renders a printable purchase order for the vendor.
+///
+/// The first page carries the PO header, followed by the line items, which continue over as
+/// many pages as needed with the column titles repeated, then the totals. Every page is
+/// numbered. Only the standard PDF fonts are used and no timestamps are written, so the same
+/// PO always produces the same bytes wherever it is rendered.
+pub fn render_po_pdf(save_file: &SaveFile, lines: &[ManifestRow]) -> Vec<u8> {
+    let lines: Vec<&ManifestRow> = lines
+        .iter()
+        .filter(|row| {
+            [&row.item_number, &row.upc, &row.description]
+                .iter()
+                .any(|value| !value.trim().is_empty())
+        })
+        .collect();
+    let summary = summarize(&lines.iter().map(|row| (*row).clone()).collect::<Vec<_>>());
+
+    let mut layout = Layout::new(save_file.po_number.trim());
+    layout.header(save_file);
+
+    let mut rows: Vec<[String; 9]> = Vec::new();
+    for (index, (row, line)) in lines.iter().zip(&summary.lines).enumerate() {
+        let numbers = row.parse_numbers();
+        let description = wrap(row.description.trim(), COLUMNS[DESCRIPTION_COLUMN].1);
+        let mut cells = [
+            (index + 1).to_string(),
+            row.item_number.trim().to_string(),
+            row.upc.trim().to_string(),
+            description.first().cloned().unwrap_or_default(),
+            numbers
+                .case_pack
+                .map(|pack| pack.normalize().to_string())
+                .unwrap_or_default(),
+            numbers
+                .cases
+                .map(|cases| cases.normalize().to_string())
+                .unwrap_or_default(),
+            line.units.normalize().to_string(),
+            numbers.mardens_cost.map(money).unwrap_or_default(),
+            money(line.extended_cost),
+        ];
+        rows.push(cells);
+        for continuation in description.iter().skip(1) {
+            cells = Default::default();
+            cells[DESCRIPTION_COLUMN] = continuation.clone();
+            rows.push(cells);
+        }
+    }
+
+    let totals = &summary.totals;
+    let mut cells: [String; 9] = Default::default();
+    cells[1] = "Total".to_string();
+    cells[DESCRIPTION_COLUMN] = format!(
+        "{} line{}",
+        totals.lines,
+        if totals.lines == 1 { "" } else { "s" }
+    );
+    cells[6] = totals.units.normalize().to_string();
+    cells[8] = money(totals.extended_cost);
+
+    layout.start_table(rows.iter().chain([&cells]));
+    for row in &rows {
+        layout.table_row(row, false);
+    }
+    layout.rule();
+    layout.table_row(&cells, true);
+
+    layout.finish()
+}
+
+/// Lays out text top to bottom, breaking onto new pages as needed.
+struct Layout {
+    po_number: String,
+    pages: Vec<Content>,
+    /// Baseline of the next line on the current page.
+    y: f32,
+    /// Set once the line table has started, so new pages repeat the column titles.
+    in_table: bool,
+    /// Width in characters of each table column.
+    widths: [usize; 9],
+    /// Font size of the table, below `TABLE_SIZE` when wide values don't fit otherwise.
+    table_size: f32,
+}
+
+impl Layout {
+    fn new(po_number: &str) -> Self {
+        Self {
+            po_number: po_number.to_string(),
+            pages: vec![Content::new()],
+            y: PAGE_HEIGHT - MARGIN,
+            in_table: false,
+            widths: COLUMNS.map(|(_, width, _)| width),
+            table_size: TABLE_SIZE,
+        }
+    }
+
+    fn page(&mut self) -> &mut Content {
+        self.pages.last_mut().expect("the layout always has a page")
+    }
+
+    /// Makes room for a line of `height`, starting a new page when it doesn't fit.
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < BOTTOM {
+            self.new_page();
+        }
+        self.y -= height;
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Content::new());
+        self.y = PAGE_HEIGHT - MARGIN - 12.0;
+        let continued = format!("Purchase Order {} (continued)", self.po_number);
+        self.text(HELVETICA_BOLD, 10.0, MARGIN, &continued);
+        self.y -= 8.0;
+        if self.in_table {
+            self.column_titles();
+        }
+    }
+
+    fn text(&mut self, font: Name, size: f32, x: f32, text: &str) {
+        let y = self.y;
+        let bytes = encode(text);
+        self.page()
+            .begin_text()
+            .set_font(font, size)
+            .next_line(x, y)
+            .show(Str(&bytes))
+            .end_text();
+    }
+
+    fn rule(&mut self) {
+        let y = self.y - 3.0;
+        self.page()
+            .set_line_width(0.5)
+            .move_to(MARGIN, y)
+            .line_to(PAGE_WIDTH - MARGIN, y)
+            .stroke();
+    }
+
+    fn header(&mut self, save_file: &SaveFile) {
+        self.reserve(18.0);
+        self.text(HELVETICA_BOLD, 18.0, MARGIN, "Purchase Order");
+        self.reserve(18.0);
+        let po_number = format!("PO {}", self.po_number);
+        self.text(HELVETICA_BOLD, 12.0, MARGIN, &po_number);
+        self.y -= 10.0;
+
+        let left = [
+            ("Vendor", save_file.vendor.as_str()),
+            ("Order date", save_file.order_date.as_str()),
+            (
+                "Ship date",
+                save_file.ship_date.as_deref().unwrap_or_default(),
+            ),
+            (
+                "Cancel date",
+                save_file.cancel_date.as_deref().unwrap_or_default(),
+            ),
+            ("Terms", save_file.terms.as_str()),
+        ];
+        let right = [
+            ("FOB", save_file.fob_type.as_str()),
+            ("FOB point", save_file.fob_point.as_str()),
+            ("Ship to", save_file.ship_to_address.as_str()),
+        ];
+        let top = self.y;
+        self.fields(&left, MARGIN, 74.0, 300.0);
+        let left_bottom = self.y;
+        self.y = top;
+        self.fields(&right, 320.0, 60.0, PAGE_WIDTH - MARGIN);
+        self.y = self.y.min(left_bottom) - 8.0;
+
+        for (label, value) in [
+            ("Description", save_file.description.as_str()),
+            ("Shipping notes", save_file.shipping_notes.as_str()),
+            ("Notes", save_file.notes.as_str()),
+        ] {
+            if value.trim().is_empty() {
+                continue;
+            }
+            self.reserve(13.0);
+            self.text(HELVETICA_BOLD, 9.0, MARGIN, label);
+            self.paragraph(value, MARGIN, PAGE_WIDTH - MARGIN);
+            self.y -= 6.0;
+        }
+        self.y -= 6.0;
+    }
+
+    /// Label and value pairs; values wrap within `value_offset..right`.
+    fn fields(&mut self, fields: &[(&str, &str)], x: f32, value_offset: f32, right: f32) {
+        for (label, value) in fields {
+            let value_lines = wrap_width(value.trim(), x + value_offset, right, 9.0);
+            for (index, line) in value_lines.iter().enumerate() {
+                self.reserve(12.0);
+                if index == 0 {
+                    self.text(HELVETICA_BOLD, 9.0, x, label);
+                }
+                self.text(HELVETICA, 9.0, x + value_offset, line);
+            }
+        }
+    }
+
+    fn paragraph(&mut self, text: &str, left: f32, right: f32) {
+        for line in text.lines() {
+            for wrapped in wrap_width(line.trim(), left, right, 9.0) {
+                self.reserve(12.0);
+                self.text(HELVETICA, 9.0, left, &wrapped);
+            }
+        }
+    }
+
+    /// Sizes the columns to fit every value in `rows`, so numbers are never cut off, and
+    /// shrinks the table font when the widened table would run past the margin.
+    fn start_table<'a>(&mut self, rows: impl Iterator<Item = &'a [String; 9]>) {
+        for row in rows {
+            for (column, cell) in row.iter().enumerate() {
+                if column != DESCRIPTION_COLUMN {
+                    self.widths[column] = self.widths[column].max(cell.chars().count());
+                }
+            }
+        }
+        let characters = self.widths.iter().sum::<usize>() + self.widths.len() - 1;
+        self.table_size = TABLE_SIZE * (TABLE_CHARACTERS / characters as f32).min(1.0);
+
+        // Start on a new page rather than leave the titles alone at the bottom of this one.
+        if self.y - 3.0 * self.table_leading() < BOTTOM {
+            self.new_page();
+        }
+        self.in_table = true;
+        self.column_titles();
+    }
+
+    fn column_titles(&mut self) {
+        let titles = COLUMNS.map(|(title, _, _)| title.to_string());
+        self.table_row(&titles, true);
+        self.rule();
+    }
+
+    fn table_leading(&self) -> f32 {
+        TABLE_LEADING * self.table_size / TABLE_SIZE
+    }
+
+    fn table_row(&mut self, cells: &[String; 9], bold: bool) {
+        self.reserve(self.table_leading());
+        let row = COLUMNS
+            .iter()
+            .zip(self.widths)
+            .zip(cells)
+            .map(|(((_, _, right), width), cell)| {
+                if *right {
+                    format!("{:>width$}", cell, width = width)
+                } else {
+                    format!("{:<width$}", cell, width = width)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let font = if bold { COURIER_BOLD } else { COURIER };
+        self.text(font, self.table_size, MARGIN, row.trim_end());
+    }
+
+    /// Writes the page footers and assembles the document.
+    fn finish(self) -> Vec<u8> {
+        let page_count = self.pages.len();
+        let catalog_id = Ref::new(1);
+        let page_tree_id = Ref::new(2);
+        let font_ids: Vec<Ref> = (0..FONTS.len() as i32).map(|i| Ref::new(3 + i)).collect();
+        let first_page = 3 + FONTS.len() as i32;
+        let page_ids: Vec<Ref> = (0..page_count as i32)
+            .map(|i| Ref::new(first_page + 2 * i))
+            .collect();
+        let info_id = Ref::new(first_page + 2 * page_count as i32);
+
+        let mut pdf = Pdf::new();
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        pdf.pages(page_tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_count as i32);
+        for ((_, base_font), id) in FONTS.iter().zip(&font_ids) {
+            pdf.type1_font(*id)
+                .base_font(Name(base_font))
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+
+        for (index, mut content) in self.pages.into_iter().enumerate() {
+            let footer = format!(
+                "PO {} - Page {} of {}",
+                self.po_number,
+                index + 1,
+                page_count
+            );
+            let width = footer.chars().count() as f32 * TABLE_SIZE * COURIER_ADVANCE;
+            content
+                .begin_text()
+                .set_font(COURIER, TABLE_SIZE)
+                .next_line((PAGE_WIDTH - width) / 2.0, MARGIN - 12.0)
+                .show(Str(&encode(&footer)))
+                .end_text();
+
+            let page_id = page_ids[index];
+            let content_id = Ref::new(page_id.get() + 1);
+            let mut page = pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+            page.parent(page_tree_id);
+            page.contents(content_id);
+            let mut resources = page.resources();
+            let mut fonts = resources.fonts();
+            for ((name, _), id) in FONTS.iter().zip(&font_ids) {
+                fonts.pair(*name, *id);
+            }
+            fonts.finish();
+            resources.finish();
+            page.finish();
+            pdf.stream(content_id, &content.finish());
+        }
+
+        let title = format!("Purchase Order {}", self.po_number);
+        pdf.document_info(info_id).title(TextStr(&title));
+        pdf.finish()
+    }
+}
+
+fn money(value: Decimal) -> String {
+    format!("{:.2}", value)
+}
+
+/// Converts text to WinAnsi bytes. Latin-1 and the common typographic characters map
+/// directly; anything else becomes `?`.
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            '\u{20}'..='\u{7E}' | '\u{A0}'..='\u{FF}' => c as u8,
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '™' => 0x99,
+            _ if c.is_whitespace() => b' ',
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Wraps Helvetica text to fit between `left` and `right` at `size`.
+fn wrap_width(text: &str, left: f32, right: f32, size: f32) -> Vec<String> {
+    let width = ((right - left) / (size * HELVETICA_ADVANCE))
+        .floor()
+        .max(1.0) as usize;
+    wrap(text, width)
+}
+
+/// Wraps at word boundaries to at most `width` characters per line, splitting longer words.
+/// Empty text gives a single empty line so the field still takes its place.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        while word.len() > width {
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            lines.push(word.drain(..width).collect());
+        }
+        let word: String = word.into_iter().collect();
+        if line.is_empty() {
+            line = word;
+        } else if line.chars().count() + 1 + word.chars().count() <= width {
+            line.push(' ');
+            line.push_str(&word);
+        } else {
+            lines.push(std::mem::replace(&mut line, word));
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save_file() -> SaveFile {
+        serde_json::from_value(serde_json::json!({
+            "version": "1.0.0",
+            "po_number": "12345",
+            "buyer_id": 1,
+            "vendor": "Acme Wholesale",
+            "order_date": "2026-10-01",
+            "ship_date": null,
+            "cancel_date": null,
+            "shipping_notes": "",
+            "description": "",
+            "terms": "",
+            "ship_to_address": "",
+            "fob_type": "Pickup",
+            "fob_point": "",
+            "notes": "",
+            "manifests": [],
+            "assets": []
+        }))
+        .unwrap()
+    }
+
+    fn contains(haystack: &[u8], needle: &str) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle.as_bytes())
+    }
+
+    #[test]
+    fn wide_numbers_are_not_cut_off() {
+        let row = ManifestRow {
+            item_number: "A100".to_string(),
+            description: "Blue widget".to_string(),
+            case_pack: "1200".to_string(),
+            cases: "987654".to_string(),
+            mardens_cost: "12345.67".to_string(),
+            ..Default::default()
+        };
+        let pdf = render_po_pdf(&save_file(), &[row]);
+        assert!(contains(&pdf, "1185184800"));
+        assert!(contains(&pdf, "14631900429816.00"));
+    }
+}
@@ -0,0 +1,372 @@
+use crate::manifest::ManifestRow;
+use crate::summary::margin_percent;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How a price is derived from the line's cost or comp retail.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PriceBasis {
+    /// `cost × (1 + percent / 100)`
+    Markup { percent: Decimal },
+    /// `comp_retail × (1 - percent / 100)`
+    PercentOffComp { percent: Decimal },
+}
+
+/// Allowed cents of a suggested price.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceEnding {
+    #[serde(rename = ".99")]
+    NinetyNine,
+    #[serde(rename = ".49")]
+    FortyNine,
+    #[serde(rename = ".00")]
+    Zero,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PricingRule {
+    /// Tried in order; the first one the line has the numbers for is used.
+    pub bases: Vec<PriceBasis>,
+    /// When empty the price is only rounded to the cent.
+    #[serde(default)]
+    pub endings: Vec<PriceEnding>,
+    #[serde(default)]
+    pub min_margin_percent: Option<Decimal>,
+    #[serde(default)]
+    pub cap_at_comp: bool,
+}
+
+/// Pricing rules keyed by department name or code, with a rule for everything else.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PricingConfig {
+    pub default_rule: PricingRule,
+    #[serde(default)]
+    pub departments: HashMap<String, PricingRule>,
+}
+
+/// Which `mardens_price` values get replaced by the suggestion.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FillMode {
+    /// Only report suggestions.
+    None,
+    /// Fill lines without a price.
+    #[default]
+    Empty,
+    /// Replace every price.
+    All,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionStatus {
+    /// The price was empty (or replaced) and set to the suggestion.
+    Filled,
+    /// The line's price already matches the suggestion.
+    Matches,
+    /// The line has a price that differs from the suggestion; it was left alone.
+    Differs,
+    /// The margin floor and the comp cap can't both be met; the capped price is suggested and
+    /// the row is left unchanged.
+    Conflict,
+    /// None of the rule's bases could be applied.
+    NoSuggestion,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PriceSuggestion {
+    /// 1-based index of the data row.
+    pub row: usize,
+    pub item_number: String,
+    /// Department key of the rule used, or `default`.
+    pub rule: String,
+    pub current_price: Option<Decimal>,
+    pub suggested_price: Option<Decimal>,
+    pub status: SuggestionStatus,
+    /// Each step that produced the price, e.g. `Markup 100% on cost $1.50 = $3.00`.
+    pub explanation: Vec<String>,
+}
+
+/// The manifest rows after pricing, with a suggestion per row.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PricedManifest {
+    pub rows: Vec<ManifestRow>,
+    pub suggestions: Vec<PriceSuggestion>,
+}
+
+impl PricingConfig {
+    fn rule_for(&self, department: &str) -> (&str, &PricingRule) {
+        let department = department.trim();
+        self.departments
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(department))
+            .map(|(key, rule)| (key.as_str(), rule))
+            .unwrap_or(("default", &self.default_rule))
+    }
+
+    /// Suggests a `mardens_price` for every row and fills them according to `fill`.
+    pub fn suggest_prices(&self, rows: &mut [ManifestRow], fill: FillMode) -> Vec<PriceSuggestion> {
+        rows.iter_mut()
+            .enumerate()
+            .map(|(index, row)| {
+                let (key, rule) = self.rule_for(&row.department);
+                let numbers = row.parse_numbers();
+                let mut suggestion = PriceSuggestion {
+                    row: index + 1,
+                    item_number: row.item_number.clone(),
+                    rule: key.to_string(),
+                    current_price: numbers.mardens_price,
+                    suggested_price: None,
+                    status: SuggestionStatus::NoSuggestion,
+                    explanation: Vec::new(),
+                };
+
+                let Some((price, conflict)) = rule.apply(
+                    numbers.mardens_cost,
+                    numbers.comp_retail,
+                    &mut suggestion.explanation,
+                ) else {
+                    return suggestion;
+                };
+                suggestion.suggested_price = Some(price);
+
+                let replace = match fill {
+                    FillMode::None => false,
+                    FillMode::Empty => row.mardens_price.trim().is_empty(),
+                    FillMode::All => true,
+                };
+                // A conflicting price needs the buyer's call, so the row is left as it was.
+                suggestion.status = if conflict {
+                    SuggestionStatus::Conflict
+                } else if replace {
+                    row.mardens_price = price.to_string();
+                    SuggestionStatus::Filled
+                } else if numbers.mardens_price == Some(price) {
+                    SuggestionStatus::Matches
+                } else {
+                    SuggestionStatus::Differs
+                };
+                suggestion
+            })
+            .collect()
+    }
+}
+
+impl PricingRule {
+    /// Computes the price, logging each step. The flag is set when the margin floor had to
+    /// give way to the comp cap.
+    fn apply(
+        &self,
+        cost: Option<Decimal>,
+        comp: Option<Decimal>,
+        explanation: &mut Vec<String>,
+    ) -> Option<(Decimal, bool)> {
+        let raw = self
+            .bases
+            .iter()
+            .find_map(|basis| match (basis, cost, comp) {
+                (PriceBasis::Markup { percent }, Some(cost), _) => {
+                    let Some(price) = Decimal::ONE
+                        .checked_add(percent / Decimal::ONE_HUNDRED)
+                        .and_then(|factor| cost.checked_mul(factor))
+                    else {
+                        explanation.push(format!(
+                            "Markup {}% on cost ${:.2} is too large",
+                            percent, cost
+                        ));
+                        return None;
+                    };
+                    explanation.push(format!(
+                        "Markup {}% on cost ${:.2} = ${:.2}",
+                        percent, cost, price
+                    ));
+                    Some(price)
+                }
+                (PriceBasis::PercentOffComp { percent }, _, Some(comp)) => {
+                    let Some(price) = Decimal::ONE
+                        .checked_sub(percent / Decimal::ONE_HUNDRED)
+                        .and_then(|factor| comp.checked_mul(factor))
+                    else {
+                        explanation.push(format!(
+                            "{}% off comp retail ${:.2} is too large",
+                            percent, comp
+                        ));
+                        return None;
+                    };
+                    explanation.push(format!(
+                        "{}% off comp retail ${:.2} = ${:.2}",
+                        percent, comp, price
+                    ));
+                    Some(price)
+                }
+                _ => None,
+            });
+        let Some(raw) = raw else {
+            explanation.push("No cost or comp retail for any of the rule's bases".to_string());
+            return None;
+        };
+
+        let mut price = self.round_nearest(raw);
+        if !self.endings.is_empty() {
+            explanation.push(format!(
+                "Rounded to the nearest price ending: ${:.2}",
+                price
+            ));
+        }
+
+        if let (Some(floor), Some(cost)) = (self.min_margin_percent, cost)
+            && margin_percent(cost, price).is_none_or(|margin| margin < floor)
+            && floor < Decimal::ONE_HUNDRED
+        {
+            let Some(minimum) = cost.checked_div(Decimal::ONE - floor / Decimal::ONE_HUNDRED)
+            else {
+                explanation.push(format!(
+                    "The {}% minimum margin on cost ${:.2} is too large to price",
+                    floor, cost
+                ));
+                return None;
+            };
+            price = self.round_up(minimum);
+            explanation.push(format!(
+                "Raised to ${:.2} to keep the {}% minimum margin",
+                price, floor
+            ));
+        }
+
+        let mut conflict = false;
+        if self.cap_at_comp
+            && let Some(comp) = comp
+            && price > comp
+        {
+            price = self.round_down(comp);
+            explanation.push(format!("Capped at comp retail ${:.2}: ${:.2}", comp, price));
+
+            if let (Some(floor), Some(cost)) = (self.min_margin_percent, cost)
+                && margin_percent(cost, price).is_none_or(|margin| margin < floor)
+            {
+                conflict = true;
+                explanation.push(format!(
+                    "The {}% minimum margin can't be met under comp retail",
+                    floor
+                ));
+            }
+        }
+
+        price.rescale(2);
+        Some((price, conflict))
+    }
+
+    /// Prices with an allowed ending in the dollars around `value`, in ascending order.
+    fn candidates(&self, value: Decimal) -> Vec<Decimal> {
+        let dollars = value.floor();
+        let mut candidates: Vec<Decimal> = [
+            dollars.checked_sub(Decimal::ONE),
+            Some(dollars),
+            dollars.checked_add(Decimal::ONE),
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(|dollars| {
+            self.endings
+                .iter()
+                .filter_map(move |ending| dollars.checked_add(ending.cents()))
+        })
+        .filter(|price| price.is_sign_positive() && !price.is_zero())
+        .collect();
+        candidates.sort();
+        candidates
+    }
+
+    fn round_nearest(&self, value: Decimal) -> Decimal {
+        self.candidates(value)
+            .into_iter()
+            .min_by_key(|price| ((*price - value).abs(), -*price))
+            .unwrap_or_else(|| value.round_dp(2))
+    }
+
+    fn round_up(&self, value: Decimal) -> Decimal {
+        self.candidates(value)
+            .into_iter()
+            .find(|price| *price >= value)
+            .unwrap_or_else(|| {
+                value.round_dp_with_strategy(2, RoundingStrategy::ToPositiveInfinity)
+            })
+    }
+
+    fn round_down(&self, value: Decimal) -> Decimal {
+        self.candidates(value)
+            .into_iter()
+            .rev()
+            .find(|price| *price <= value)
+            .unwrap_or_else(|| {
+                value.round_dp_with_strategy(2, RoundingStrategy::ToNegativeInfinity)
+            })
+    }
+}
+
+impl PriceEnding {
+    fn cents(self) -> Decimal {
+        match self {
+            PriceEnding::NinetyNine => Decimal::new(99, 2),
+            PriceEnding::FortyNine => Decimal::new(49, 2),
+            PriceEnding::Zero => Decimal::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(rule: PricingRule) -> PricingConfig {
+        PricingConfig {
+            default_rule: rule,
+            departments: HashMap::new(),
+        }
+    }
+
+    fn row(cost: &str, price: &str, comp: &str) -> ManifestRow {
+        ManifestRow {
+            item_number: "A100".to_string(),
+            mardens_cost: cost.to_string(),
+            mardens_price: price.to_string(),
+            comp_retail: comp.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn conflict_leaves_row_unchanged() {
+        let config = config(PricingRule {
+            bases: vec![PriceBasis::Markup {
+                percent: Decimal::ONE_HUNDRED,
+            }],
+            endings: vec![PriceEnding::NinetyNine],
+            min_margin_percent: Some(Decimal::from(40)),
+            cap_at_comp: true,
+        });
+        let mut rows = vec![row("4.00", "", "5.00")];
+        let suggestions = config.suggest_prices(&mut rows, FillMode::All);
+        assert_eq!(suggestions[0].status, SuggestionStatus::Conflict);
+        assert_eq!(suggestions[0].suggested_price, Some(Decimal::new(499, 2)));
+        assert_eq!(rows[0].mardens_price, "");
+    }
+
+    #[test]
+    fn overflowing_markup_has_no_suggestion() {
+        let config = config(PricingRule {
+            bases: vec![PriceBasis::Markup {
+                percent: Decimal::from(1000),
+            }],
+            endings: Vec::new(),
+            min_margin_percent: None,
+            cap_at_comp: false,
+        });
+        let mut rows = vec![row("79228162514264337593543950335", "", "")];
+        let suggestions = config.suggest_prices(&mut rows, FillMode::Empty);
+        assert_eq!(suggestions[0].status, SuggestionStatus::NoSuggestion);
+        assert_eq!(rows[0].mardens_price, "");
+    }
+}
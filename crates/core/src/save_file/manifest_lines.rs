@@ -0,0 +1,168 @@
+use super::{ManifestItem, SaveFile};
+use crate::manifest::{MANIFEST_FIELDS, ManifestRow};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Identifies a manifest line by its item number, so edits stay on the same line when the
+/// rows of the source file move.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LineId {
+    /// Item number as read from the source file.
+    pub item_number: String,
+    /// 1-based count among the lines with this item number, for files that list an item more
+    /// than once.
+    pub occurrence: usize,
+}
+
+/// A buyer's edit to one cell of a manifest line.
+///
+/// Overrides are kept apart from the normalized rows, so the values from the vendor's file
+/// are never changed and an edit can be reverted.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CellOverride {
+    #[serde(flatten)]
+    pub line: LineId,
+    /// Template field, e.g. `mardens_cost`.
+    pub field: String,
+    pub value: String,
+}
+
+/// The id of every row, in row order.
+fn line_ids(rows: &[ManifestRow]) -> Vec<LineId> {
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+    rows.iter()
+        .map(|row| {
+            let item_number = row.item_number.trim();
+            let occurrence = occurrences.entry(item_number).or_default();
+            *occurrence += 1;
+            LineId {
+                item_number: item_number.to_string(),
+                occurrence: *occurrence,
+            }
+        })
+        .collect()
+}
+
+impl ManifestItem {
+    /// Stores freshly mapped rows from the source file.
+    ///
+    /// Overrides for lines that no longer exist are dropped, the rest are kept so edits survive
+    /// a remap. Overrides that now match the source value are dropped as well.
+    pub fn set_rows(&mut self, rows: Vec<ManifestRow>) {
+        self.rows = rows;
+        let lines: HashMap<LineId, &ManifestRow> =
+            line_ids(&self.rows).into_iter().zip(&self.rows).collect();
+        self.overrides.retain(|cell| {
+            lines
+                .get(&cell.line)
+                .and_then(|row| row.field(&cell.field))
+                .is_some_and(|original| original != cell.value)
+        });
+    }
+
+    /// Returns the stored rows with the overrides applied.
+    pub fn lines(&self) -> Vec<ManifestRow> {
+        let mut lines = self.rows.clone();
+        let indexes: HashMap<LineId, usize> = line_ids(&self.rows)
+            .into_iter()
+            .enumerate()
+            .map(|(index, id)| (id, index))
+            .collect();
+        for cell in &self.overrides {
+            if let Some(&index) = indexes.get(&cell.line) {
+                lines[index].set_field(&cell.field, cell.value.clone());
+            }
+        }
+        lines
+    }
+
+    /// Sets or, with `None`, clears the override of one cell of the 1-based `row`.
+    ///
+    /// Setting a cell back to its source value clears the override.
+    pub fn set_override(&mut self, row: usize, field: &str, value: Option<String>) -> Result<()> {
+        if !MANIFEST_FIELDS.contains(&field) {
+            return Err(anyhow!("Unknown manifest field: {}", field));
+        }
+        let index = row.wrapping_sub(1);
+        let original = self
+            .rows
+            .get(index)
+            .and_then(|line| line.field(field))
+            .ok_or_else(|| anyhow!("Row {} does not exist in {}", row, self.filename))?;
+        let line = line_ids(&self.rows).swap_remove(index);
+
+        self.overrides
+            .retain(|cell| !(cell.line == line && cell.field == field));
+        if let Some(value) = value
+            && value != original
+        {
+            self.overrides.push(CellOverride {
+                line,
+                field: field.to_string(),
+                value,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl SaveFile {
+    /// All stored lines of the PO, manifest by manifest, with the buyer's edits applied.
+    pub fn lines(&self) -> Vec<ManifestRow> {
+        self.manifests
+            .iter()
+            .flat_map(ManifestItem::lines)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(item_number: &str, cost: &str) -> ManifestRow {
+        ManifestRow {
+            item_number: item_number.to_string(),
+            mardens_cost: cost.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn item(rows: Vec<ManifestRow>) -> ManifestItem {
+        ManifestItem {
+            filename: "acme.csv".to_string(),
+            path: "acme.csv".to_string(),
+            mappings: HashMap::new(),
+            rows,
+            overrides: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn override_follows_line_when_rows_move() {
+        let mut item = item(vec![row("A100", "1.00"), row("B200", "2.00")]);
+        item.set_override(2, "mardens_cost", Some("2.50".to_string()))
+            .unwrap();
+
+        item.set_rows(vec![
+            row("C300", "3.00"),
+            row("A100", "1.00"),
+            row("B200", "2.00"),
+        ]);
+        let lines = item.lines();
+        assert_eq!(lines[0].mardens_cost, "3.00");
+        assert_eq!(lines[2].mardens_cost, "2.50");
+    }
+
+    #[test]
+    fn override_is_dropped_when_line_is_gone() {
+        let mut item = item(vec![row("A100", "1.00"), row("A100", "1.00")]);
+        item.set_override(2, "mardens_cost", Some("1.25".to_string()))
+            .unwrap();
+        assert_eq!(item.overrides[0].line.occurrence, 2);
+
+        item.set_rows(vec![row("A100", "1.00")]);
+        assert!(item.overrides.is_empty());
+    }
+}
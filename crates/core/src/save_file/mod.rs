@@ -1,3 +1,9 @@
+mod manifest_lines;
+
+pub use manifest_lines::{CellOverride, LineId};
+
+use crate::manifest::ManifestRow;
+use crate::summary::{summarize, PoSummary};
 use anyhow::{anyhow, Result};
 use log::{debug, info};
 use serde::{Deserialize, Serialize};
@@ -22,6 +28,9 @@ pub struct SaveFile {
     pub notes: String,
     pub manifests: Vec<ManifestItem>,
     pub assets: Vec<AssetFile>,
+    /// Totals of the stored lines, worked out again on every save.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<PoSummary>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -29,6 +38,12 @@ pub struct ManifestItem {
     pub filename: String,
     pub path: String,
     pub mappings: HashMap<String, String>,
+    /// Normalized lines as mapped from the source file; empty until the file is first loaded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rows: Vec<ManifestRow>,
+    /// Buyer edits on top of `rows`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<CellOverride>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -201,6 +216,13 @@ impl SaveFile {
             notes: self.notes.clone(),
             manifests: self.manifests.clone(),
             assets: self.assets.clone(),
+            summary: self.current_summary(),
         }
     }
+
+    /// Summarizes the stored lines with the buyer's edits; `None` until a manifest has lines.
+    fn current_summary(&self) -> Option<PoSummary> {
+        let lines = self.lines();
+        (!lines.is_empty()).then(|| summarize(&lines))
+    }
 }
@@ -0,0 +1,250 @@
+use crate::manifest::ManifestRow;
+use crate::manifest::numbers::{FieldError, RowNumbers};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Group key used for rows that leave the department, category or season empty.
+pub const UNASSIGNED: &str = "Unassigned";
+
+/// Financial figures for a single manifest line.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LineSummary {
+    /// 1-based index of the data row.
+    pub row: usize,
+    pub item_number: String,
+    /// `case_pack` × `cases`; a missing case pack counts as 1.
+    pub units: Decimal,
+    /// `units` × `mardens_cost`
+    pub extended_cost: Decimal,
+    /// `units` × `mardens_price`
+    pub extended_retail: Decimal,
+    /// `units` × `comp_retail`
+    pub extended_comp_retail: Decimal,
+    pub margin_percent: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SummaryTotals {
+    pub lines: usize,
+    pub units: Decimal,
+    pub extended_cost: Decimal,
+    pub extended_retail: Decimal,
+    pub extended_comp_retail: Decimal,
+    /// Margin at Mardens price over the lines that have both a cost and a price.
+    pub margin_percent: Option<Decimal>,
+    #[serde(skip)]
+    priced_cost: Decimal,
+    #[serde(skip)]
+    priced_retail: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GroupSummary {
+    pub key: String,
+    pub totals: SummaryTotals,
+}
+
+/// A numeric value that couldn't be parsed while summarizing; the line is counted as zero for it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LineError {
+    pub row: usize,
+    #[serde(flatten)]
+    pub error: FieldError,
+}
+
+/// Totals for a whole PO, rolled up by department, category and season.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PoSummary {
+    pub totals: SummaryTotals,
+    pub lines: Vec<LineSummary>,
+    pub by_department: Vec<GroupSummary>,
+    pub by_category: Vec<GroupSummary>,
+    pub by_season: Vec<GroupSummary>,
+    pub errors: Vec<LineError>,
+}
+
+impl SummaryTotals {
+    /// Adds a line, or returns `None` when the totals would overflow.
+    fn checked_add(&self, line: &LineSummary) -> Option<Self> {
+        let mut totals = self.clone();
+        totals.lines += 1;
+        totals.units = totals.units.checked_add(line.units)?;
+        totals.extended_cost = totals.extended_cost.checked_add(line.extended_cost)?;
+        totals.extended_retail = totals.extended_retail.checked_add(line.extended_retail)?;
+        totals.extended_comp_retail = totals
+            .extended_comp_retail
+            .checked_add(line.extended_comp_retail)?;
+        if line.margin_percent.is_some() {
+            totals.priced_cost = totals.priced_cost.checked_add(line.extended_cost)?;
+            totals.priced_retail = totals.priced_retail.checked_add(line.extended_retail)?;
+            totals.margin_percent = margin_percent(totals.priced_cost, totals.priced_retail);
+        }
+        Some(totals)
+    }
+
+    fn can_add(&self, line: &LineSummary) -> bool {
+        self.checked_add(line).is_some()
+    }
+
+    /// Adds a line that [`can_add`](Self::can_add) accepted; a zero line always fits.
+    fn add(&mut self, line: &LineSummary) {
+        if let Some(totals) = self.checked_add(line) {
+            *self = totals;
+        }
+    }
+}
+
+/// Computes the margin at `retail` as a percentage rounded to two decimals.
+pub fn margin_percent(cost: Decimal, retail: Decimal) -> Option<Decimal> {
+    if retail.is_zero() {
+        return None;
+    }
+    let margin = retail.checked_sub(cost)?.checked_div(retail)?;
+    Some(margin.checked_mul(Decimal::ONE_HUNDRED)?.round_dp(2))
+}
+
+impl LineSummary {
+    /// Computes a line's figures, or `None` when one of them is too large for a decimal.
+    fn new(row: usize, item_number: &str, numbers: &RowNumbers) -> Option<Self> {
+        let units = numbers
+            .case_pack
+            .unwrap_or(Decimal::ONE)
+            .checked_mul(numbers.cases.unwrap_or_default())?;
+        Some(LineSummary {
+            row,
+            item_number: item_number.to_string(),
+            units,
+            extended_cost: units.checked_mul(numbers.mardens_cost.unwrap_or_default())?,
+            extended_retail: units.checked_mul(numbers.mardens_price.unwrap_or_default())?,
+            extended_comp_retail: units.checked_mul(numbers.comp_retail.unwrap_or_default())?,
+            margin_percent: numbers
+                .mardens_price
+                .and_then(|price| margin_percent(numbers.mardens_cost?, price)),
+        })
+    }
+
+    /// A line that is counted with no units or amounts.
+    fn zero(row: usize, item_number: &str) -> Self {
+        LineSummary {
+            row,
+            item_number: item_number.to_string(),
+            units: Decimal::ZERO,
+            extended_cost: Decimal::ZERO,
+            extended_retail: Decimal::ZERO,
+            extended_comp_retail: Decimal::ZERO,
+            margin_percent: None,
+        }
+    }
+}
+
+fn overflow_error(row: usize, row_data: &ManifestRow) -> LineError {
+    LineError {
+        row,
+        error: FieldError {
+            field: "cases".to_string(),
+            value: row_data.cases.clone(),
+            message: "Quantity and amounts are too large to total".to_string(),
+        },
+    }
+}
+
+/// Summarizes the full set of manifest rows of a PO.
+///
+/// Costs and prices are per unit. Unparsable values are reported in `errors` and count as zero,
+/// so the totals still cover every line. Lines whose figures are too large to total are
+/// reported the same way and count as zero.
+pub fn summarize(rows: &[ManifestRow]) -> PoSummary {
+    let mut summary = PoSummary::default();
+    let mut departments: BTreeMap<String, SummaryTotals> = BTreeMap::new();
+    let mut categories: BTreeMap<String, SummaryTotals> = BTreeMap::new();
+    let mut seasons: BTreeMap<String, SummaryTotals> = BTreeMap::new();
+
+    for (index, row) in rows.iter().enumerate() {
+        let row_number = index + 1;
+        let numbers = row.parse_numbers();
+        summary
+            .errors
+            .extend(numbers.errors.iter().cloned().map(|error| LineError {
+                row: row_number,
+                error,
+            }));
+
+        let totals = [
+            &mut summary.totals,
+            departments.entry(group_key(&row.department)).or_default(),
+            categories.entry(group_key(&row.category)).or_default(),
+            seasons.entry(group_key(&row.season)).or_default(),
+        ];
+        let line = match LineSummary::new(row_number, &row.item_number, &numbers)
+            .filter(|line| totals.iter().all(|totals| totals.can_add(line)))
+        {
+            Some(line) => line,
+            None => {
+                summary.errors.push(overflow_error(row_number, row));
+                LineSummary::zero(row_number, &row.item_number)
+            }
+        };
+        for totals in totals {
+            totals.add(&line);
+        }
+        summary.lines.push(line);
+    }
+
+    summary.by_department = into_groups(departments);
+    summary.by_category = into_groups(categories);
+    summary.by_season = into_groups(seasons);
+    summary
+}
+
+fn group_key(value: &str) -> String {
+    let value = value.trim();
+    if value.is_empty() {
+        UNASSIGNED.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn into_groups(groups: BTreeMap<String, SummaryTotals>) -> Vec<GroupSummary> {
+    groups
+        .into_iter()
+        .map(|(key, totals)| GroupSummary { key, totals })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(case_pack: &str, cases: &str, cost: &str) -> ManifestRow {
+        ManifestRow {
+            item_number: "1001".to_string(),
+            case_pack: case_pack.to_string(),
+            cases: cases.to_string(),
+            mardens_cost: cost.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overflowing_line_counts_as_zero() {
+        let huge = "79228162514264337593543950335";
+        let summary = summarize(&[row("12", "2", "1.50"), row(huge, "2", "1")]);
+        assert_eq!(summary.totals.lines, 2);
+        assert_eq!(summary.totals.units, Decimal::from(24));
+        assert_eq!(summary.totals.extended_cost, Decimal::from(36));
+        assert_eq!(summary.lines[1].units, Decimal::ZERO);
+        assert_eq!(summary.errors.len(), 1);
+        assert_eq!(summary.errors[0].row, 2);
+    }
+
+    #[test]
+    fn overflowing_totals_leave_earlier_lines() {
+        let big = "50000000000000000000000000000";
+        let summary = summarize(&[row("1", big, "1"), row("1", big, "1")]);
+        assert_eq!(summary.totals.units, big.parse::<Decimal>().unwrap());
+        assert_eq!(summary.by_department[0].totals.lines, 2);
+        assert_eq!(summary.errors.len(), 1);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::manifest::ManifestRow;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Minimum Jaro-Winkler similarity for a fuzzy match to be accepted automatically.
+const FUZZY_MATCH_THRESHOLD: f64 = 0.92;
+/// Minimum similarity for a value to be offered as a suggestion for an unresolved value.
+const SUGGESTION_THRESHOLD: f64 = 0.75;
+const MAX_SUGGESTIONS: usize = 3;
+
+/// One entry of the department/category/subcategory tree.
+///
+/// `parent_id` is the department of a category and the category of a subcategory; it is
+/// `None` for departments.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TaxonomyEntry {
+    pub id: u32,
+    pub name: String,
+    pub code: String,
+    #[serde(default)]
+    pub parent_id: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Taxonomy {
+    pub departments: Vec<TaxonomyEntry>,
+    pub categories: Vec<TaxonomyEntry>,
+    pub subcategories: Vec<TaxonomyEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchKind {
+    Code,
+    Name,
+    Fuzzy,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResolvedValue {
+    pub id: u32,
+    pub name: String,
+    pub code: String,
+    pub matched_by: MatchKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RowResolution {
+    /// 1-based index of the data row.
+    pub row: usize,
+    pub department: Option<ResolvedValue>,
+    pub category: Option<ResolvedValue>,
+    pub sub_category: Option<ResolvedValue>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Suggestion {
+    pub id: u32,
+    pub name: String,
+    pub code: String,
+    pub score: f64,
+}
+
+/// A value that could not be resolved, grouped across rows so it can be fixed up in bulk.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UnresolvedValue {
+    pub field: String,
+    pub value: String,
+    /// Id of the resolved parent (department for categories, category for subcategories).
+    pub parent_id: Option<u32>,
+    pub reason: String,
+    pub rows: Vec<usize>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TaxonomyReport {
+    pub rows: Vec<RowResolution>,
+    pub unresolved: Vec<UnresolvedValue>,
+}
+
+impl TaxonomyReport {
+    pub fn is_resolved(&self) -> bool {
+        self.unresolved.is_empty()
+    }
+}
+
+impl Taxonomy {
+    /// Resolves the department, category and subcategory of every row to taxonomy ids.
+    ///
+    /// Values are matched by code, then by name, then fuzzily by name. A category is only
+    /// resolved within the row's department and a subcategory only within its category; a
+    /// value that exists elsewhere in the tree is reported as unresolved with the reason.
+    /// Empty values are skipped.
+    pub fn resolve_rows(&self, rows: &[ManifestRow]) -> TaxonomyReport {
+        let mut report = TaxonomyReport::default();
+        let mut unresolved: BTreeMap<(String, String, Option<u32>), UnresolvedValue> =
+            BTreeMap::new();
+
+        for (index, row) in rows.iter().enumerate() {
+            let mut resolution = RowResolution {
+                row: index + 1,
+                ..Default::default()
+            };
+
+            let levels = [
+                ("department", "", &row.department, &self.departments),
+                ("category", "department", &row.category, &self.categories),
+                (
+                    "sub_category",
+                    "category",
+                    &row.sub_category,
+                    &self.subcategories,
+                ),
+            ];
+            let mut parent: Option<Option<u32>> = None;
+
+            for (field, parent_field, value, entries) in levels {
+                let value = value.trim();
+                if value.is_empty() {
+                    parent = Some(None);
+                    continue;
+                }
+
+                let parent_id = parent.flatten();
+                let result = match parent {
+                    // Departments are the root of the tree
+                    None => resolve(value, entries.iter()).ok_or("No matching value".to_string()),
+                    Some(None) => Err(format!(
+                        "The row's {} is missing or unresolved",
+                        parent_field
+                    )),
+                    Some(Some(parent_id)) => {
+                        let children = entries
+                            .iter()
+                            .filter(|entry| entry.parent_id == Some(parent_id));
+                        resolve(value, children).ok_or_else(|| {
+                            if resolve(value, entries.iter()).is_some() {
+                                format!("Does not belong to the row's {}", parent_field)
+                            } else {
+                                "No matching value".to_string()
+                            }
+                        })
+                    }
+                };
+
+                match result {
+                    Ok(resolved) => {
+                        parent = Some(Some(resolved.id));
+                        match field {
+                            "department" => resolution.department = Some(resolved),
+                            "category" => resolution.category = Some(resolved),
+                            _ => resolution.sub_category = Some(resolved),
+                        }
+                    }
+                    Err(reason) => {
+                        parent = Some(None);
+                        let key = (field.to_string(), value.to_lowercase(), parent_id);
+                        unresolved
+                            .entry(key)
+                            .or_insert_with(|| UnresolvedValue {
+                                field: field.to_string(),
+                                value: value.to_string(),
+                                parent_id,
+                                reason,
+                                rows: Vec::new(),
+                                suggestions: suggest(value, entries, parent_id),
+                            })
+                            .rows
+                            .push(index + 1);
+                    }
+                }
+            }
+
+            report.rows.push(resolution);
+        }
+
+        report.unresolved = unresolved.into_values().collect();
+        report
+    }
+}
+
+fn resolve<'a>(
+    value: &str,
+    entries: impl Iterator<Item = &'a TaxonomyEntry> + Clone,
+) -> Option<ResolvedValue> {
+    let normalized = normalize(value);
+    let resolved = |entry: &TaxonomyEntry, matched_by| ResolvedValue {
+        id: entry.id,
+        name: entry.name.clone(),
+        code: entry.code.clone(),
+        matched_by,
+    };
+
+    if let Some(entry) = entries
+        .clone()
+        .find(|entry| entry.code.eq_ignore_ascii_case(value))
+    {
+        return Some(resolved(entry, MatchKind::Code));
+    }
+    if let Some(entry) = entries
+        .clone()
+        .find(|entry| normalize(&entry.name) == normalized)
+    {
+        return Some(resolved(entry, MatchKind::Name));
+    }
+
+    entries
+        .map(|entry| (entry, similarity(&normalized, entry)))
+        .filter(|(_, score)| *score >= FUZZY_MATCH_THRESHOLD)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(entry, _)| resolved(entry, MatchKind::Fuzzy))
+}
+
+fn suggest(value: &str, entries: &[TaxonomyEntry], parent_id: Option<u32>) -> Vec<Suggestion> {
+    let normalized = normalize(value);
+    let mut suggestions: Vec<Suggestion> = entries
+        .iter()
+        .filter(|entry| parent_id.is_none() || entry.parent_id == parent_id)
+        .map(|entry| Suggestion {
+            id: entry.id,
+            name: entry.name.clone(),
+            code: entry.code.clone(),
+            score: similarity(&normalized, entry),
+        })
+        .filter(|suggestion| suggestion.score >= SUGGESTION_THRESHOLD)
+        .collect();
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+/// Best similarity of a normalized value against an entry's name and code.
+fn similarity(normalized: &str, entry: &TaxonomyEntry) -> f64 {
+    let name = strsim::jaro_winkler(normalized, &normalize(&entry.name));
+    let code = strsim::jaro_winkler(normalized, &normalize(&entry.code));
+    name.max(code)
+}
+
+/// Lowercases and strips punctuation so `Curtains/Drapes` matches `curtains drapes`.
+fn normalize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .flat_map(|c| c.to_lowercase())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS email_outbox
+(
+    id              SERIAL PRIMARY KEY,
+    kind            VARCHAR(64)                               NOT NULL,
+    sender          VARCHAR(255)                              NOT NULL,
+    recipients      JSON                                      NOT NULL,
+    subject         VARCHAR(998)                              NOT NULL,
+    message         MEDIUMBLOB                                NOT NULL,
+    status          VARCHAR(16)  DEFAULT 'pending'            NOT NULL,
+    attempts        INT UNSIGNED DEFAULT 0                    NOT NULL,
+    last_error      TEXT                                      NULL,
+    next_attempt_at TIMESTAMP    DEFAULT CURRENT_TIMESTAMP    NOT NULL,
+    created_at      TIMESTAMP    DEFAULT CURRENT_TIMESTAMP    NOT NULL,
+    sent_at         TIMESTAMP                                 NULL,
+    INDEX (status, next_attempt_at)
+);
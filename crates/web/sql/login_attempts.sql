@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS login_attempts
+(
+    id             SERIAL PRIMARY KEY,
+    email          VARCHAR(255)                        NOT NULL,
+    user_id        INT UNSIGNED                        NULL,
+    ip_address     VARCHAR(45)                         NOT NULL,
+    user_agent     VARCHAR(512)                        NULL,
+    success        BOOLEAN                             NOT NULL,
+    failure_reason VARCHAR(32)                         NULL,
+    attempted_at   TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    INDEX (user_id, attempted_at),
+    INDEX (email, attempted_at),
+    INDEX (ip_address, attempted_at),
+    INDEX (attempted_at)
+);
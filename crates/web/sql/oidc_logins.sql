@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS oidc_logins
+(
+    id            SERIAL PRIMARY KEY,
+    state_hash    CHAR(64)                            NOT NULL UNIQUE,
+    code_verifier VARCHAR(128)                        NOT NULL,
+    nonce         VARCHAR(128)                        NOT NULL,
+    redirect_uri  VARCHAR(512)                        NOT NULL,
+    created_at    TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    expires_at    TIMESTAMP                           NOT NULL,
+    INDEX (expires_at)
+);
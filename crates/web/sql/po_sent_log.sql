@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS po_sent_log
+(
+    id          SERIAL PRIMARY KEY,
+    po_number   VARCHAR(255)                        NOT NULL,
+    recipients  JSON                                NOT NULL,
+    subject     VARCHAR(998)                        NOT NULL,
+    attachments JSON                                NOT NULL,
+    message_id  VARCHAR(255)                        NOT NULL,
+    sent_by     INT UNSIGNED                        NOT NULL REFERENCES users (id),
+    sent_at     TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    INDEX (po_number)
+);
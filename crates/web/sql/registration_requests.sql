@@ -1,8 +1,10 @@
 CREATE TABLE IF NOT EXISTS registration_requests
 (
-    id      SERIAL PRIMARY KEY,
-    email   VARCHAR(255) NOT NULL UNIQUE,
-    token   VARCHAR(255) NOT NULL UNIQUE,
-    user_id INT UNSIGNED NOT NULL REFERENCES users (id),
-    INDEX (token, email)
-);
\ No newline at end of file
+    id         SERIAL PRIMARY KEY,
+    email      VARCHAR(255) NOT NULL UNIQUE,
+    token      VARCHAR(255) NOT NULL UNIQUE,
+    user_id    INT UNSIGNED NOT NULL REFERENCES users (id),
+    expires_at TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX (token, email),
+    INDEX (expires_at)
+);
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS scheduled_jobs
+(
+    name             VARCHAR(64)                            NOT NULL,
+    interval_seconds INT UNSIGNED                           NOT NULL,
+    next_run_at      TIMESTAMP    DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    locked_until     TIMESTAMP                              NULL,
+    last_run_at      TIMESTAMP                              NULL,
+    last_error       TEXT                                   NULL,
+    PRIMARY KEY (name)
+);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS two_factor_policy
+(
+    role       TINYINT UNSIGNED                    NOT NULL,
+    required   BOOLEAN   DEFAULT FALSE             NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL ON UPDATE CURRENT_TIMESTAMP,
+    PRIMARY KEY (role)
+);
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS user_invites
+(
+    id         SERIAL PRIMARY KEY,
+    email      VARCHAR(255)                        NOT NULL UNIQUE,
+    token_hash CHAR(64)                            NOT NULL UNIQUE,
+    role       TINYINT UNSIGNED                    NOT NULL,
+    invited_by INT UNSIGNED                        NULL REFERENCES users (id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP                           NOT NULL,
+    INDEX (expires_at)
+);
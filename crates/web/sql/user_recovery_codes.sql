@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS user_recovery_codes
+(
+    id         SERIAL PRIMARY KEY,
+    user_id    INT UNSIGNED                        NOT NULL REFERENCES users (id),
+    code_hash  CHAR(64)                            NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    used_at    TIMESTAMP                           NULL,
+    INDEX (user_id)
+);
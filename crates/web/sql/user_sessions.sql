@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS user_sessions
+(
+    id                  SERIAL PRIMARY KEY,
+    user_id             INT UNSIGNED                        NOT NULL REFERENCES users (id),
+    token_hash          CHAR(64)                            NOT NULL UNIQUE,
+    previous_token_hash CHAR(64)                            NULL,
+    created_at          TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    last_used_at        TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    expires_at          TIMESTAMP                           NOT NULL,
+    INDEX (previous_token_hash),
+    INDEX (user_id),
+    INDEX (expires_at)
+);
@@ -8,6 +8,19 @@ CREATE TABLE IF NOT EXISTS users
     role       TINYINT UNSIGNED DEFAULT 2         NOT NULL,
     needs_password_reset BOOLEAN DEFAULT FALSE,
     has_confirmed_email BOOLEAN DEFAULT FALSE,
+    session_version INT UNSIGNED DEFAULT 0 NOT NULL,
+    failed_logins INT UNSIGNED DEFAULT 0 NOT NULL,
+    locked_until TIMESTAMP NULL,
+    unlock_token_hash CHAR(64) NULL,
+    totp_secret VARCHAR(64) NULL,
+    totp_pending_secret VARCHAR(64) NULL,
+    totp_enabled BOOLEAN DEFAULT FALSE NOT NULL,
+    totp_last_step BIGINT UNSIGNED NULL,
+    auth_source VARCHAR(16) DEFAULT 'local' NOT NULL,
+    is_approved BOOLEAN DEFAULT TRUE NOT NULL,
+    password_peppered BOOLEAN DEFAULT FALSE NOT NULL,
+    password_reset_token_hash CHAR(64) NULL,
+    password_reset_expires_at TIMESTAMP NULL,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
     last_online TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
     PRIMARY KEY (id)
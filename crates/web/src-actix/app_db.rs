@@ -1,7 +1,7 @@
-use crate::{auth, data};
+use crate::{auth, data, email_outbox, po, scheduler};
 use anyhow::Result;
 use sqlx::mysql::{MySqlConnectOptions, MySqlPoolOptions};
-use sqlx::{ConnectOptions, MySqlPool};
+use sqlx::{ConnectOptions, MySqlPool, MySqlTransaction};
 
 pub async fn create_pool() -> Result<MySqlPool> {
     // The mysql connection string.
@@ -28,6 +28,9 @@ pub async fn initialize_database() -> Result<()> {
 	// ======   List tables here    ======
 	auth::initialize_table(&mut transaction).await?;
     data::initialize_table(&mut transaction).await?;
+    po::initialize_table(&mut transaction).await?;
+    email_outbox::initialize_table(&mut transaction).await?;
+    scheduler::initialize_table(&mut transaction).await?;
 
 	// ======   End of list         ======
 
@@ -35,3 +38,31 @@ pub async fn initialize_database() -> Result<()> {
     pool.close().await;
     Ok(())
 }
+
+/// Adds a column to a table created before the column was part of its schema.
+///
+/// `CREATE TABLE IF NOT EXISTS` leaves existing tables alone, so columns added later need this.
+/// Returns whether the column was added.
+pub async fn add_column_if_missing<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<bool> {
+    let existing: i64 = sqlx::query_scalar(
+        r#"SELECT COUNT(*) FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND COLUMN_NAME = ?"#,
+    )
+    .bind(table)
+    .bind(column)
+    .fetch_one(&mut **transaction)
+    .await?;
+    if existing > 0 {
+        return Ok(false);
+    }
+    sqlx::query(&format!(
+        "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+    ))
+    .execute(&mut **transaction)
+    .await?;
+    Ok(true)
+}
@@ -1,36 +1,206 @@
-use crate::auth::auth_endpoint_data::{ConfirmEmailBody, LoginRequestBody, UserRegistrationBody};
-use crate::auth::auth_middleware::validator;
+use crate::auth::auth_endpoint_data::{
+    ApproveRegistrationBody, ChallengeTokenBody, ConfirmEmailBody, DirectoryLoginBody,
+    InviteUserBody, LoginHistoryQuery, LoginRequestBody, OidcAuthorizeBody, OidcLoginBody,
+    PasswordResetBody, PasswordResetRequestBody, RefreshTokenBody, TwoFactorCodeBody,
+    TwoFactorLoginBody, TwoFactorPolicyBody, UnlockAccountBody, UserRegistrationBody,
+};
+use crate::auth::auth_middleware::{require_admin, require_user, validator};
 use crate::auth::jwt_data::Claims;
+use crate::auth::login_attempts_db::{self, LoginClient};
+use crate::auth::login_error::LoginError;
+use crate::auth::login_guard::limit_by_ip;
+use crate::auth::oidc::{self, OidcConfig};
+use crate::auth::password_policy::PasswordPolicy;
+use crate::auth::registration_error::RegistrationError;
+use crate::auth::user_role::UserRole;
 use crate::auth::users_data::User;
+use crate::auth::{directory, invites_db, two_factor, two_factor_db, users_db};
+use actix_web::middleware::from_fn;
 use actix_web::web::Json;
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, Result, get, post, web};
+use actix_web::{
+    HttpMessage, HttpRequest, HttpResponse, Responder, Result, delete, get, post, put, web,
+};
 use actix_web_httpauth::middleware::HttpAuthentication;
 use log::error;
 use serde_json::json;
 
 #[get("/users")]
-pub async fn get_users() -> Result<impl Responder> {
+pub async fn get_users(req: HttpRequest) -> Result<impl Responder> {
+    require_admin(&req).await?;
     let users = User::get_users()
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
     Ok(HttpResponse::Ok().json(users))
 }
 
-#[post("/login")]
-pub async fn login(body: Json<LoginRequestBody>) -> Result<impl Responder> {
+#[post("/login", wrap = "from_fn(limit_by_ip)")]
+pub async fn login(req: HttpRequest, body: Json<LoginRequestBody>) -> Result<impl Responder> {
     let body = body.into_inner();
     let email = body.email.as_str();
     let password = body.password.as_str();
-    let response = User::login(email, password)
+    let response = User::login(email, password, &LoginClient::from_request(&req)).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Logs in with a domain account, when directory logins are turned on.
+#[post("/login/directory", wrap = "from_fn(limit_by_ip)")]
+pub async fn login_directory(
+    req: HttpRequest,
+    body: Json<DirectoryLoginBody>,
+) -> Result<impl Responder> {
+    let response = User::login_with_directory(
+        &body.username,
+        &body.password,
+        &LoginClient::from_request(&req),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Starts a single sign-on. The client sends the user to the returned URL, and passes the
+/// code the identity provider redirects back with to `/login/oidc`.
+#[post("/oidc/authorize", wrap = "from_fn(limit_by_ip)")]
+pub async fn authorize_oidc(body: Json<OidcAuthorizeBody>) -> Result<impl Responder> {
+    let config = OidcConfig::from_config()
+        .map_err(LoginError::from)?
+        .ok_or(LoginError::OidcDisabled)?;
+    let authorization = oidc::begin_login(&config, &body.redirect_uri)
         .await
-        .map_err(actix_web::error::ErrorInternalServerError)?;
+        .map_err(LoginError::from)?
+        .ok_or(LoginError::InvalidRedirectUri)?;
+    Ok(HttpResponse::Ok().json(authorization))
+}
+
+/// Finishes a single sign-on with the code the identity provider redirected back with.
+#[post("/login/oidc", wrap = "from_fn(limit_by_ip)")]
+pub async fn login_oidc(req: HttpRequest, body: Json<OidcLoginBody>) -> Result<impl Responder> {
+    let response =
+        User::login_with_oidc(&body.code, &body.state, &LoginClient::from_request(&req)).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// The ways of logging in that are turned on, so the login page can offer them.
+#[get("/providers")]
+pub async fn get_login_providers() -> Result<impl Responder> {
+    let oidc = OidcConfig::from_config().unwrap_or_else(|e| {
+        error!("Invalid single sign-on configuration: {e}");
+        None
+    });
+    Ok(HttpResponse::Ok().json(json!({
+        "password": true,
+        "directory": directory::is_enabled(),
+        "oidc": oidc.is_some(),
+        "oidc_name": oidc.as_ref().map(|config| config.display_name()),
+    })))
+}
+
+/// The rules new passwords have to follow, so the registration page can show them.
+#[get("/password-policy")]
+pub async fn get_password_policy() -> Result<impl Responder> {
+    Ok(HttpResponse::Ok().json(PasswordPolicy::from_config()))
+}
+
+/// Second step of a login that returned a two-factor challenge.
+#[post("/login/two-factor", wrap = "from_fn(limit_by_ip)")]
+pub async fn login_two_factor(
+    req: HttpRequest,
+    body: Json<TwoFactorLoginBody>,
+) -> Result<impl Responder> {
+    let response = User::complete_two_factor_login(
+        &body.challenge_token,
+        &body.code,
+        &LoginClient::from_request(&req),
+    )
+    .await?;
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Starts the authenticator app setup a `setup` challenge asks for.
+#[post("/login/two-factor/setup", wrap = "from_fn(limit_by_ip)")]
+pub async fn login_two_factor_setup(body: Json<ChallengeTokenBody>) -> Result<impl Responder> {
+    let setup = User::begin_login_two_factor_setup(&body.challenge_token).await?;
+    Ok(HttpResponse::Ok().json(setup))
+}
+
+/// Lifts a lockout with the link from the unlock email.
+#[post("/unlock", wrap = "from_fn(limit_by_ip)")]
+pub async fn unlock_account(body: Json<UnlockAccountBody>) -> Result<impl Responder> {
+    let unlocked = User::unlock(&body.email, &body.token)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !unlocked {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Invalid or expired unlock link".to_string(),
+        })));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Emails a link to choose a new password. Answers the same whether the email has an account.
+#[post("/password-reset", wrap = "from_fn(limit_by_ip)")]
+pub async fn request_password_reset(
+    body: Json<PasswordResetRequestBody>,
+) -> Result<impl Responder> {
+    User::request_password_reset(&body.email)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Sets a new password with the link from the password reset email.
+#[post("/password-reset/confirm", wrap = "from_fn(limit_by_ip)")]
+pub async fn reset_password(body: Json<PasswordResetBody>) -> Result<impl Responder> {
+    User::reset_password(&body.email, &body.token, &body.password).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Exchanges a refresh token for a new access token and refresh token.
+#[post("/refresh")]
+pub async fn refresh(body: Json<RefreshTokenBody>) -> Result<impl Responder> {
+    let response = User::refresh(&body.refresh_token)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    match response {
+        Some(response) => Ok(HttpResponse::Ok().json(response)),
+        None => Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Invalid or expired refresh token".to_string(),
+        }))),
+    }
+}
+
+/// Ends the session of the given refresh token. Its access token stops working right away.
+#[post("/logout")]
+pub async fn logout(body: Json<RefreshTokenBody>) -> Result<impl Responder> {
+    User::logout(&body.refresh_token)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Ends every session of the signed-in user.
+#[post("/logout-all")]
+pub async fn logout_all(req: HttpRequest) -> Result<impl Responder> {
+    let claims = req
+        .extensions()
+        .get::<Claims>()
+        .cloned()
+        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Unauthorized"))?;
+    User::logout_everywhere(claims.sub as u32)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
 #[post("/register")]
 pub async fn register_user(body: Json<UserRegistrationBody>) -> Result<impl Responder> {
-    let mut user: User = body.into_inner().into();
-    if let Err(e) = user.register().await {
+    let body = body.into_inner();
+    let invite_token = body.invite_token.clone();
+    let mut user: User = body.into();
+    if let Err(e) = user.register(invite_token.as_deref()).await {
+        // Refused registrations didn't create anything
+        let RegistrationError::Other(_) = e else {
+            return Err(e.into());
+        };
         // Cleanup on failure
         async fn cleanup(email: String) {
             let Ok(pool) = crate::app_db::create_pool().await else {
@@ -65,13 +235,97 @@ pub async fn register_user(body: Json<UserRegistrationBody>) -> Result<impl Resp
         }
 
         cleanup(user.email.clone()).await;
-        return Err(actix_web::error::ErrorInternalServerError(e));
+        return Err(e.into());
+    }
+    if user.is_approved {
+        return Ok(HttpResponse::Ok().json(json!({
+            "message": "Your account was created. You can sign in now.",
+            "pending_approval": false,
+        })));
     }
     Ok(HttpResponse::Ok().json(json!({
-        "message": "User registration request was successful. Please check your email for further instructions.",
+        "message": "User registration request was successful. Please check your email for further instructions. An administrator has to approve your account before you can sign in.",
+        "pending_approval": true,
     })))
 }
 
+/// Invites someone to register with a role. The invite email links to the registration page.
+#[post("/invites")]
+pub async fn invite_user(req: HttpRequest, body: Json<InviteUserBody>) -> Result<impl Responder> {
+    let admin = require_admin(&req).await?;
+    let invite = User::invite(&body.email, body.role, &admin).await?;
+    Ok(HttpResponse::Ok().json(invite))
+}
+
+/// Invites that haven't been used yet.
+#[get("/invites")]
+pub async fn get_invites(req: HttpRequest) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    let invites = invites_db::get_invites()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(invites))
+}
+
+/// Withdraws an invite; its link stops working.
+#[delete("/invites/{id}")]
+pub async fn delete_invite(req: HttpRequest, id: web::Path<u64>) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    let removed = invites_db::remove_invite(id.into_inner())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !removed {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "error": "Invite not found".to_string(),
+        })));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Self-registered users waiting for an admin to approve them.
+#[get("/registrations/pending")]
+pub async fn get_pending_registrations(req: HttpRequest) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    let users = users_db::get_pending_users()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(users))
+}
+
+/// Lets a self-registered user log in with the role the admin picks.
+#[post("/registrations/{id}/approve")]
+pub async fn approve_registration(
+    req: HttpRequest,
+    id: web::Path<u32>,
+    body: Json<ApproveRegistrationBody>,
+) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    let approved = users_db::approve_user(id.into_inner(), body.role)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !approved {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "error": "No pending registration for this user".to_string(),
+        })));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Deletes a self-registered user instead of approving them.
+#[post("/registrations/{id}/reject")]
+pub async fn reject_registration(req: HttpRequest, id: web::Path<u32>) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    let rejected = users_db::reject_user(id.into_inner())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !rejected {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "error": "No pending registration for this user".to_string(),
+        })));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
 #[post("/confirm-email")]
 pub async fn confirm_email(body: Json<ConfirmEmailBody>) -> Result<impl Responder> {
     let body = body.into_inner();
@@ -97,15 +351,167 @@ pub async fn get_current_user(req: HttpRequest) -> Result<impl Responder> {
     }
 }
 
+const DEFAULT_HISTORY_LIMIT: u32 = 50;
+const MAX_HISTORY_LIMIT: u32 = 500;
+
+/// The signed-in user's own login history, newest first.
+#[get("/me/login-history")]
+pub async fn get_my_login_history(
+    req: HttpRequest,
+    query: web::Query<LoginHistoryQuery>,
+) -> Result<impl Responder> {
+    let claims = req
+        .extensions()
+        .get::<Claims>()
+        .cloned()
+        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Unauthorized"))?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT);
+    let attempts =
+        login_attempts_db::get_attempts(Some(claims.sub as u32), None, query.success, limit)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(attempts))
+}
+
+/// Login attempts of every user, including attempts for emails without an account.
+#[get("/login-history")]
+pub async fn get_login_history(
+    req: HttpRequest,
+    query: web::Query<LoginHistoryQuery>,
+) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT);
+    let attempts = login_attempts_db::get_attempts(
+        query.user_id,
+        query.email.as_deref(),
+        query.success,
+        limit,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(attempts))
+}
+
+#[get("/two-factor")]
+pub async fn get_two_factor_status(req: HttpRequest) -> Result<impl Responder> {
+    let user = require_user(&req).await?;
+    let status = two_factor::get_status(&user)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// Starts setting up an authenticator app; confirmed with a code from the app.
+#[post("/two-factor/setup")]
+pub async fn begin_two_factor_setup(req: HttpRequest) -> Result<impl Responder> {
+    let user = require_user(&req).await?;
+    let setup = two_factor::begin_setup(&user).await?;
+    Ok(HttpResponse::Ok().json(setup))
+}
+
+#[post("/two-factor/confirm")]
+pub async fn confirm_two_factor_setup(
+    req: HttpRequest,
+    body: Json<TwoFactorCodeBody>,
+) -> Result<impl Responder> {
+    let user = require_user(&req).await?;
+    let recovery_codes = two_factor::confirm_setup(&user, &body.code).await?;
+    Ok(HttpResponse::Ok().json(json!({ "recovery_codes": recovery_codes })))
+}
+
+#[post("/two-factor/disable")]
+pub async fn disable_two_factor(
+    req: HttpRequest,
+    body: Json<TwoFactorCodeBody>,
+) -> Result<impl Responder> {
+    let user = require_user(&req).await?;
+    two_factor::disable(&user, &body.code).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Replaces the recovery codes; the old ones stop working.
+#[post("/two-factor/recovery-codes")]
+pub async fn regenerate_recovery_codes(
+    req: HttpRequest,
+    body: Json<TwoFactorCodeBody>,
+) -> Result<impl Responder> {
+    let user = require_user(&req).await?;
+    let recovery_codes = two_factor::regenerate_recovery_codes(&user, &body.code).await?;
+    Ok(HttpResponse::Ok().json(json!({ "recovery_codes": recovery_codes })))
+}
+
+/// Which roles have to use two-factor authentication.
+#[get("/two-factor/policy")]
+pub async fn get_two_factor_policy(req: HttpRequest) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    let policies = two_factor_db::get_policies()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(policies))
+}
+
+/// Makes two-factor authentication required or optional for a role. Users of the role who
+/// haven't set it up are asked to at their next login.
+#[put("/two-factor/policy/{role}")]
+pub async fn set_two_factor_policy(
+    req: HttpRequest,
+    role: web::Path<UserRole>,
+    body: Json<TwoFactorPolicyBody>,
+) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    two_factor_db::set_policy(role.into_inner(), body.required)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
     let auth = HttpAuthentication::bearer(validator);
     cfg.service(
         web::scope("/auth")
-            .service(get_users)
             .service(login)
+            .service(login_directory)
+            .service(authorize_oidc)
+            .service(login_oidc)
+            .service(get_login_providers)
+            .service(get_password_policy)
+            .service(login_two_factor)
+            .service(login_two_factor_setup)
+            .service(refresh)
+            .service(logout)
+            .service(unlock_account)
+            .service(request_password_reset)
+            .service(reset_password)
             .service(register_user)
             .service(confirm_email)
-            .service(web::scope("").wrap(auth).service(get_current_user))
+            .service(
+                web::scope("")
+                    .wrap(auth)
+                    .service(get_users)
+                    .service(get_current_user)
+                    .service(get_my_login_history)
+                    .service(get_login_history)
+                    .service(get_two_factor_status)
+                    .service(begin_two_factor_setup)
+                    .service(confirm_two_factor_setup)
+                    .service(disable_two_factor)
+                    .service(regenerate_recovery_codes)
+                    .service(get_two_factor_policy)
+                    .service(set_two_factor_policy)
+                    .service(invite_user)
+                    .service(get_invites)
+                    .service(delete_invite)
+                    .service(get_pending_registrations)
+                    .service(approve_registration)
+                    .service(reject_registration)
+                    .service(logout_all),
+            )
             .default_service(web::to(|| async {
                 HttpResponse::NotFound().json(json!({
                     "error": "API endpoint not found".to_string(),
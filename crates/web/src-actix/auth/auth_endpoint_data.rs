@@ -1,3 +1,4 @@
+use crate::auth::auth_source::AuthSource;
 use crate::auth::user_role::UserRole;
 use crate::auth::users_data::User;
 
@@ -9,7 +10,10 @@ pub struct UserRegistrationBody{
 	pub last_name: String,
 	pub email: String,
 	pub password: String,
-	pub role: UserRole
+	/// Token from an invite email. Invited users get the invite's role and can log in right
+	/// away; everyone else waits for an admin to approve them.
+	#[serde(default, alias="inviteToken")]
+	pub invite_token: Option<String>
 }
 
 impl From<UserRegistrationBody> for User {
@@ -20,9 +24,12 @@ impl From<UserRegistrationBody> for User {
 			last_name: val.last_name,
 			email: val.email,
 			password: val.password,
-			role: val.role,
+			role: UserRole::default(),
 			has_confirmed_email: false,
-			needs_password_reset: false
+			needs_password_reset: false,
+			auth_source: AuthSource::Local,
+			is_approved: false,
+			password_peppered: false
 		}
 	}
 }
@@ -37,4 +44,84 @@ pub struct ConfirmEmailBody{
 pub struct LoginRequestBody{
 	pub email: String,
 	pub password: String
-}
\ No newline at end of file
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct DirectoryLoginBody{
+	pub username: String,
+	pub password: String
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct RefreshTokenBody{
+	pub refresh_token: String
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct UnlockAccountBody{
+	pub email: String,
+	pub token: String
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct PasswordResetRequestBody{
+	pub email: String
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct PasswordResetBody{
+	pub email: String,
+	pub token: String,
+	pub password: String
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct LoginHistoryQuery{
+	pub user_id: Option<u32>,
+	pub email: Option<String>,
+	pub success: Option<bool>,
+	pub limit: Option<u32>
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct TwoFactorLoginBody{
+	pub challenge_token: String,
+	pub code: String
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ChallengeTokenBody{
+	pub challenge_token: String
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct TwoFactorCodeBody{
+	pub code: String
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct OidcAuthorizeBody{
+	pub redirect_uri: String
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct OidcLoginBody{
+	pub code: String,
+	pub state: String
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct InviteUserBody{
+	pub email: String,
+	pub role: UserRole
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ApproveRegistrationBody{
+	pub role: UserRole
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct TwoFactorPolicyBody{
+	pub required: bool
+}
@@ -1,21 +1,90 @@
 use crate::auth::auth_service::validate_jwt_token;
+use crate::auth::jwt_data::Claims;
+use crate::auth::users_data::User;
+use crate::auth::{sessions_db, users_db};
+use crate::util::rate_limit::RateLimiter;
 use actix_web::dev::ServiceRequest;
-use actix_web::HttpMessage;
+use actix_web::error::{ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::{HttpMessage, HttpRequest};
 use actix_web_httpauth::extractors::bearer::{BearerAuth, Config};
 use actix_web_httpauth::extractors::AuthenticationError;
+use log::warn;
+use std::sync::LazyLock;
+use std::time::Duration;
 
+/// Lets `last_online` be written at most once per interval per user, rather than on every
+/// request.
+static LAST_ONLINE_THROTTLE: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(1, Duration::from_mins(5)));
+
+/// Accepts a bearer token if it is a valid access token whose session hasn't been ended and
+/// whose user hasn't logged out everywhere since it was issued.
 pub async fn validator(
     req: ServiceRequest,
     credentials: BearerAuth,
 ) -> Result<ServiceRequest, (actix_web::Error, ServiceRequest)> {
-    match validate_jwt_token(credentials.token()) {
-        Ok(claims) => {
+    let Ok(claims) = validate_jwt_token(credentials.token()) else {
+        return Err(unauthorized(req));
+    };
+    match sessions_db::get_active_session_version(claims.sid, claims.sub as u32).await {
+        Ok(Some(version)) if version == claims.ver => {
+            touch_last_online(claims.sub as u32);
             req.extensions_mut().insert(claims);
             Ok(req)
         }
-        Err(_) => {
-            let config = req.app_data::<Config>().cloned().unwrap_or_default();
-            Err((AuthenticationError::from(config).into(), req))
+        Ok(_) => Err(unauthorized(req)),
+        Err(e) => Err((ErrorInternalServerError(e), req)),
+    }
+}
+
+/// Updates the user's `last_online` in the background, unless it was updated recently.
+fn touch_last_online(user_id: u32) {
+    if LAST_ONLINE_THROTTLE
+        .check_and_hit(&user_id.to_string())
+        .is_err()
+    {
+        return;
+    }
+    tokio::spawn(async move {
+        if let Err(e) = users_db::touch_last_online(user_id).await {
+            warn!("Failed to update last_online of user {}: {e}", user_id);
         }
+    });
+}
+
+fn unauthorized(req: ServiceRequest) -> (actix_web::Error, ServiceRequest) {
+    let config = req.app_data::<Config>().cloned().unwrap_or_default();
+    (AuthenticationError::from(config).into(), req)
+}
+
+/// Returns the signed-in user, for endpoints behind the bearer validator.
+pub async fn require_user(req: &HttpRequest) -> actix_web::Result<User> {
+    let claims = req
+        .extensions()
+        .get::<Claims>()
+        .cloned()
+        .ok_or_else(|| ErrorUnauthorized("Unauthorized"))?;
+    User::get_user_by_id(claims.sub as u32)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .ok_or_else(|| ErrorUnauthorized("Unauthorized"))
+}
+
+/// Returns the signed-in user if they are an admin, for endpoints behind the bearer validator.
+pub async fn require_admin(req: &HttpRequest) -> actix_web::Result<User> {
+    let user = require_user(req).await?;
+    if !user.role.is_admin() {
+        return Err(ErrorForbidden("Admin access required"));
+    }
+    Ok(user)
+}
+
+/// Returns the signed-in user if they are a buyer or an admin, for endpoints that deal with PO
+/// costs.
+pub async fn require_buyer(req: &HttpRequest) -> actix_web::Result<User> {
+    let user = require_user(req).await?;
+    if !(user.role.is_buyer() || user.role.is_admin()) {
+        return Err(ErrorForbidden("Buyer access required"));
     }
+    Ok(user)
 }
@@ -1,14 +1,32 @@
 use anyhow::Result;
 use chrono::Utc;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
-use crate::auth::jwt_data::Claims;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use hmac::{Hmac, Mac};
+use log::warn;
+use sha2::{Digest, Sha256};
+use crate::auth::jwt_data::{ChallengeClaims, Claims, TwoFactorStep};
 
 const JWT_SECRET: &str = env!("JWT_SECRET");
 const HASH_SALT: &str = env!("HASH_SALT");
 
-pub fn generate_jwt_token(user_id: u32, email: &str) -> Result<String, jsonwebtoken::errors::Error> {
+/// Access tokens are short-lived; clients get a new one from `/auth/refresh`.
+pub const ACCESS_TOKEN_MINUTES: i64 = 15;
+/// Time a user has to enter their second factor after the password.
+pub const CHALLENGE_TOKEN_MINUTES: i64 = 5;
+/// bcrypt cost of new password hashes. Passwords hashed with a lower cost are rehashed at
+/// their next login.
+const PASSWORD_HASH_COST_VAR: &str = "PASSWORD_HASH_COST";
+
+pub fn generate_jwt_token(
+	user_id: u32,
+	email: &str,
+	session_id: u64,
+	session_version: u32,
+) -> Result<String, jsonwebtoken::errors::Error> {
 	let expiration = Utc::now()
-		.checked_add_signed(chrono::Duration::days(30))
+		.checked_add_signed(chrono::Duration::minutes(ACCESS_TOKEN_MINUTES))
 		.expect("Time went backwards")
 		.timestamp();
 
@@ -17,6 +35,8 @@ pub fn generate_jwt_token(user_id: u32, email: &str) -> Result<String, jsonwebto
 		email: email.to_owned(),
 		exp: expiration,
 		iat: Utc::now().timestamp(),
+		sid: session_id,
+		ver: session_version,
 	};
 
 
@@ -36,4 +56,107 @@ pub fn validate_jwt_token(token: &str)->Result<Claims, jsonwebtoken::errors::Err
 	)?;
 
 	Ok(token_data.claims)
-}
\ No newline at end of file
+}
+
+pub fn generate_challenge_token(
+	user_id: u32,
+	email: &str,
+	two_factor: TwoFactorStep,
+) -> Result<String, jsonwebtoken::errors::Error> {
+	let expiration = Utc::now()
+		.checked_add_signed(chrono::Duration::minutes(CHALLENGE_TOKEN_MINUTES))
+		.expect("Time went backwards")
+		.timestamp();
+
+	let claims = ChallengeClaims{
+		sub: user_id as u64,
+		email: email.to_owned(),
+		exp: expiration,
+		iat: Utc::now().timestamp(),
+		two_factor,
+	};
+
+	encode(
+		&Header::default(),
+		&claims,
+		&EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+	)
+}
+
+pub fn validate_challenge_token(token: &str)->Result<ChallengeClaims, jsonwebtoken::errors::Error>
+{
+	let token_data = decode::<ChallengeClaims>(
+		token,
+		&DecodingKey::from_secret(JWT_SECRET.as_bytes()),
+		&Validation::default(),
+	)?;
+
+	Ok(token_data.claims)
+}
+
+/// A new random refresh or unlock token. Only its hash is stored.
+pub fn generate_token() -> String
+{
+	format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+/// The value stored for a refresh token, unlock token or recovery code, so a leaked table can't be used to log in.
+pub fn hash_token(token: &str) -> String
+{
+	format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// The bcrypt cost from `PASSWORD_HASH_COST`, or bcrypt's default.
+pub fn password_hash_cost() -> u32
+{
+	let Ok(cost) = std::env::var(PASSWORD_HASH_COST_VAR) else {
+		return bcrypt::DEFAULT_COST;
+	};
+	match cost.trim().parse::<u32>() {
+		Ok(cost) if (4..=31).contains(&cost) => cost,
+		_ => {
+			warn!("Invalid {}, using the default cost: {}", PASSWORD_HASH_COST_VAR, cost);
+			bcrypt::DEFAULT_COST
+		}
+	}
+}
+
+/// What bcrypt hashes in place of the password: an HMAC of it keyed with `HASH_SALT`, so a
+/// leaked users table can't be cracked without the server's secret as well. It also keeps
+/// bcrypt from cutting off passwords longer than 72 bytes. Changing `HASH_SALT` makes every
+/// peppered password stop working.
+fn pepper_password(password: &str) -> String
+{
+	let mut mac = Hmac::<Sha256>::new_from_slice(HASH_SALT.as_bytes()).expect("HMAC accepts keys of any length");
+	mac.update(password.as_bytes());
+	STANDARD.encode(mac.finalize().into_bytes())
+}
+
+pub fn hash_password(password: &str) -> Result<String>
+{
+	Ok(bcrypt::hash(pepper_password(password), password_hash_cost())?)
+}
+
+/// Checks a password against its stored hash. `peppered` is false for hashes from before
+/// passwords were peppered, which bcrypt hashed as they were.
+pub fn verify_password(password: &str, hash: &str, peppered: bool) -> Result<bool>
+{
+	if peppered {
+		Ok(bcrypt::verify(pepper_password(password), hash)?)
+	} else {
+		Ok(bcrypt::verify(password, hash)?)
+	}
+}
+
+/// Whether a hash should be replaced at the next successful login: it isn't peppered yet, or
+/// `PASSWORD_HASH_COST` was raised since it was made.
+pub fn needs_rehash(hash: &str, peppered: bool) -> bool
+{
+	if !peppered {
+		return true;
+	}
+	match hash.parse::<bcrypt::HashParts>() {
+		Ok(parts) => parts.get_cost() < password_hash_cost(),
+		Err(_) => false,
+	}
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+/// Where a user's password is checked, as stored in `users.auth_source`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthSource {
+    /// Against the bcrypt hash in `users.password`.
+    #[default]
+    Local,
+    /// By the directory; `users.password` holds a hash of a random password nobody knows.
+    Directory,
+    /// By the identity provider of a single sign-on, with an unusable password like
+    /// `Directory`.
+    Oidc,
+}
+
+impl AuthSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuthSource::Local => "local",
+            AuthSource::Directory => "directory",
+            AuthSource::Oidc => "oidc",
+        }
+    }
+}
+
+impl TryFrom<String> for AuthSource {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "local" => Ok(AuthSource::Local),
+            "directory" => Ok(AuthSource::Directory),
+            "oidc" => Ok(AuthSource::Oidc),
+            other => Err(format!("invalid auth source: {}", other)),
+        }
+    }
+}
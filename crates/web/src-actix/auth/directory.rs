@@ -0,0 +1,206 @@
+use crate::auth::ldap_directory::LdapDirectory;
+#[cfg(any(test, debug_assertions))]
+use crate::auth::mock_directory;
+use crate::auth::user_role::UserRole;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Selects the directory staff can log in with their domain account through: `ldap`, or
+/// `mock` in debug builds. Directory logins are turned off when it isn't set.
+const DIRECTORY_PROVIDER_VAR: &str = "DIRECTORY_PROVIDER";
+/// `;`-separated directory groups whose members get the role. A group matches by its full DN
+/// or by its CN, ignoring case.
+const ADMIN_GROUPS_VAR: &str = "DIRECTORY_ADMIN_GROUPS";
+const BUYER_GROUPS_VAR: &str = "DIRECTORY_BUYER_GROUPS";
+const WAREHOUSE_GROUPS_VAR: &str = "DIRECTORY_WAREHOUSE_GROUPS";
+/// Role for members of none of the groups above. They are turned away when it isn't set.
+const DEFAULT_ROLE_VAR: &str = "DIRECTORY_DEFAULT_ROLE";
+
+/// An account as the directory, or the identity provider of a single sign-on, knows it.
+#[derive(Debug, Clone)]
+pub struct DirectoryUser {
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    /// Groups the account is a member of, as DNs or names.
+    pub groups: Vec<String>,
+}
+
+/// Checks domain account credentials, so staff don't need a separate password here.
+#[async_trait]
+pub trait DirectoryProvider: Send + Sync {
+    /// Checks a username and password with the directory. `None` when they don't match an
+    /// account.
+    async fn authenticate(&self, username: &str, password: &str) -> Result<Option<DirectoryUser>>;
+
+    /// Looks an account up by email without its password, to check it still exists.
+    async fn find_by_email(&self, email: &str) -> Result<Option<DirectoryUser>>;
+}
+
+/// Which role members of which directory groups get.
+#[derive(Debug, Clone, Default)]
+pub struct RoleMapping {
+    /// Most privileged role first.
+    groups: Vec<(UserRole, Vec<String>)>,
+    default_role: Option<UserRole>,
+}
+
+impl RoleMapping {
+    pub fn new(groups: Vec<(UserRole, Vec<String>)>, default_role: Option<UserRole>) -> Self {
+        Self {
+            groups,
+            default_role,
+        }
+    }
+
+    /// Reads the mapping from the `DIRECTORY_*_GROUPS` and `DIRECTORY_DEFAULT_ROLE` variables.
+    pub fn from_config() -> Result<Self> {
+        Self::from_vars(
+            [ADMIN_GROUPS_VAR, BUYER_GROUPS_VAR, WAREHOUSE_GROUPS_VAR],
+            DEFAULT_ROLE_VAR,
+        )
+    }
+
+    /// Reads the mapping from variables holding the `;`-separated groups of the admin, buyer
+    /// and warehouse roles, and the role for members of none of them.
+    pub fn from_vars(group_vars: [&str; 3], default_role_var: &str) -> Result<Self> {
+        let [admin_var, buyer_var, warehouse_var] = group_vars;
+        let groups = [
+            (UserRole::Admin, admin_var),
+            (UserRole::Buyer, buyer_var),
+            (UserRole::Warehouse, warehouse_var),
+        ]
+        .into_iter()
+        .map(|(role, var)| {
+            let groups = std::env::var(var)
+                .unwrap_or_default()
+                .split(';')
+                .map(str::trim)
+                .filter(|group| !group.is_empty())
+                .map(str::to_string)
+                .collect();
+            (role, groups)
+        })
+        .collect();
+        let default_role = match std::env::var(default_role_var) {
+            Ok(role) if !role.trim().is_empty() => Some(
+                serde_json::from_value(serde_json::Value::String(role.trim().to_string()))
+                    .map_err(|e| anyhow!("Invalid {}: {}", default_role_var, e))?,
+            ),
+            _ => None,
+        };
+        Ok(Self::new(groups, default_role))
+    }
+
+    /// The role of a member of `groups`: the most privileged one mapped to any of them, or
+    /// else the default. `None` when the account shouldn't have access.
+    pub fn role_for(&self, groups: &[String]) -> Option<UserRole> {
+        self.groups
+            .iter()
+            .find(|(_, mapped)| {
+                mapped
+                    .iter()
+                    .any(|mapped| groups.iter().any(|group| group_matches(mapped, group)))
+            })
+            .map(|(role, _)| *role)
+            .or(self.default_role)
+    }
+}
+
+/// Whether a group from the directory is the configured one, compared by DN or by CN.
+fn group_matches(configured: &str, group: &str) -> bool {
+    if group.eq_ignore_ascii_case(configured) {
+        return true;
+    }
+    let first_rdn = group.split(',').next().unwrap_or_default().trim();
+    first_rdn.split_once('=').is_some_and(|(attribute, cn)| {
+        attribute.trim().eq_ignore_ascii_case("cn") && cn.trim().eq_ignore_ascii_case(configured)
+    })
+}
+
+/// Builds the directory selected by `DIRECTORY_PROVIDER`, or `None` when directory logins are
+/// turned off.
+pub fn from_config() -> Result<Option<Arc<dyn DirectoryProvider>>> {
+    let kind = std::env::var(DIRECTORY_PROVIDER_VAR).unwrap_or_default();
+    match kind.trim().to_lowercase().as_str() {
+        "" | "none" => Ok(None),
+        "ldap" => Ok(Some(Arc::new(LdapDirectory::from_config()?))),
+        #[cfg(any(test, debug_assertions))]
+        "mock" => Ok(Some(mock_directory::shared())),
+        other => Err(anyhow!(
+            "Unknown {} '{}', expected {}",
+            DIRECTORY_PROVIDER_VAR,
+            other,
+            if cfg!(debug_assertions) {
+                "ldap or mock"
+            } else {
+                "ldap"
+            }
+        )),
+    }
+}
+
+/// Whether directory logins are turned on.
+pub fn is_enabled() -> bool {
+    let kind = std::env::var(DIRECTORY_PROVIDER_VAR).unwrap_or_default();
+    !matches!(kind.trim().to_lowercase().as_str(), "" | "none")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(default_role: Option<UserRole>) -> RoleMapping {
+        RoleMapping::new(
+            vec![
+                (UserRole::Admin, vec!["PO Admins".to_string()]),
+                (UserRole::Buyer, vec!["PO Buyers".to_string()]),
+                (UserRole::Warehouse, vec!["PO Warehouse".to_string()]),
+            ],
+            default_role,
+        )
+    }
+
+    fn groups(groups: &[&str]) -> Vec<String> {
+        groups.iter().map(|group| group.to_string()).collect()
+    }
+
+    #[test]
+    fn most_privileged_role_wins() {
+        let groups = groups(&[
+            "cn=PO Warehouse,ou=Groups,dc=example,dc=test",
+            "cn=PO Buyers,ou=Groups,dc=example,dc=test",
+        ]);
+        assert_eq!(mapping(None).role_for(&groups), Some(UserRole::Buyer));
+    }
+
+    #[test]
+    fn unmapped_groups_get_the_default_role() {
+        let groups = groups(&["cn=Everyone,ou=Groups,dc=example,dc=test"]);
+        assert_eq!(mapping(None).role_for(&groups), None);
+        assert_eq!(
+            mapping(Some(UserRole::Warehouse)).role_for(&groups),
+            Some(UserRole::Warehouse)
+        );
+        assert_eq!(mapping(None).role_for(&[]), None);
+    }
+
+    #[test]
+    fn groups_match_by_dn_or_cn_ignoring_case() {
+        let dn = "cn=PO Admins,ou=Groups,dc=example,dc=test";
+        assert!(group_matches(
+            dn,
+            "CN=po admins,OU=Groups,DC=example,DC=test"
+        ));
+        assert!(group_matches("po admins", dn));
+        assert!(group_matches("PO Admins", " CN = PO Admins ,ou=Groups"));
+        assert!(group_matches("PO Admins", "PO Admins"));
+        assert!(!group_matches("PO Admins", "cn=PO Admins Old,ou=Groups"));
+        assert!(!group_matches(
+            "PO Admins",
+            "ou=PO Admins,dc=example,dc=test"
+        ));
+        assert!(!group_matches("Groups", dn));
+    }
+}
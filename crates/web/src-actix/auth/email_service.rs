@@ -1,67 +1,216 @@
+use crate::auth::invites_db::INVITE_TTL_DAYS;
+use crate::auth::user_role::UserRole;
 use anyhow::Result;
+use lettre::Message;
 use lettre::message::header::ContentType;
-use lettre::transport::smtp::authentication::Credentials;
-use lettre::{SmtpTransport, Transport};
-use log::{debug, error};
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use log::{debug, warn};
+use std::path::Path;
 
-const SMTP_HOST: &str = env!("SMTP_HOST");
 const SMTP_USERNAME: &str = env!("SMTP_USERNAME");
-const SMTP_PASSWORD: &str = env!("SMTP_PASSWORD");
 const CONFIRM_EMAIL_TEMPLATE: &str =
     include_str!("../../templates/confirm_email_template.html.tera");
 const RESET_PASSWORD_TEMPLATE: &str =
     include_str!("../../templates/reset_password_template.html.tera");
+const PO_EMAIL_TEMPLATE: &str = include_str!("../../templates/po_email_template.html.tera");
+const UNLOCK_ACCOUNT_TEMPLATE: &str =
+    include_str!("../../templates/unlock_account_template.html.tera");
+const INVITE_TEMPLATE: &str = include_str!("../../templates/invite_template.html.tera");
 
-pub struct EmailService {
-    transport: SmtpTransport,
+/// Directory checked at runtime for templates that replace the built-in ones, by file name.
+const TEMPLATE_DIR_VAR: &str = "EMAIL_TEMPLATE_DIR";
+/// Address emails are sent from. `SMTP_USERNAME` is used when it isn't set.
+const EMAIL_FROM_VAR: &str = "EMAIL_FROM";
+/// Sender when neither is set, as with the file and memory transports in development and tests.
+const DEFAULT_FROM_ADDRESS: &str = "PO Tracker <po-tracker@localhost>";
+
+/// Builds the emails the app sends; they're delivered through the email outbox.
+pub struct EmailService;
+
+/// A file attached to an outgoing email.
+pub struct EmailAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+/// A purchase order sent to a vendor contact.
+pub struct PoEmail<'a> {
+    pub to: &'a str,
+    pub cc: &'a [String],
+    pub reply_to: &'a str,
+    pub subject: &'a str,
+    pub context: tera::Context,
+    pub attachments: Vec<EmailAttachment>,
+}
+
+/// Returns the template from the override directory if it has one by that name, otherwise the
+/// built-in template.
+fn load_template(name: &str, built_in: &str) -> String {
+    if let Ok(dir) = std::env::var(TEMPLATE_DIR_VAR) {
+        let path = Path::new(&dir).join(name);
+        match std::fs::read_to_string(&path) {
+            Ok(template) => return template,
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                warn!("Failed to read email template {}: {}", path.display(), e)
+            }
+            Err(_) => {}
+        }
+    }
+    built_in.to_string()
+}
+
+/// The sender of every email.
+fn from_address() -> Result<Mailbox> {
+    let configured = std::env::var(EMAIL_FROM_VAR).unwrap_or_default();
+    let from = [configured.trim(), SMTP_USERNAME.trim()]
+        .into_iter()
+        .find(|address| !address.is_empty())
+        .unwrap_or(DEFAULT_FROM_ADDRESS);
+    Ok(from.parse()?)
+}
+
+/// Where links in emails point to.
+fn site_url() -> String {
+    if cfg!(debug_assertions) {
+        format!("http://localhost:{}", crate::PORT)
+    } else {
+        "https://potracker.mardens.com".to_string()
+    }
 }
 
 impl EmailService {
-    pub fn new() -> Result<Self> {
-        debug!(
-            "Creating SMTP transport with credentials: {}@{}",
-            SMTP_USERNAME, SMTP_HOST
-        );
-        let creds = Credentials::new(SMTP_USERNAME.to_string(), SMTP_PASSWORD.to_string());
-        let transport = SmtpTransport::starttls_relay(SMTP_HOST)?
-            .credentials(creds)
-            .build();
-        Ok(Self { transport })
+    /// Builds the email that lets a new user confirm their address.
+    ///
+    /// It isn't sent here; registration queues it in the email outbox in the same transaction
+    /// as the user, so a mail server hiccup can't undo the registration.
+    pub fn confirmation_email(
+        email_address: &str,
+        token: &str,
+        first_name: &str,
+    ) -> Result<Message> {
+        debug!("Building confirmation email for {}", email_address);
+        let mut context = tera::Context::new();
+        context.insert("email", email_address);
+        context.insert("token", token);
+        context.insert("first_name", first_name);
+        context.insert("url", &site_url());
+        let template = load_template("confirm_email_template.html.tera", CONFIRM_EMAIL_TEMPLATE);
+        let body = tera::Tera::one_off(&template, &context, true)?;
+        let email = lettre::Message::builder()
+            .from(from_address()?)
+            .to(email_address.parse()?)
+            .subject("Confirm your email address")
+            .header(ContentType::TEXT_HTML)
+            .body(body)?;
+        Ok(email)
     }
 
-    pub async fn send_confirmation_email(
-        &self,
+    /// Builds the email with a link to choose a new password, valid for `expires_minutes`.
+    /// Queued in the email outbox like the confirmation email.
+    pub fn password_reset_email(
         email_address: &str,
         token: &str,
         first_name: &str,
-    ) -> Result<()> {
-        debug!("Sending confirmation email to {}", email_address);
+        expires_minutes: i64,
+    ) -> Result<Message> {
+        debug!("Building password reset email for {}", email_address);
         let mut context = tera::Context::new();
         context.insert("email", email_address);
         context.insert("token", token);
         context.insert("first_name", first_name);
-        let url = if cfg!(debug_assertions) {
-            format!("http://localhost:{}", crate::PORT)
-        } else {
-            "https://potracker.mardens.com".to_string()
-        };
-        context.insert("url", &url);
-        let body = tera::Tera::one_off(CONFIRM_EMAIL_TEMPLATE, &context, true)?;
+        context.insert("expires_minutes", &expires_minutes);
+        context.insert("url", &site_url());
+        let template = load_template("reset_password_template.html.tera", RESET_PASSWORD_TEMPLATE);
+        let body = tera::Tera::one_off(&template, &context, true)?;
         let email = lettre::Message::builder()
-			.from(SMTP_USERNAME.parse()?)
-			.to(email_address.parse()?)
-			.subject("Confirm your email address")
-			.header(ContentType::TEXT_HTML)
-			.body(body)
-			.map_err(|e| {
-					error!("Failed to build email context with the following credentials: {SMTP_USERNAME}:{SMTP_PASSWORD}@{SMTP_HOST}");
-					anyhow::Error::from(e)
-				})?;
-        self.transport.send(&email)
-                      .map_err(|e| {
-	                      error!("Failed to send email with the following credentials: {SMTP_USERNAME}:{SMTP_PASSWORD}@{SMTP_HOST}");
-	                      anyhow::Error::from(e)
-                      })?;
-        Ok(())
+            .from(from_address()?)
+            .to(email_address.parse()?)
+            .subject("Reset your password")
+            .header(ContentType::TEXT_HTML)
+            .body(body)?;
+        Ok(email)
+    }
+
+    /// Builds the email sent when an account gets locked after too many failed logins, with a
+    /// link that unlocks it. Queued in the email outbox like the confirmation email.
+    pub fn unlock_email(
+        email_address: &str,
+        token: &str,
+        first_name: &str,
+        locked_minutes: u32,
+    ) -> Result<Message> {
+        debug!("Building unlock email for {}", email_address);
+        let mut context = tera::Context::new();
+        context.insert("email", email_address);
+        context.insert("token", token);
+        context.insert("first_name", first_name);
+        context.insert("locked_minutes", &locked_minutes);
+        context.insert("url", &site_url());
+        let template = load_template("unlock_account_template.html.tera", UNLOCK_ACCOUNT_TEMPLATE);
+        let body = tera::Tera::one_off(&template, &context, true)?;
+        let email = lettre::Message::builder()
+            .from(from_address()?)
+            .to(email_address.parse()?)
+            .subject("Your account has been locked")
+            .header(ContentType::TEXT_HTML)
+            .body(body)?;
+        Ok(email)
+    }
+
+    /// Builds the email inviting someone to create an account with the role an admin picked
+    /// for them. Queued in the email outbox like the confirmation email.
+    pub fn invite_email(
+        email_address: &str,
+        token: &str,
+        role: UserRole,
+        inviter_name: &str,
+    ) -> Result<Message> {
+        debug!("Building invite email for {}", email_address);
+        let mut context = tera::Context::new();
+        context.insert("email", email_address);
+        context.insert("token", token);
+        context.insert("role", role.label());
+        context.insert("inviter_name", inviter_name);
+        context.insert("expires_days", &INVITE_TTL_DAYS);
+        context.insert("url", &site_url());
+        let template = load_template("invite_template.html.tera", INVITE_TEMPLATE);
+        let body = tera::Tera::one_off(&template, &context, true)?;
+        let email = lettre::Message::builder()
+            .from(from_address()?)
+            .to(email_address.parse()?)
+            .subject("You're invited to the Mardens PO Tracker")
+            .header(ContentType::TEXT_HTML)
+            .body(body)?;
+        Ok(email)
+    }
+
+    /// Builds the email of a PO to a vendor contact.
+    ///
+    /// The body is rendered from `po_email_template.html.tera` with `email.context`; replies go
+    /// to `email.reply_to`, normally the buyer. It isn't sent here; the PO is queued in the email
+    /// outbox in the same transaction as its sent-log entry.
+    pub fn po_email(email: PoEmail<'_>) -> Result<Message> {
+        debug!("Building PO email to {}", email.to);
+        let template = load_template("po_email_template.html.tera", PO_EMAIL_TEMPLATE);
+        let body = tera::Tera::one_off(&template, &email.context, true)?;
+
+        let mut builder = lettre::Message::builder()
+            .from(from_address()?)
+            .reply_to(email.reply_to.parse()?)
+            .to(email.to.parse()?)
+            .subject(email.subject)
+            .message_id(None);
+        for cc in email.cc {
+            builder = builder.cc(cc.parse()?);
+        }
+        let mut parts = MultiPart::mixed().singlepart(SinglePart::html(body));
+        for attachment in email.attachments {
+            parts = parts.singlepart(Attachment::new(attachment.filename).body(
+                attachment.body,
+                ContentType::parse(&attachment.content_type)?,
+            ));
+        }
+        Ok(builder.multipart(parts)?)
     }
 }
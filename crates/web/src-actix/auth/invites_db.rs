@@ -0,0 +1,122 @@
+use crate::auth::user_role::UserRole;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::MySqlTransaction;
+
+const USER_INVITES_TABLE_SCHEMA: &str = include_str!(r#"../../sql/user_invites.sql"#);
+
+/// How long an invited user has to create their account.
+pub const INVITE_TTL_DAYS: u32 = 7;
+
+/// An invite an admin sent that hasn't been used yet.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct UserInvite {
+    pub id: u64,
+    pub email: String,
+    pub role: UserRole,
+    pub invited_by: Option<u32>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub async fn initialize_table<'a>(transaction: &mut MySqlTransaction<'a>) -> Result<()> {
+    sqlx::query(USER_INVITES_TABLE_SCHEMA)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// Invites `email` with `role`. Inviting an email again replaces its earlier invite, so only
+/// the newest link works.
+pub async fn insert_invite_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    email: &str,
+    token_hash: &str,
+    role: UserRole,
+    invited_by: u32,
+) -> Result<UserInvite> {
+    sqlx::query(
+        r#"INSERT INTO user_invites (email, token_hash, role, invited_by, expires_at) VALUES (?, ?, ?, ?, NOW() + INTERVAL ? DAY)
+           ON DUPLICATE KEY UPDATE token_hash = VALUES(token_hash), role = VALUES(role), invited_by = VALUES(invited_by), created_at = NOW(), expires_at = VALUES(expires_at)"#,
+    )
+    .bind(email)
+    .bind(token_hash)
+    .bind(role)
+    .bind(invited_by)
+    .bind(INVITE_TTL_DAYS)
+    .execute(&mut **transaction)
+    .await?;
+    let invite: UserInvite = sqlx::query_as(
+        r#"SELECT id, email, role, invited_by, created_at, expires_at FROM user_invites WHERE email = ?"#,
+    )
+    .bind(email)
+    .fetch_one(&mut **transaction)
+    .await?;
+    Ok(invite)
+}
+
+/// Removes and returns the invite a token was sent for, so it can only be used once. `None`
+/// when the token doesn't match the email's invite or the invite has expired.
+pub async fn take_invite_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    email: &str,
+    token_hash: &str,
+) -> Result<Option<UserInvite>> {
+    let invite: Option<UserInvite> = sqlx::query_as(
+        r#"SELECT id, email, role, invited_by, created_at, expires_at FROM user_invites WHERE email = ? AND token_hash = ? AND expires_at > NOW() FOR UPDATE"#,
+    )
+    .bind(email)
+    .bind(token_hash)
+    .fetch_optional(&mut **transaction)
+    .await?;
+    if let Some(invite) = &invite {
+        sqlx::query(r#"DELETE FROM user_invites WHERE id = ?"#)
+            .bind(invite.id)
+            .execute(&mut **transaction)
+            .await?;
+    }
+    Ok(invite)
+}
+
+/// Invites that haven't been used or expired yet, newest first.
+pub async fn get_invites() -> Result<Vec<UserInvite>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let invites: Vec<UserInvite> = sqlx::query_as(
+        r#"SELECT id, email, role, invited_by, created_at, expires_at FROM user_invites WHERE expires_at > NOW() ORDER BY created_at DESC"#,
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(invites)
+}
+
+/// Withdraws an invite. Returns whether there was one.
+pub async fn remove_invite(invite_id: u64) -> Result<bool> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let removed = sqlx::query(r#"DELETE FROM user_invites WHERE id = ?"#)
+        .bind(invite_id)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected()
+        > 0;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(removed)
+}
+
+/// Deletes invites nobody used in time. Returns how many were deleted.
+pub async fn remove_expired_invites() -> Result<u64> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let deleted = sqlx::query(r#"DELETE FROM user_invites WHERE expires_at <= NOW()"#)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(deleted)
+}
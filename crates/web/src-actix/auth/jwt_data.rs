@@ -6,6 +6,8 @@ pub struct Claims {
     pub email: String,  // User email
     pub exp: i64,       // Expiration time
     pub iat: i64,       // Issued at
+    pub sid: u64,       // Session ID
+    pub ver: u32,       // User's session version when issued
 }
 
 /// Authentication response with token
@@ -13,4 +15,57 @@ pub struct Claims {
 pub struct AuthResponse {
     pub token: String,
     pub token_type: String,
-}
\ No newline at end of file
+    /// Seconds until `token` expires
+    pub expires_in: i64,
+    /// Single-use token for `/auth/refresh`; a new one comes with every refresh
+    pub refresh_token: String,
+}
+
+/// What a two-factor challenge token lets the client do next
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TwoFactorStep {
+    /// Send a code from the authenticator app or a recovery code
+    Verify,
+    /// Set up an authenticator app first, because the user's role requires one
+    Setup,
+}
+
+/// Claims of the short-lived token handed out between the password and the second factor.
+/// It has no session, so it can't be used as an access token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChallengeClaims {
+    pub sub: u64,                 // User ID
+    pub email: String,            // User email
+    pub exp: i64,                 // Expiration time
+    pub iat: i64,                 // Issued at
+    pub two_factor: TwoFactorStep,
+}
+
+/// Response to a correct password when the user still has to pass the second factor
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallenge {
+    pub two_factor: TwoFactorStep,
+    /// Sent back with the code to `/auth/login/two-factor`
+    pub challenge_token: String,
+    /// Seconds until `challenge_token` expires
+    pub expires_in: i64,
+}
+
+/// Response to a login: tokens, or a challenge for the second factor
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(AuthResponse),
+    TwoFactorRequired(TwoFactorChallenge),
+}
+
+/// Response to a passed second factor. Recovery codes are only included when the
+/// authenticator app was set up during this login.
+#[derive(Debug, Serialize)]
+pub struct TwoFactorLoginResponse {
+    #[serde(flatten)]
+    pub auth: AuthResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
+}
@@ -0,0 +1,180 @@
+use crate::auth::directory::{DirectoryProvider, DirectoryUser};
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use ldap3::{Ldap, LdapConnAsync, LdapConnSettings, Scope, SearchEntry, ldap_escape};
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// `ldap://` or `ldaps://` URL of the directory server.
+const LDAP_URL_VAR: &str = "LDAP_URL";
+/// Account used to look users up. Searches are anonymous when it isn't set.
+const LDAP_BIND_DN_VAR: &str = "LDAP_BIND_DN";
+const LDAP_BIND_PASSWORD_VAR: &str = "LDAP_BIND_PASSWORD";
+/// Where users are searched for, e.g. `ou=People,dc=mardens,dc=com`.
+const LDAP_BASE_DN_VAR: &str = "LDAP_BASE_DN";
+/// Filter that finds the account for a login, with `{username}` standing in for what the user
+/// typed. For Active Directory, `(sAMAccountName={username})` is the usual choice.
+const LDAP_USER_FILTER_VAR: &str = "LDAP_USER_FILTER";
+const DEFAULT_USER_FILTER: &str = "(|(uid={username})(mail={username}))";
+/// Set to `true` to upgrade an `ldap://` connection with StartTLS.
+const LDAP_STARTTLS_VAR: &str = "LDAP_STARTTLS";
+const LDAP_EMAIL_ATTRIBUTE_VAR: &str = "LDAP_EMAIL_ATTRIBUTE";
+const DEFAULT_EMAIL_ATTRIBUTE: &str = "mail";
+/// Attribute listing the groups an account is a member of.
+const LDAP_GROUP_ATTRIBUTE_VAR: &str = "LDAP_GROUP_ATTRIBUTE";
+const DEFAULT_GROUP_ATTRIBUTE: &str = "memberOf";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Result code for a bind with the wrong password.
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// Authenticates against an LDAP server such as OpenLDAP or Active Directory: finds the
+/// account with the lookup account, then binds as it with the user's password.
+pub struct LdapDirectory {
+    url: String,
+    bind_dn: Option<String>,
+    bind_password: String,
+    base_dn: String,
+    user_filter: String,
+    starttls: bool,
+    email_attribute: String,
+    group_attribute: String,
+}
+
+impl LdapDirectory {
+    /// Reads the settings from the `LDAP_*` variables.
+    pub fn from_config() -> Result<Self> {
+        let required = |var: &str| {
+            std::env::var(var)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .ok_or_else(|| anyhow!("{} must be set for LDAP logins", var))
+        };
+        let optional = |var: &str, default: &str| {
+            std::env::var(var)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .unwrap_or_else(|| default.to_string())
+        };
+        Ok(Self {
+            url: required(LDAP_URL_VAR)?,
+            bind_dn: std::env::var(LDAP_BIND_DN_VAR)
+                .ok()
+                .filter(|value| !value.trim().is_empty()),
+            bind_password: std::env::var(LDAP_BIND_PASSWORD_VAR).unwrap_or_default(),
+            base_dn: required(LDAP_BASE_DN_VAR)?,
+            user_filter: optional(LDAP_USER_FILTER_VAR, DEFAULT_USER_FILTER),
+            starttls: optional(LDAP_STARTTLS_VAR, "false")
+                .trim()
+                .eq_ignore_ascii_case("true"),
+            email_attribute: optional(LDAP_EMAIL_ATTRIBUTE_VAR, DEFAULT_EMAIL_ATTRIBUTE),
+            group_attribute: optional(LDAP_GROUP_ATTRIBUTE_VAR, DEFAULT_GROUP_ATTRIBUTE),
+        })
+    }
+
+    /// Opens a connection, bound as the lookup account if there is one.
+    async fn connect(&self) -> Result<Ldap> {
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(CONNECT_TIMEOUT)
+            .set_starttls(self.starttls);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &self.url).await?;
+        ldap3::drive!(conn);
+        if let Some(bind_dn) = &self.bind_dn {
+            ldap.simple_bind(bind_dn, &self.bind_password)
+                .await?
+                .success()?;
+        }
+        Ok(ldap)
+    }
+
+    /// The DN and details of the one account matching `filter`. `None` when there is no
+    /// such account, or more than one.
+    async fn find_one(
+        &self,
+        ldap: &mut Ldap,
+        filter: &str,
+    ) -> Result<Option<(String, DirectoryUser)>> {
+        let attributes = [
+            self.email_attribute.as_str(),
+            "givenName",
+            "sn",
+            "cn",
+            self.group_attribute.as_str(),
+        ];
+        let (entries, _) = ldap
+            .search(&self.base_dn, Scope::Subtree, filter, attributes)
+            .await?
+            .success()?;
+        if entries.len() > 1 {
+            warn!("LDAP filter {} matched {} accounts", filter, entries.len());
+            return Ok(None);
+        }
+        let Some(entry) = entries.into_iter().next() else {
+            return Ok(None);
+        };
+        let entry = SearchEntry::construct(entry);
+        let attrs = &entry.attrs;
+        let Some(email) = first_value(attrs, &self.email_attribute) else {
+            warn!("LDAP account {} has no {}", entry.dn, self.email_attribute);
+            return Ok(None);
+        };
+        let user = DirectoryUser {
+            email,
+            first_name: first_value(attrs, "givenName")
+                .or_else(|| first_value(attrs, "cn"))
+                .unwrap_or_default(),
+            last_name: first_value(attrs, "sn").unwrap_or_default(),
+            groups: values(attrs, &self.group_attribute),
+        };
+        Ok(Some((entry.dn, user)))
+    }
+}
+
+#[async_trait]
+impl DirectoryProvider for LdapDirectory {
+    async fn authenticate(&self, username: &str, password: &str) -> Result<Option<DirectoryUser>> {
+        // A bind with an empty password is an anonymous bind, which "succeeds" for anyone.
+        if username.trim().is_empty() || password.is_empty() {
+            return Ok(None);
+        }
+        let mut ldap = self.connect().await?;
+        let filter = self
+            .user_filter
+            .replace("{username}", &ldap_escape(username.trim()));
+        let Some((dn, user)) = self.find_one(&mut ldap, &filter).await? else {
+            ldap.unbind().await?;
+            return Ok(None);
+        };
+        let bind = ldap.simple_bind(&dn, password).await?;
+        let user = if bind.rc == INVALID_CREDENTIALS {
+            debug!("Wrong password for LDAP account {}", dn);
+            None
+        } else {
+            bind.success()?;
+            Some(user)
+        };
+        ldap.unbind().await?;
+        Ok(user)
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<DirectoryUser>> {
+        let mut ldap = self.connect().await?;
+        let filter = format!("({}={})", self.email_attribute, ldap_escape(email));
+        let user = self.find_one(&mut ldap, &filter).await?;
+        ldap.unbind().await?;
+        Ok(user.map(|(_, user)| user))
+    }
+}
+
+/// Attribute names come back in whatever case the server stores them in.
+fn values(attrs: &HashMap<String, Vec<String>>, name: &str) -> Vec<String> {
+    attrs
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, values)| values.clone())
+        .unwrap_or_default()
+}
+
+fn first_value(attrs: &HashMap<String, Vec<String>>, name: &str) -> Option<String> {
+    values(attrs, name).into_iter().next()
+}
@@ -0,0 +1,167 @@
+use crate::util::rate_limit::client_ip;
+use actix_web::HttpRequest;
+use actix_web::http::header;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::MySqlTransaction;
+
+const LOGIN_ATTEMPTS_TABLE_SCHEMA: &str = include_str!(r#"../../sql/login_attempts.sql"#);
+
+/// Longest user agent stored; longer ones are cut off.
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// A login attempt, for the login history.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct LoginAttempt {
+    pub id: u64,
+    pub email: String,
+    pub user_id: Option<u32>,
+    pub ip_address: String,
+    pub user_agent: Option<String>,
+    pub success: bool,
+    pub failure_reason: Option<String>,
+    pub attempted_at: DateTime<Utc>,
+}
+
+/// Where a login attempt came from.
+#[derive(Debug, Clone)]
+pub struct LoginClient {
+    pub ip_address: String,
+    pub user_agent: Option<String>,
+}
+
+impl LoginClient {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let user_agent = req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+        Self {
+            ip_address: client_ip(req),
+            user_agent,
+        }
+    }
+}
+
+/// Why a login attempt was turned down, as stored in `login_attempts.failure_reason`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureReason {
+    UnknownEmail,
+    WrongPassword,
+    Locked,
+    RateLimited,
+    EmailNotConfirmed,
+    /// The user registered themselves and no admin has approved them yet.
+    PendingApproval,
+    PasswordResetRequired,
+    /// The password was right and the second factor is still to come.
+    TwoFactorPending,
+    WrongTwoFactorCode,
+    /// The directory accepted the password, but the account is in no group with a role.
+    NoDirectoryRole,
+    /// The identity provider vouched for the account, but it is in no group with a role.
+    NoOidcRole,
+    /// A directory or single sign-on login matched a local account that may not be linked.
+    LocalAccountExists,
+}
+
+impl FailureReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailureReason::UnknownEmail => "unknown_email",
+            FailureReason::WrongPassword => "wrong_password",
+            FailureReason::Locked => "locked",
+            FailureReason::RateLimited => "rate_limited",
+            FailureReason::EmailNotConfirmed => "email_not_confirmed",
+            FailureReason::PendingApproval => "pending_approval",
+            FailureReason::PasswordResetRequired => "password_reset_required",
+            FailureReason::TwoFactorPending => "two_factor_pending",
+            FailureReason::WrongTwoFactorCode => "wrong_two_factor_code",
+            FailureReason::NoDirectoryRole => "no_directory_role",
+            FailureReason::NoOidcRole => "no_oidc_role",
+            FailureReason::LocalAccountExists => "local_account_exists",
+        }
+    }
+}
+
+pub async fn initialize_table<'a>(transaction: &mut MySqlTransaction<'a>) -> Result<()> {
+    sqlx::query(LOGIN_ATTEMPTS_TABLE_SCHEMA)
+        .execute(&mut **transaction)
+        .await?;
+    crate::app_db::add_column_if_missing(
+        transaction,
+        "login_attempts",
+        "user_agent",
+        "VARCHAR(512) NULL",
+    )
+    .await?;
+    Ok(())
+}
+
+/// Records a login attempt; `failure` is `None` for a successful one.
+pub async fn record_attempt(
+    email: &str,
+    user_id: Option<u32>,
+    client: &LoginClient,
+    failure: Option<FailureReason>,
+) -> Result<()> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    sqlx::query(
+        r#"INSERT INTO login_attempts (email, user_id, ip_address, user_agent, success, failure_reason) VALUES (?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(email)
+    .bind(user_id)
+    .bind(&client.ip_address)
+    .bind(&client.user_agent)
+    .bind(failure.is_none())
+    .bind(failure.map(FailureReason::as_str))
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(())
+}
+
+/// The latest login attempts, newest first, narrowed down by whichever filters are given.
+pub async fn get_attempts(
+    user_id: Option<u32>,
+    email: Option<&str>,
+    success: Option<bool>,
+    limit: u32,
+) -> Result<Vec<LoginAttempt>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let attempts: Vec<LoginAttempt> = sqlx::query_as(
+        r#"SELECT id, email, user_id, ip_address, user_agent, success, failure_reason, attempted_at FROM login_attempts WHERE (? IS NULL OR user_id = ?) AND (? IS NULL OR email = ?) AND (? IS NULL OR success = ?) ORDER BY id DESC LIMIT ?"#,
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .bind(email)
+    .bind(email)
+    .bind(success)
+    .bind(success)
+    .bind(limit)
+    .fetch_all(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(attempts)
+}
+
+/// Deletes attempts older than `days` days. Returns how many were deleted.
+pub async fn purge_attempts(days: u32) -> Result<u64> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let deleted =
+        sqlx::query(r#"DELETE FROM login_attempts WHERE attempted_at < NOW() - INTERVAL ? DAY"#)
+            .bind(days)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected();
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(deleted)
+}
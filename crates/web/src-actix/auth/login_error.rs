@@ -0,0 +1,84 @@
+use actix_web::http::StatusCode;
+use actix_web::http::header;
+use actix_web::{HttpResponse, ResponseError};
+use log::error;
+use serde_json::json;
+use std::time::Duration;
+
+/// Why a login was refused. Unknown emails and wrong passwords share one error so the
+/// response doesn't tell which emails have an account.
+#[derive(Debug, thiserror::Error)]
+pub enum LoginError {
+    #[error("Invalid email or password")]
+    InvalidCredentials,
+    #[error("Too many failed login attempts, try again later")]
+    TooManyAttempts { retry_after: Duration },
+    #[error("Email not confirmed")]
+    EmailNotConfirmed,
+    #[error("Your account is waiting for an administrator to approve it")]
+    PendingApproval,
+    #[error("Password reset required")]
+    PasswordResetRequired,
+    #[error("Invalid or expired two-factor challenge, log in again")]
+    InvalidChallenge,
+    #[error("Invalid two-factor code")]
+    InvalidTwoFactorCode,
+    #[error("Directory login is not enabled")]
+    DirectoryDisabled,
+    #[error("Your directory account isn't in a group with access")]
+    NoDirectoryRole,
+    #[error("Single sign-on is not enabled")]
+    OidcDisabled,
+    #[error("Redirect URI is not allowed for single sign-on")]
+    InvalidRedirectUri,
+    #[error("Single sign-on failed or expired, try again")]
+    InvalidOidcLogin,
+    #[error("Your single sign-on account isn't in a group with access")]
+    NoOidcRole,
+    #[error("An account with this email already exists, log in with its password instead")]
+    LocalAccountExists,
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for LoginError {
+    fn from(e: sqlx::Error) -> Self {
+        LoginError::Other(e.into())
+    }
+}
+
+impl ResponseError for LoginError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            LoginError::InvalidCredentials
+            | LoginError::InvalidChallenge
+            | LoginError::InvalidTwoFactorCode
+            | LoginError::InvalidOidcLogin => StatusCode::UNAUTHORIZED,
+            LoginError::TooManyAttempts { .. } => StatusCode::TOO_MANY_REQUESTS,
+            LoginError::EmailNotConfirmed
+            | LoginError::PendingApproval
+            | LoginError::PasswordResetRequired
+            | LoginError::NoDirectoryRole
+            | LoginError::NoOidcRole => StatusCode::FORBIDDEN,
+            LoginError::LocalAccountExists => StatusCode::CONFLICT,
+            LoginError::InvalidRedirectUri => StatusCode::BAD_REQUEST,
+            LoginError::DirectoryDisabled | LoginError::OidcDisabled => StatusCode::NOT_FOUND,
+            LoginError::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let LoginError::TooManyAttempts { retry_after } = self {
+            response.insert_header((header::RETRY_AFTER, retry_after.as_secs().max(1)));
+        }
+        let message = match self {
+            LoginError::Other(e) => {
+                error!("Login failed: {e}");
+                "Internal server error".to_string()
+            }
+            other => other.to_string(),
+        };
+        response.json(json!({ "error": message }))
+    }
+}
@@ -0,0 +1,57 @@
+use crate::auth::login_error::LoginError;
+use crate::util::rate_limit::{RateLimiter, client_ip};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Failed logins in a row before an account is locked, and again for every further lockout.
+const LOCKOUT_THRESHOLD: u32 = 5;
+/// The first lockout; it doubles with every further one.
+const BASE_LOCKOUT_SECONDS: u32 = 15 * 60;
+const MAX_LOCKOUT_SECONDS: u32 = 24 * 60 * 60;
+
+/// Requests per IP address to the login endpoints.
+static IP_LIMITER: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(30, Duration::from_mins(15)));
+
+/// Failed logins per email address, whether or not it has an account, so unknown emails
+/// are turned away just like locked accounts.
+pub static ACCOUNT_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| {
+    RateLimiter::new(
+        LOCKOUT_THRESHOLD as usize,
+        Duration::from_secs(BASE_LOCKOUT_SECONDS as u64),
+    )
+});
+
+/// Middleware that turns away clients making too many requests to the login endpoints.
+pub async fn limit_by_ip(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let ip_address = client_ip(req.request());
+    if let Err(retry_after) = IP_LIMITER.check_and_hit(&ip_address) {
+        return Err(LoginError::TooManyAttempts { retry_after }.into());
+    }
+    next.call(req).await
+}
+
+/// The key `ACCOUNT_LIMITER` tracks an email under.
+pub fn account_key(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// How long to lock an account after `failed_logins` failures in a row, if at all. Every
+/// `LOCKOUT_THRESHOLD` failures lock it again, each time for twice as long.
+pub fn lockout_seconds(failed_logins: u32) -> Option<u32> {
+    if failed_logins == 0 || !failed_logins.is_multiple_of(LOCKOUT_THRESHOLD) {
+        return None;
+    }
+    let factor = 2u32.saturating_pow(failed_logins / LOCKOUT_THRESHOLD - 1);
+    Some(
+        BASE_LOCKOUT_SECONDS
+            .saturating_mul(factor)
+            .min(MAX_LOCKOUT_SECONDS),
+    )
+}
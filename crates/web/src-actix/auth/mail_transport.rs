@@ -0,0 +1,218 @@
+use anyhow::{Result, anyhow};
+use lettre::address::Envelope;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{FileTransport, Message, SmtpTransport, Transport};
+use log::debug;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex};
+
+const SMTP_HOST: &str = env!("SMTP_HOST");
+const SMTP_USERNAME: &str = env!("SMTP_USERNAME");
+const SMTP_PASSWORD: &str = env!("SMTP_PASSWORD");
+
+/// Selects the transport: `smtp` (the default), `file` or `memory`.
+const MAIL_TRANSPORT_VAR: &str = "MAIL_TRANSPORT";
+/// Folder the `file` transport writes `.eml` files to.
+const MAIL_DROP_DIR_VAR: &str = "MAIL_DROP_DIR";
+const DEFAULT_MAIL_DROP_DIR: &str = "target/dev-env/mail";
+/// `host:port` of a plain, unauthenticated SMTP server (e.g. MailHog) to send through instead
+/// of the relay, for trying out emails locally.
+const SMTP_STANDIN_VAR: &str = "SMTP_STANDIN";
+
+static MEMORY_OUTBOX: LazyLock<Arc<MemoryTransport>> =
+    LazyLock::new(|| Arc::new(MemoryTransport::default()));
+
+/// Delivers a built email.
+pub trait MailTransport: Send + Sync {
+    /// Delivers an already formatted message, as stored in the outbox.
+    fn send_raw(&self, envelope: &Envelope, message: &[u8]) -> Result<()>;
+
+    fn send(&self, message: &Message) -> Result<()> {
+        self.send_raw(message.envelope(), &message.formatted())
+    }
+}
+
+/// Sends through the SMTP relay, or through the stand-in when `SMTP_STANDIN` is set.
+pub struct SmtpMailTransport(SmtpTransport);
+
+/// Writes each email to `<uuid>.eml` in a folder instead of sending it.
+pub struct FileMailTransport(FileTransport);
+
+/// Keeps sent emails in memory so tests can assert on them.
+#[derive(Default)]
+pub struct MemoryTransport {
+    messages: Mutex<Vec<SentMessage>>,
+}
+
+/// An email captured by the [`MemoryTransport`].
+#[derive(Debug, Clone)]
+pub struct SentMessage {
+    pub to: Vec<String>,
+    pub subject: String,
+    /// The full message as it would have gone over the wire.
+    pub raw: String,
+}
+
+impl SmtpMailTransport {
+    pub fn new() -> Result<Self> {
+        if let Ok(standin) = std::env::var(SMTP_STANDIN_VAR) {
+            let (host, port) = standin.rsplit_once(':').unwrap_or((standin.as_str(), "25"));
+            debug!("Using SMTP stand-in at {}:{}", host, port);
+            let transport = SmtpTransport::builder_dangerous(host)
+                .port(port.parse()?)
+                .build();
+            return Ok(Self(transport));
+        }
+        debug!(
+            "Creating SMTP transport with credentials: {}@{}",
+            SMTP_USERNAME, SMTP_HOST
+        );
+        let creds = Credentials::new(SMTP_USERNAME.to_string(), SMTP_PASSWORD.to_string());
+        let transport = SmtpTransport::starttls_relay(SMTP_HOST)?
+            .credentials(creds)
+            .build();
+        Ok(Self(transport))
+    }
+}
+
+impl MailTransport for SmtpMailTransport {
+    fn send_raw(&self, envelope: &Envelope, message: &[u8]) -> Result<()> {
+        self.0.send_raw(envelope, message)?;
+        Ok(())
+    }
+}
+
+impl FileMailTransport {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        debug!("Writing emails to {}", dir.display());
+        Ok(Self(FileTransport::new(dir)))
+    }
+}
+
+impl MailTransport for FileMailTransport {
+    fn send_raw(&self, envelope: &Envelope, message: &[u8]) -> Result<()> {
+        self.0.send_raw(envelope, message)?;
+        Ok(())
+    }
+}
+
+impl MemoryTransport {
+    /// The emails sent so far, oldest first.
+    pub fn messages(&self) -> Vec<SentMessage> {
+        self.messages.lock().expect("outbox lock poisoned").clone()
+    }
+
+    /// Removes and returns the emails sent so far.
+    pub fn take(&self) -> Vec<SentMessage> {
+        std::mem::take(&mut *self.messages.lock().expect("outbox lock poisoned"))
+    }
+}
+
+impl MailTransport for MemoryTransport {
+    fn send_raw(&self, envelope: &Envelope, message: &[u8]) -> Result<()> {
+        let raw = String::from_utf8_lossy(message).into_owned();
+        let sent = SentMessage {
+            to: envelope.to().iter().map(ToString::to_string).collect(),
+            subject: raw
+                .lines()
+                .take_while(|line| !line.is_empty())
+                .find_map(|line| line.strip_prefix("Subject: "))
+                .unwrap_or_default()
+                .to_string(),
+            raw,
+        };
+        self.messages
+            .lock()
+            .expect("outbox lock poisoned")
+            .push(sent);
+        Ok(())
+    }
+}
+
+/// The outbox shared by every service using the `memory` transport.
+pub fn memory_outbox() -> Arc<MemoryTransport> {
+    MEMORY_OUTBOX.clone()
+}
+
+/// Builds the transport selected by `MAIL_TRANSPORT`.
+pub fn from_config() -> Result<Arc<dyn MailTransport>> {
+    let kind = std::env::var(MAIL_TRANSPORT_VAR).unwrap_or_default();
+    match kind.trim().to_lowercase().as_str() {
+        "" | "smtp" => Ok(Arc::new(SmtpMailTransport::new()?)),
+        "file" => {
+            let dir = std::env::var(MAIL_DROP_DIR_VAR)
+                .unwrap_or_else(|_| DEFAULT_MAIL_DROP_DIR.to_string());
+            Ok(Arc::new(FileMailTransport::new(dir)?))
+        }
+        "memory" => Ok(memory_outbox()),
+        other => Err(anyhow!(
+            "Unknown {} '{}', expected smtp, file or memory",
+            MAIL_TRANSPORT_VAR,
+            other
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::email_service::EmailService;
+    use crate::auth::user_role::UserRole;
+
+    fn deliver(message: Message) -> SentMessage {
+        let transport = MemoryTransport::default();
+        transport.send(&message).unwrap();
+        let mut sent = transport.take();
+        assert_eq!(sent.len(), 1);
+        assert!(transport.messages().is_empty());
+        sent.remove(0)
+    }
+
+    #[test]
+    fn sends_confirmation_email() {
+        let message =
+            EmailService::confirmation_email("sam@example.test", "confirm123", "Sam").unwrap();
+        let sent = deliver(message);
+        assert_eq!(sent.to, ["sam@example.test"]);
+        assert_eq!(sent.subject, "Confirm your email address");
+        assert!(sent.raw.contains("confirm123"));
+    }
+
+    #[test]
+    fn sends_password_reset_email() {
+        let message =
+            EmailService::password_reset_email("sam@example.test", "reset123", "Sam", 60).unwrap();
+        let sent = deliver(message);
+        assert_eq!(sent.to, ["sam@example.test"]);
+        assert_eq!(sent.subject, "Reset your password");
+        assert!(sent.raw.contains("reset123"));
+    }
+
+    #[test]
+    fn sends_unlock_email() {
+        let message =
+            EmailService::unlock_email("sam@example.test", "unlock123", "Sam", 15).unwrap();
+        let sent = deliver(message);
+        assert_eq!(sent.to, ["sam@example.test"]);
+        assert_eq!(sent.subject, "Your account has been locked");
+        assert!(sent.raw.contains("unlock123"));
+    }
+
+    #[test]
+    fn sends_invite_email() {
+        let message = EmailService::invite_email(
+            "sam@example.test",
+            "invite123",
+            UserRole::Buyer,
+            "Ava Admin",
+        )
+        .unwrap();
+        let sent = deliver(message);
+        assert_eq!(sent.to, ["sam@example.test"]);
+        assert_eq!(sent.subject, "You're invited to the Mardens PO Tracker");
+        assert!(sent.raw.contains("invite123"));
+        assert!(sent.raw.contains("buyer"));
+    }
+}
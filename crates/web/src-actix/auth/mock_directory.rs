@@ -0,0 +1,172 @@
+use crate::auth::directory::{DirectoryProvider, DirectoryUser};
+use anyhow::Result;
+use async_trait::async_trait;
+use log::{debug, warn};
+use serde::Deserialize;
+use std::sync::{Arc, LazyLock};
+
+/// JSON file with the accounts of the `mock` directory.
+const DIRECTORY_MOCK_FILE_VAR: &str = "DIRECTORY_MOCK_FILE";
+
+static MOCK_DIRECTORY: LazyLock<Arc<MockDirectory>> = LazyLock::new(|| {
+    let directory = match std::env::var(DIRECTORY_MOCK_FILE_VAR) {
+        Ok(path) => MockDirectory::from_file(&path).unwrap_or_else(|e| {
+            warn!("Failed to load mock directory from {}: {}", path, e);
+            MockDirectory::default()
+        }),
+        Err(_) => MockDirectory::default(),
+    };
+    Arc::new(directory)
+});
+
+/// An account of the [`MockDirectory`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct MockDirectoryAccount {
+    pub username: String,
+    pub password: String,
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+/// Keeps directory accounts in memory, for trying out directory logins and for tests,
+/// without an LDAP server. Only built into debug builds, so its accounts can't log in to a
+/// release build.
+#[derive(Debug, Default)]
+pub struct MockDirectory {
+    accounts: Vec<MockDirectoryAccount>,
+}
+
+impl MockDirectory {
+    pub fn new(accounts: Vec<MockDirectoryAccount>) -> Self {
+        Self { accounts }
+    }
+
+    /// Loads the accounts from a JSON array.
+    pub fn from_file(path: &str) -> Result<Self> {
+        let accounts: Vec<MockDirectoryAccount> =
+            serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        debug!("Loaded {} mock directory accounts", accounts.len());
+        Ok(Self::new(accounts))
+    }
+
+    fn find(
+        &self,
+        matches: impl Fn(&MockDirectoryAccount) -> bool,
+    ) -> Option<MockDirectoryAccount> {
+        self.accounts
+            .iter()
+            .find(|account| matches(account))
+            .cloned()
+    }
+}
+
+impl From<MockDirectoryAccount> for DirectoryUser {
+    fn from(account: MockDirectoryAccount) -> Self {
+        Self {
+            email: account.email,
+            first_name: account.first_name,
+            last_name: account.last_name,
+            groups: account.groups,
+        }
+    }
+}
+
+#[async_trait]
+impl DirectoryProvider for MockDirectory {
+    async fn authenticate(&self, username: &str, password: &str) -> Result<Option<DirectoryUser>> {
+        let account = self.find(|account| {
+            account.username.eq_ignore_ascii_case(username)
+                || account.email.eq_ignore_ascii_case(username)
+        });
+        Ok(account
+            .filter(|account| !password.is_empty() && account.password == password)
+            .map(DirectoryUser::from))
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<DirectoryUser>> {
+        Ok(self
+            .find(|account| account.email.eq_ignore_ascii_case(email))
+            .map(DirectoryUser::from))
+    }
+}
+
+/// The directory shared by everything using the `mock` provider.
+pub fn shared() -> Arc<MockDirectory> {
+    MOCK_DIRECTORY.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory() -> MockDirectory {
+        MockDirectory::new(vec![MockDirectoryAccount {
+            username: "bbuyer".to_string(),
+            password: "password".to_string(),
+            email: "blake.buyer@example.test".to_string(),
+            first_name: "Blake".to_string(),
+            last_name: "Buyer".to_string(),
+            groups: vec!["cn=PO Buyers,ou=Groups,dc=example,dc=test".to_string()],
+        }])
+    }
+
+    #[actix_web::test]
+    async fn authenticates_by_username_or_email() {
+        let directory = directory();
+        let user = directory
+            .authenticate("BBuyer", "password")
+            .await
+            .unwrap()
+            .expect("username should log in");
+        assert_eq!(user.email, "blake.buyer@example.test");
+        assert_eq!(user.groups.len(), 1);
+        assert!(
+            directory
+                .authenticate("Blake.Buyer@example.test", "password")
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[actix_web::test]
+    async fn refuses_wrong_or_empty_passwords() {
+        let directory = directory();
+        assert!(
+            directory
+                .authenticate("bbuyer", "Password")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            directory
+                .authenticate("bbuyer", "")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            directory
+                .authenticate("nobody", "password")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[actix_web::test]
+    async fn finds_accounts_by_email() {
+        let directory = directory();
+        let user = directory
+            .find_by_email("BLAKE.BUYER@example.test")
+            .await
+            .unwrap()
+            .expect("account should be found");
+        assert_eq!(user.first_name, "Blake");
+        assert!(directory.find_by_email("bbuyer").await.unwrap().is_none());
+    }
+}
@@ -1,13 +1,31 @@
 mod users_db;
-mod users_data;
+pub(crate) mod users_data;
 mod user_role;
 mod auth_endpoint;
 mod auth_endpoint_data;
-mod jwt_data;
+pub(crate) mod jwt_data;
 mod auth_service;
-mod auth_middleware;
-mod registration_db;
-mod email_service;
+pub(crate) mod auth_middleware;
+pub(crate) mod registration_db;
+mod registration_error;
+mod password_policy;
+pub(crate) mod invites_db;
+pub(crate) mod sessions_db;
+pub(crate) mod login_attempts_db;
+mod login_error;
+mod login_guard;
+mod totp;
+mod auth_source;
+pub mod directory;
+mod ldap_directory;
+#[cfg(any(test, debug_assertions))]
+mod mock_directory;
+mod oidc;
+pub(crate) mod oidc_db;
+pub(crate) mod two_factor;
+pub(crate) mod two_factor_db;
+pub(crate) mod email_service;
+pub mod mail_transport;
 
 use sqlx::MySqlTransaction;
 pub use auth_endpoint::configure;
@@ -15,5 +33,10 @@ pub use auth_endpoint::configure;
 pub async fn initialize_table<'a>(transaction: &mut MySqlTransaction<'a>) -> anyhow::Result<()> {
 	users_db::initialize_table(transaction).await?;
 	registration_db::initialize_table(transaction).await?;
+	invites_db::initialize_table(transaction).await?;
+	sessions_db::initialize_table(transaction).await?;
+	login_attempts_db::initialize_table(transaction).await?;
+	two_factor_db::initialize_table(transaction).await?;
+	oidc_db::initialize_table(transaction).await?;
 	Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,467 @@
+use crate::auth::auth_service::{generate_token, hash_token};
+use crate::auth::directory::{DirectoryUser, RoleMapping};
+use crate::auth::oidc_db::{self, PendingOidcLogin};
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use url::Url;
+
+/// Issuer URL of the identity provider, e.g. `https://login.microsoftonline.com/{tenant}/v2.0`.
+/// Single sign-on is turned off when it isn't set.
+const OIDC_ISSUER_VAR: &str = "OIDC_ISSUER";
+const OIDC_CLIENT_ID_VAR: &str = "OIDC_CLIENT_ID";
+/// Sent with the authorization code when the identity provider treats us as a confidential
+/// client. PKCE alone is used when it isn't set.
+const OIDC_CLIENT_SECRET_VAR: &str = "OIDC_CLIENT_SECRET";
+/// `;`-separated redirect URIs of the web dashboard, as registered with the identity provider.
+const OIDC_REDIRECT_URIS_VAR: &str = "OIDC_REDIRECT_URIS";
+/// Set to `false` to refuse the `http://127.0.0.1:{port}` redirects the desktop app listens
+/// on. The identity provider has to allow them as well.
+const OIDC_ALLOW_LOOPBACK_VAR: &str = "OIDC_ALLOW_LOOPBACK";
+/// Set to `false` to accept ID tokens without an `email_verified` claim, for identity
+/// providers that only hand out verified emails and don't send it. Tokens saying the email
+/// isn't verified are always refused.
+const OIDC_REQUIRE_EMAIL_VERIFIED_VAR: &str = "OIDC_REQUIRE_EMAIL_VERIFIED";
+const OIDC_SCOPES_VAR: &str = "OIDC_SCOPES";
+const DEFAULT_SCOPES: &str = "openid email profile";
+/// Claim listing the groups or roles of the user. Nested claims are written with dots, e.g.
+/// `realm_access.roles` for Keycloak.
+const OIDC_GROUPS_CLAIM_VAR: &str = "OIDC_GROUPS_CLAIM";
+const DEFAULT_GROUPS_CLAIM: &str = "groups";
+/// Shown on the sign-in button.
+const OIDC_DISPLAY_NAME_VAR: &str = "OIDC_DISPLAY_NAME";
+const DEFAULT_DISPLAY_NAME: &str = "Single Sign-On";
+/// Same as the `DIRECTORY_*` role mapping, for the groups in the groups claim.
+const ADMIN_GROUPS_VAR: &str = "OIDC_ADMIN_GROUPS";
+const BUYER_GROUPS_VAR: &str = "OIDC_BUYER_GROUPS";
+const WAREHOUSE_GROUPS_VAR: &str = "OIDC_WAREHOUSE_GROUPS";
+const DEFAULT_ROLE_VAR: &str = "OIDC_DEFAULT_ROLE";
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How to reach the identity provider for single sign-on with OpenID Connect.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    issuer: String,
+    client_id: String,
+    client_secret: Option<String>,
+    redirect_uris: Vec<String>,
+    allow_loopback: bool,
+    require_email_verified: bool,
+    scopes: String,
+    groups_claim: String,
+    display_name: String,
+}
+
+/// Where to send the user to sign in with the identity provider. The client keeps `state`
+/// to check that the redirect back belongs to the login it started.
+#[derive(Debug, Serialize)]
+pub struct OidcAuthorization {
+    pub authorization_url: String,
+    pub state: String,
+}
+
+/// The parts of the identity provider's discovery document used here.
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+impl OidcConfig {
+    /// Reads the settings from the `OIDC_*` variables, or `None` when single sign-on is off.
+    pub fn from_config() -> Result<Option<Self>> {
+        let var = |var: &str| {
+            std::env::var(var)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let Some(issuer) = var(OIDC_ISSUER_VAR) else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            issuer: issuer.trim_end_matches('/').to_string(),
+            client_id: var(OIDC_CLIENT_ID_VAR)
+                .ok_or_else(|| anyhow!("{} must be set for single sign-on", OIDC_CLIENT_ID_VAR))?,
+            client_secret: var(OIDC_CLIENT_SECRET_VAR),
+            redirect_uris: var(OIDC_REDIRECT_URIS_VAR)
+                .unwrap_or_default()
+                .split(';')
+                .map(str::trim)
+                .filter(|uri| !uri.is_empty())
+                .map(str::to_string)
+                .collect(),
+            allow_loopback: !var(OIDC_ALLOW_LOOPBACK_VAR)
+                .is_some_and(|value| value.eq_ignore_ascii_case("false")),
+            require_email_verified: !var(OIDC_REQUIRE_EMAIL_VERIFIED_VAR)
+                .is_some_and(|value| value.eq_ignore_ascii_case("false")),
+            scopes: var(OIDC_SCOPES_VAR).unwrap_or_else(|| DEFAULT_SCOPES.to_string()),
+            groups_claim: var(OIDC_GROUPS_CLAIM_VAR)
+                .unwrap_or_else(|| DEFAULT_GROUPS_CLAIM.to_string()),
+            display_name: var(OIDC_DISPLAY_NAME_VAR)
+                .unwrap_or_else(|| DEFAULT_DISPLAY_NAME.to_string()),
+        }))
+    }
+
+    /// Reads the mapping from the `OIDC_*_GROUPS` and `OIDC_DEFAULT_ROLE` variables.
+    pub fn role_mapping() -> Result<RoleMapping> {
+        RoleMapping::from_vars(
+            [ADMIN_GROUPS_VAR, BUYER_GROUPS_VAR, WAREHOUSE_GROUPS_VAR],
+            DEFAULT_ROLE_VAR,
+        )
+    }
+
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
+
+    /// The registered redirect URIs of the web dashboard, or any port on the loopback address
+    /// for the desktop app (RFC 8252).
+    fn allows_redirect_uri(&self, redirect_uri: &str) -> bool {
+        if self
+            .redirect_uris
+            .iter()
+            .any(|allowed| allowed == redirect_uri)
+        {
+            return true;
+        }
+        self.allow_loopback
+            && Url::parse(redirect_uri).is_ok_and(|url| {
+                url.scheme() == "http" && matches!(url.host_str(), Some("127.0.0.1" | "[::1]"))
+            })
+    }
+}
+
+/// Starts a login with the identity provider, which sends the user back to `redirect_uri`
+/// with a code for `complete_login`. `None` when the redirect URI isn't allowed.
+///
+/// The PKCE verifier and the nonce stay here, so only this server can redeem the code.
+pub async fn begin_login(
+    config: &OidcConfig,
+    redirect_uri: &str,
+) -> Result<Option<OidcAuthorization>> {
+    if !config.allows_redirect_uri(redirect_uri) {
+        warn!("Refused single sign-on redirect URI {}", redirect_uri);
+        return Ok(None);
+    }
+    let metadata = discover(config).await?;
+    let state = generate_token();
+    let login = PendingOidcLogin {
+        code_verifier: generate_token(),
+        nonce: generate_token(),
+        redirect_uri: redirect_uri.to_string(),
+    };
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(login.code_verifier.as_bytes()));
+    let authorization_url = Url::parse_with_params(
+        &metadata.authorization_endpoint,
+        [
+            ("response_type", "code"),
+            ("client_id", config.client_id.as_str()),
+            ("redirect_uri", redirect_uri),
+            ("scope", config.scopes.as_str()),
+            ("state", state.as_str()),
+            ("nonce", login.nonce.as_str()),
+            ("code_challenge", code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )?;
+    oidc_db::insert_login(&hash_token(&state), &login).await?;
+    Ok(Some(OidcAuthorization {
+        authorization_url: authorization_url.into(),
+        state,
+    }))
+}
+
+/// Redeems the code the identity provider sent back with `state`, and returns who it vouches
+/// for. `None` when the login is unknown or expired, the code is refused, or the ID token
+/// doesn't check out.
+pub async fn complete_login(
+    config: &OidcConfig,
+    code: &str,
+    state: &str,
+) -> Result<Option<DirectoryUser>> {
+    let Some(login) = oidc_db::take_login(&hash_token(state)).await? else {
+        warn!("Single sign-on came back with an unknown or expired state");
+        return Ok(None);
+    };
+    let metadata = discover(config).await?;
+
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", login.redirect_uri.as_str()),
+        ("code_verifier", login.code_verifier.as_str()),
+        ("client_id", config.client_id.as_str()),
+    ];
+    if let Some(client_secret) = &config.client_secret {
+        form.push(("client_secret", client_secret.as_str()));
+    }
+    let mut response = http_client()
+        .post(&metadata.token_endpoint)
+        .send_form(&form)
+        .await
+        .map_err(|e| anyhow!("Request to {} failed: {}", metadata.token_endpoint, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.body().await.unwrap_or_default();
+        warn!(
+            "Identity provider refused the authorization code ({}): {}",
+            status,
+            String::from_utf8_lossy(&body)
+        );
+        return Ok(None);
+    }
+    let tokens: TokenResponse = response
+        .json()
+        .await
+        .map_err(|e| anyhow!("Invalid token response: {}", e))?;
+
+    let jwks: JwkSet = get_json(&metadata.jwks_uri).await?;
+    let claims = match verify_id_token(&tokens.id_token, &jwks, &metadata.issuer, config) {
+        Ok(claims) => claims,
+        Err(e) => {
+            warn!("Rejected ID token: {}", e);
+            return Ok(None);
+        }
+    };
+    if claims.get("nonce").and_then(Value::as_str) != Some(login.nonce.as_str()) {
+        warn!("Rejected ID token with the wrong nonce");
+        return Ok(None);
+    }
+    Ok(user_from_claims(&claims, config))
+}
+
+async fn discover(config: &OidcConfig) -> Result<ProviderMetadata> {
+    get_json(&format!(
+        "{}/.well-known/openid-configuration",
+        config.issuer
+    ))
+    .await
+}
+
+/// Checks the ID token's signature against the identity provider's keys, and that it was
+/// issued by it for us and hasn't expired.
+fn verify_id_token(
+    id_token: &str,
+    jwks: &JwkSet,
+    issuer: &str,
+    config: &OidcConfig,
+) -> Result<Map<String, Value>> {
+    let header = decode_header(id_token)?;
+    // Shared-secret algorithms would let anyone who knows the client secret sign tokens.
+    if matches!(
+        header.alg,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    ) {
+        return Err(anyhow!("ID token is signed with {:?}", header.alg));
+    }
+    let jwk = match &header.kid {
+        Some(kid) => jwks.find(kid),
+        None => jwks.keys.first(),
+    }
+    .ok_or_else(|| anyhow!("No key for the ID token in the identity provider's JWKS"))?;
+    let key = DecodingKey::from_jwk(jwk)?;
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[issuer]);
+    validation.set_audience(&[config.client_id.as_str()]);
+    Ok(decode::<Map<String, Value>>(id_token, &key, &validation)?.claims)
+}
+
+/// The user an ID token is for. `None` when it has no email, or one the identity provider
+/// doesn't say it verified.
+fn user_from_claims(claims: &Map<String, Value>, config: &OidcConfig) -> Option<DirectoryUser> {
+    let string = |name: &str| claims.get(name).and_then(Value::as_str).map(str::to_string);
+    let Some(email) = string("email") else {
+        warn!("Rejected ID token without an email claim");
+        return None;
+    };
+    // Some identity providers send this as a string.
+    let email_verified = match claims.get("email_verified") {
+        Some(Value::Bool(verified)) => Some(*verified),
+        Some(Value::String(verified)) => Some(verified.eq_ignore_ascii_case("true")),
+        _ => None,
+    };
+    if !email_verified.unwrap_or(!config.require_email_verified) {
+        warn!("Rejected ID token for unverified email {}", email);
+        return None;
+    }
+    let first_name = string("given_name")
+        .or_else(|| string("name"))
+        .unwrap_or_default();
+    let last_name = string("family_name").unwrap_or_default();
+    let groups = match claim_at(claims, &config.groups_claim) {
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        Some(Value::String(value)) => vec![value.clone()],
+        _ => Vec::new(),
+    };
+    Some(DirectoryUser {
+        email,
+        first_name,
+        last_name,
+        groups,
+    })
+}
+
+/// A claim by name, or a nested one by its path with dots.
+fn claim_at<'a>(claims: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    if let Some(value) = claims.get(path) {
+        return Some(value);
+    }
+    let mut parts = path.split('.');
+    let mut value = claims.get(parts.next()?)?;
+    for part in parts {
+        value = value.get(part)?;
+    }
+    Some(value)
+}
+
+fn http_client() -> awc::Client {
+    awc::Client::builder().timeout(HTTP_TIMEOUT).finish()
+}
+
+async fn get_json<T: DeserializeOwned>(url: &str) -> Result<T> {
+    let mut response = http_client()
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| anyhow!("Request to {} failed: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(anyhow!("{} returned {}", url, response.status()));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| anyhow!("Invalid response from {}: {}", url, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> OidcConfig {
+        OidcConfig {
+            issuer: "https://login.example.test".to_string(),
+            client_id: "po-tracker".to_string(),
+            client_secret: None,
+            redirect_uris: vec!["https://po.example.test/login".to_string()],
+            allow_loopback: true,
+            require_email_verified: true,
+            scopes: DEFAULT_SCOPES.to_string(),
+            groups_claim: "realm_access.roles".to_string(),
+            display_name: DEFAULT_DISPLAY_NAME.to_string(),
+        }
+    }
+
+    fn claims(value: Value) -> Map<String, Value> {
+        let Value::Object(claims) = value else {
+            panic!("claims should be an object");
+        };
+        claims
+    }
+
+    #[test]
+    fn allows_registered_and_loopback_redirects() {
+        let config = config();
+        assert!(config.allows_redirect_uri("https://po.example.test/login"));
+        assert!(!config.allows_redirect_uri("https://po.example.test/login/"));
+        assert!(config.allows_redirect_uri("http://127.0.0.1:49152/callback"));
+        assert!(config.allows_redirect_uri("http://[::1]:49152/callback"));
+        assert!(!config.allows_redirect_uri("http://localhost:49152/callback"));
+        assert!(!config.allows_redirect_uri("http://po.example.test/login"));
+        assert!(!config.allows_redirect_uri("https://127.0.0.1:49152/callback"));
+    }
+
+    #[test]
+    fn loopback_redirects_can_be_turned_off() {
+        let config = OidcConfig {
+            allow_loopback: false,
+            ..config()
+        };
+        assert!(!config.allows_redirect_uri("http://127.0.0.1:49152/callback"));
+        assert!(config.allows_redirect_uri("https://po.example.test/login"));
+    }
+
+    #[test]
+    fn claims_are_found_by_name_or_path() {
+        let claims = claims(json!({
+            "groups": ["PO Buyers"],
+            "realm_access": { "roles": ["PO Admins"] },
+            "a.b": "flat",
+        }));
+        assert_eq!(claim_at(&claims, "groups"), Some(&json!(["PO Buyers"])));
+        assert_eq!(
+            claim_at(&claims, "realm_access.roles"),
+            Some(&json!(["PO Admins"]))
+        );
+        assert_eq!(claim_at(&claims, "a.b"), Some(&json!("flat")));
+        assert_eq!(claim_at(&claims, "realm_access.missing"), None);
+        assert_eq!(claim_at(&claims, "missing"), None);
+    }
+
+    #[test]
+    fn user_comes_from_verified_claims() {
+        let claims = claims(json!({
+            "email": "ava.admin@example.test",
+            "email_verified": true,
+            "given_name": "Ava",
+            "family_name": "Admin",
+            "realm_access": { "roles": ["PO Admins", "offline_access"] },
+        }));
+        let user = user_from_claims(&claims, &config()).unwrap();
+        assert_eq!(user.email, "ava.admin@example.test");
+        assert_eq!(user.first_name, "Ava");
+        assert_eq!(user.last_name, "Admin");
+        assert_eq!(user.groups, ["PO Admins", "offline_access"]);
+    }
+
+    #[test]
+    fn unverified_or_missing_emails_are_refused() {
+        let config = config();
+        for email_verified in [json!(false), json!("false"), Value::Null] {
+            let claims = claims(json!({
+                "email": "ava.admin@example.test",
+                "email_verified": email_verified,
+            }));
+            assert!(user_from_claims(&claims, &config).is_none());
+        }
+        let unclaimed = claims(json!({ "email": "ava.admin@example.test" }));
+        assert!(user_from_claims(&unclaimed, &config).is_none());
+        let verified = claims(json!({ "email_verified": "true" }));
+        assert!(user_from_claims(&verified, &config).is_none());
+    }
+
+    #[test]
+    fn missing_verification_can_be_allowed() {
+        let config = OidcConfig {
+            require_email_verified: false,
+            ..config()
+        };
+        let unclaimed = claims(json!({ "email": "ava.admin@example.test" }));
+        assert!(user_from_claims(&unclaimed, &config).is_some());
+        let unverified = claims(json!({
+            "email": "ava.admin@example.test",
+            "email_verified": false,
+        }));
+        assert!(user_from_claims(&unverified, &config).is_none());
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use sqlx::MySqlTransaction;
+
+const OIDC_LOGINS_TABLE_SCHEMA: &str = include_str!(r#"../../sql/oidc_logins.sql"#);
+
+/// How long a user has to come back from the identity provider.
+const OIDC_LOGIN_TTL_SECONDS: u32 = 10 * 60;
+
+/// A single sign-on login that was sent to the identity provider and hasn't come back yet.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PendingOidcLogin {
+    pub code_verifier: String,
+    pub nonce: String,
+    pub redirect_uri: String,
+}
+
+pub async fn initialize_table<'a>(transaction: &mut MySqlTransaction<'a>) -> Result<()> {
+    sqlx::query(OIDC_LOGINS_TABLE_SCHEMA)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+pub async fn insert_login(state_hash: &str, login: &PendingOidcLogin) -> Result<()> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    sqlx::query(
+        r#"INSERT INTO oidc_logins (state_hash, code_verifier, nonce, redirect_uri, expires_at) VALUES (?, ?, ?, ?, NOW() + INTERVAL ? SECOND)"#,
+    )
+    .bind(state_hash)
+    .bind(&login.code_verifier)
+    .bind(&login.nonce)
+    .bind(&login.redirect_uri)
+    .bind(OIDC_LOGIN_TTL_SECONDS)
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(())
+}
+
+/// Removes and returns the login a `state` was handed out for, so it can only be completed
+/// once. `None` when there is no such login or it has expired.
+pub async fn take_login(state_hash: &str) -> Result<Option<PendingOidcLogin>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let login: Option<PendingOidcLogin> = sqlx::query_as(
+        r#"SELECT code_verifier, nonce, redirect_uri FROM oidc_logins WHERE state_hash = ? AND expires_at > NOW() FOR UPDATE"#,
+    )
+    .bind(state_hash)
+    .fetch_optional(&mut *transaction)
+    .await?;
+    sqlx::query(r#"DELETE FROM oidc_logins WHERE state_hash = ?"#)
+        .bind(state_hash)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(login)
+}
+
+/// Deletes logins nobody came back from in time. Returns how many were deleted.
+pub async fn remove_expired_logins() -> Result<u64> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let deleted = sqlx::query(r#"DELETE FROM oidc_logins WHERE expires_at <= NOW()"#)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(deleted)
+}
@@ -0,0 +1,346 @@
+use anyhow::Result;
+use log::{debug, error};
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Fewest characters a password can have.
+const PASSWORD_MIN_LENGTH_VAR: &str = "PASSWORD_MIN_LENGTH";
+const DEFAULT_MIN_LENGTH: usize = 10;
+const PASSWORD_MAX_LENGTH_VAR: &str = "PASSWORD_MAX_LENGTH";
+const DEFAULT_MAX_LENGTH: usize = 128;
+/// How many of lowercase letters, uppercase letters, digits and symbols a password needs.
+const PASSWORD_MIN_CHARACTER_CLASSES_VAR: &str = "PASSWORD_MIN_CHARACTER_CLASSES";
+const DEFAULT_MIN_CHARACTER_CLASSES: usize = 3;
+/// Directory of breached password hashes, split by the first 5 hex digits of their SHA-1 like
+/// the Pwned Passwords range API: `ABCDE.txt` holds `SUFFIX:COUNT` lines for the hashes
+/// starting with `ABCDE`. The `haveibeenpwned-downloader` tool writes this layout. Passwords
+/// aren't checked against breaches when it isn't set, or is missing or empty.
+const BREACHED_PASSWORDS_DIR_VAR: &str = "BREACHED_PASSWORDS_DIR";
+const PREFIX_LENGTH: usize = 5;
+
+/// The breached password directory, checked once so a bad one is reported at the first use
+/// rather than passing every password.
+static BREACHED_PASSWORDS_DIR: LazyLock<Option<String>> = LazyLock::new(|| {
+    let dir = std::env::var(BREACHED_PASSWORDS_DIR_VAR)
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())?;
+    match std::fs::read_dir(&dir).map(|mut entries| entries.next().is_some()) {
+        Ok(true) => Some(dir),
+        Ok(false) => {
+            error!(
+                "{} {} is empty, passwords aren't checked against breaches",
+                BREACHED_PASSWORDS_DIR_VAR, dir
+            );
+            None
+        }
+        Err(e) => {
+            error!(
+                "Can't read {} {}, passwords aren't checked against breaches: {}",
+                BREACHED_PASSWORDS_DIR_VAR, dir, e
+            );
+            None
+        }
+    }
+});
+
+/// Why a password was turned down.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, thiserror::Error)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum PasswordViolation {
+    #[error("Password must be at least {min_length} characters")]
+    TooShort { min_length: usize },
+    #[error("Password can't be longer than {max_length} characters")]
+    TooLong { max_length: usize },
+    #[error(
+        "Password must use at least {min_character_classes} of lowercase letters, uppercase letters, digits and symbols"
+    )]
+    TooFewCharacterClasses { min_character_classes: usize },
+    #[error("Password can't be your email or name")]
+    PersonalInfo,
+    #[error("Password has appeared in a data breach, choose another one")]
+    Breached,
+}
+
+/// The rules new passwords have to follow, from the `PASSWORD_*` variables.
+#[derive(Debug, Clone, Serialize)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub min_character_classes: usize,
+    /// Whether passwords are checked against the breached password list.
+    pub breach_check: bool,
+    #[serde(skip)]
+    breached_passwords_dir: Option<String>,
+}
+
+/// Who the password is for, so it can't just be their email or name.
+pub struct PasswordOwner<'a> {
+    pub email: &'a str,
+    pub first_name: &'a str,
+    pub last_name: &'a str,
+}
+
+impl PasswordPolicy {
+    pub fn from_config() -> Self {
+        let number = |var: &str, default: usize| {
+            std::env::var(var)
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(default)
+        };
+        Self::new(
+            number(PASSWORD_MIN_LENGTH_VAR, DEFAULT_MIN_LENGTH),
+            number(PASSWORD_MAX_LENGTH_VAR, DEFAULT_MAX_LENGTH),
+            number(
+                PASSWORD_MIN_CHARACTER_CLASSES_VAR,
+                DEFAULT_MIN_CHARACTER_CLASSES,
+            ),
+            BREACHED_PASSWORDS_DIR.clone(),
+        )
+    }
+
+    /// Keeps the configured numbers to ones a password can meet: at least one character, and
+    /// no more character classes than there are.
+    fn new(
+        min_length: usize,
+        max_length: usize,
+        min_character_classes: usize,
+        breached_passwords_dir: Option<String>,
+    ) -> Self {
+        Self {
+            min_length: min_length.max(1),
+            max_length,
+            min_character_classes: min_character_classes.min(4),
+            breach_check: breached_passwords_dir.is_some(),
+            breached_passwords_dir,
+        }
+    }
+
+    /// Everything wrong with `password`; empty when it's fine.
+    pub fn check(
+        &self,
+        password: &str,
+        owner: &PasswordOwner<'_>,
+    ) -> Result<Vec<PasswordViolation>> {
+        let mut violations = Vec::new();
+        let length = password.chars().count();
+        if length < self.min_length {
+            violations.push(PasswordViolation::TooShort {
+                min_length: self.min_length,
+            });
+        }
+        if length > self.max_length {
+            violations.push(PasswordViolation::TooLong {
+                max_length: self.max_length,
+            });
+        }
+        if character_classes(password) < self.min_character_classes {
+            violations.push(PasswordViolation::TooFewCharacterClasses {
+                min_character_classes: self.min_character_classes,
+            });
+        }
+        if is_personal_info(password, owner) {
+            violations.push(PasswordViolation::PersonalInfo);
+        }
+        // Only worth looking up once nothing else is wrong with it.
+        if violations.is_empty()
+            && let Some(dir) = &self.breached_passwords_dir
+            && is_breached(Path::new(dir), password)?
+        {
+            violations.push(PasswordViolation::Breached);
+        }
+        Ok(violations)
+    }
+}
+
+fn character_classes(password: &str) -> usize {
+    [
+        password.chars().any(|c| c.is_lowercase()),
+        password.chars().any(|c| c.is_uppercase()),
+        password.chars().any(|c| c.is_ascii_digit()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ]
+    .into_iter()
+    .filter(|has| *has)
+    .count()
+}
+
+fn is_personal_info(password: &str, owner: &PasswordOwner<'_>) -> bool {
+    let password = password.trim().to_lowercase();
+    let local_part = owner.email.split('@').next().unwrap_or_default();
+    let first_name = owner.first_name.trim();
+    let last_name = owner.last_name.trim();
+    [
+        owner.email.trim().to_string(),
+        local_part.to_string(),
+        first_name.to_string(),
+        last_name.to_string(),
+        format!("{first_name}{last_name}"),
+        format!("{first_name} {last_name}"),
+    ]
+    .iter()
+    .any(|value| !value.trim().is_empty() && value.trim().to_lowercase() == password)
+}
+
+/// Looks the password's SHA-1 up in the file for its prefix. A missing file means no breached
+/// password starts with that prefix.
+fn is_breached(dir: &Path, password: &str) -> Result<bool> {
+    let hash = format!("{:X}", Sha1::digest(password.as_bytes()));
+    let (prefix, suffix) = hash.split_at(PREFIX_LENGTH);
+    let path = dir.join(format!("{prefix}.txt"));
+    let hashes = match std::fs::read_to_string(&path) {
+        Ok(hashes) => hashes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            debug!("No breached password file {}", path.display());
+            return Ok(false);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    // Padded range responses list made-up hashes with a count of 0.
+    Ok(hashes.lines().any(|line| {
+        let (listed, count) = line.split_once(':').unwrap_or((line, "1"));
+        listed.trim().eq_ignore_ascii_case(suffix) && count.trim() != "0"
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const OWNER: PasswordOwner<'static> = PasswordOwner {
+        email: "blake.buyer@mardens.com",
+        first_name: "Blake",
+        last_name: "Buyer",
+    };
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy::new(10, 20, 3, None)
+    }
+
+    /// A breached password directory holding the file for `password`'s prefix, with `lines`
+    /// written as is and `{suffix}` replaced by the rest of its hash.
+    fn breached_dir(name: &str, password: &str, lines: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "po-tracker-breached-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let hash = format!("{:X}", Sha1::digest(password.as_bytes()));
+        let (prefix, suffix) = hash.split_at(PREFIX_LENGTH);
+        let hashes = lines
+            .iter()
+            .map(|line| line.replace("{suffix}", suffix))
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        std::fs::write(dir.join(format!("{prefix}.txt")), hashes).unwrap();
+        dir
+    }
+
+    #[test]
+    fn accepts_a_good_password() {
+        assert!(
+            policy()
+                .check("Correct-Horse-9", &OWNER)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn rejects_short_and_long_passwords() {
+        assert_eq!(
+            policy().check("Sh0rt!", &OWNER).unwrap(),
+            [PasswordViolation::TooShort { min_length: 10 }]
+        );
+        assert_eq!(
+            policy().check("Much-Too-Long-Password-1", &OWNER).unwrap(),
+            [PasswordViolation::TooLong { max_length: 20 }]
+        );
+    }
+
+    #[test]
+    fn length_counts_characters_not_bytes() {
+        assert!(policy().check("Ünïcödé-Pä55", &OWNER).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_too_few_character_classes() {
+        assert_eq!(
+            policy().check("alllowercase1", &OWNER).unwrap(),
+            [PasswordViolation::TooFewCharacterClasses {
+                min_character_classes: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_personal_info() {
+        let policy = PasswordPolicy::new(1, 128, 1, None);
+        for password in [
+            "blake.buyer@mardens.com",
+            "Blake.Buyer",
+            "BLAKE",
+            "blakebuyer",
+            "Blake Buyer",
+        ] {
+            assert_eq!(
+                policy.check(password, &OWNER).unwrap(),
+                [PasswordViolation::PersonalInfo],
+                "{password}"
+            );
+        }
+        assert!(policy.check("Blake-Buyer-1", &OWNER).unwrap().is_empty());
+    }
+
+    #[test]
+    fn finds_breached_passwords() {
+        let dir = breached_dir(
+            "listed",
+            "Correct-Horse-9",
+            &["0018A45C4D1DEF81644B54AB7F969B88D65:1", "{suffix}:42"],
+        );
+        assert!(is_breached(&dir, "Correct-Horse-9").unwrap());
+        let policy = PasswordPolicy::new(10, 20, 3, Some(dir.display().to_string()));
+        assert!(policy.breach_check);
+        assert_eq!(
+            policy.check("Correct-Horse-9", &OWNER).unwrap(),
+            [PasswordViolation::Breached]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn ignores_padding_lines() {
+        let dir = breached_dir("padding", "Correct-Horse-9", &["{suffix}:0"]);
+        assert!(!is_breached(&dir, "Correct-Horse-9").unwrap());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_prefix_file_is_not_breached() {
+        let dir = breached_dir("missing", "Correct-Horse-9", &["{suffix}:42"]);
+        assert!(!is_breached(&dir, "Battery-Staple-7").unwrap());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn clamps_configured_numbers() {
+        let policy = PasswordPolicy::new(0, 128, 9, None);
+        assert_eq!(policy.min_length, 1);
+        assert_eq!(policy.min_character_classes, 4);
+        assert!(!policy.breach_check);
+        assert_eq!(
+            policy.check("", &OWNER).unwrap(),
+            [
+                PasswordViolation::TooShort { min_length: 1 },
+                PasswordViolation::TooFewCharacterClasses {
+                    min_character_classes: 4
+                }
+            ]
+        );
+    }
+}
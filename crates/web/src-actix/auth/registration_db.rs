@@ -3,10 +3,34 @@ use sqlx::MySqlTransaction;
 
 const USERS_TABLE_SCHEMA: &str = include_str!(r#"../../sql/registration_requests.sql"#);
 
+/// How long a new user has to confirm their email before the registration is dropped.
+pub const REGISTRATION_TTL_SECONDS: u32 = 60 * 60;
+
+#[derive(Debug, Clone, Copy, sqlx::FromRow)]
+pub struct RegistrationRequest {
+    pub id: u32,
+    pub user_id: u32,
+}
+
 pub async fn initialize_table<'a>(transaction: &mut MySqlTransaction<'a>) -> Result<()> {
     sqlx::query(USERS_TABLE_SCHEMA)
         .execute(&mut **transaction)
         .await?;
+    let added = crate::app_db::add_column_if_missing(
+        transaction,
+        "registration_requests",
+        "expires_at",
+        "TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP",
+    )
+    .await?;
+    if added {
+        // Requests from before the column existed relied on in-memory timers, which are gone
+        // now; give them a full window from here.
+        sqlx::query("UPDATE registration_requests SET expires_at = NOW() + INTERVAL ? SECOND")
+            .bind(REGISTRATION_TTL_SECONDS)
+            .execute(&mut **transaction)
+            .await?;
+    }
     Ok(())
 }
 
@@ -16,14 +40,16 @@ pub async fn insert_request_with_transaction<'a>(
     token: &str,
     user_id: u32,
 ) -> Result<u32> {
-    let request_id: u32 =
-        sqlx::query("INSERT INTO registration_requests (token, email, user_id) VALUES (?, ?, ?)")
-            .bind(token)
-            .bind(email)
-            .bind(user_id)
-            .execute(&mut **transaction)
-            .await?
-            .last_insert_id() as u32;
+    let request_id: u32 = sqlx::query(
+        "INSERT INTO registration_requests (token, email, user_id, expires_at) VALUES (?, ?, ?, NOW() + INTERVAL ? SECOND)",
+    )
+    .bind(token)
+    .bind(email)
+    .bind(user_id)
+    .bind(REGISTRATION_TTL_SECONDS)
+    .execute(&mut **transaction)
+    .await?
+    .last_insert_id() as u32;
     Ok(request_id)
 }
 
@@ -36,28 +62,31 @@ pub async fn insert_request(email: &str, token: &str, user_id: u32) -> Result<u3
     Ok(request_id)
 }
 
+/// Finds the request for a token, ignoring expired ones that haven't been cleaned up yet.
 pub async fn get_request_from_token_with_transaction<'a>(
     transaction: &mut MySqlTransaction<'a>,
     token: &str,
     email: &str,
-) -> Result<Option<u32>> {
-    let request_id: Option<u32> = sqlx::query_scalar(
-        "SELECT id FROM registration_requests WHERE token = ? and email = ? LIMIT 1",
+) -> Result<Option<RegistrationRequest>> {
+    let request: Option<RegistrationRequest> = sqlx::query_as(
+        "SELECT id, user_id FROM registration_requests WHERE token = ? and email = ? AND expires_at > NOW() LIMIT 1",
     )
     .bind(token)
     .bind(email)
     .fetch_optional(&mut **transaction)
     .await?;
-    Ok(request_id)
+    Ok(request)
 }
 
-pub async fn get_request_from_token(token: &str, email: &str) -> Result<Option<u32>> {
+pub async fn get_request_from_token(
+    token: &str,
+    email: &str,
+) -> Result<Option<RegistrationRequest>> {
     let pool = crate::app_db::create_pool().await?;
     let mut transaction = pool.begin().await?;
-    let request_id =
-        get_request_from_token_with_transaction(&mut transaction, token, email).await?;
+    let request = get_request_from_token_with_transaction(&mut transaction, token, email).await?;
     transaction.commit().await?;
-    Ok(request_id)
+    Ok(request)
 }
 
 pub async fn remove_request_with_transaction<'a>(
@@ -71,6 +100,19 @@ pub async fn remove_request_with_transaction<'a>(
     Ok(())
 }
 
+/// Removes any registration a user still has open, for when their account gets confirmed
+/// another way.
+pub async fn remove_requests_for_user_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+) -> Result<()> {
+    sqlx::query("DELETE FROM registration_requests WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
 pub async fn remove_request(request_id: u32) -> Result<()> {
     let pool = crate::app_db::create_pool().await?;
     let mut transaction = pool.begin().await?;
@@ -103,13 +145,39 @@ pub async fn confirm_request_with_transaction<'a>(
     email: &str,
     token: &str,
 ) -> Result<()> {
-    let request_id = get_request_from_token_with_transaction(transaction, token, email).await?;
-    match request_id {
-        Some(id) => {
-            crate::auth::users_db::set_confirmed_email_with_transaction(transaction, id).await?;
-            remove_request_with_transaction(transaction, id).await?;
+    let request = get_request_from_token_with_transaction(transaction, token, email).await?;
+    match request {
+        Some(request) => {
+            crate::auth::users_db::set_confirmed_email_with_transaction(transaction, request.user_id)
+                .await?;
+            remove_request_with_transaction(transaction, request.id).await?;
             Ok(())
         }
         None => Err(anyhow!("Invalid token")),
     }
 }
+
+/// Drops registrations that weren't confirmed in time, along with their users if those still
+/// haven't confirmed their email.
+/// Returns how many were dropped.
+pub async fn remove_expired_requests() -> Result<u64> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let expired: Vec<RegistrationRequest> = sqlx::query_as(
+        "SELECT id, user_id FROM registration_requests WHERE expires_at <= NOW() FOR UPDATE",
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    for request in &expired {
+        // The user may have become active some other way since, e.g. through a directory login.
+        crate::auth::users_db::delete_unconfirmed_user_with_transaction(
+            &mut transaction,
+            request.user_id,
+        )
+        .await?;
+        remove_request_with_transaction(&mut transaction, request.id).await?;
+    }
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(expired.len() as u64)
+}
@@ -0,0 +1,57 @@
+use crate::auth::password_policy::PasswordViolation;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use log::error;
+use serde_json::json;
+
+/// Why a registration, invite or password reset was refused.
+#[derive(Debug, thiserror::Error)]
+pub enum RegistrationError {
+    #[error("Registration isn't open to this email domain, ask an administrator for an invite")]
+    EmailDomainNotAllowed,
+    #[error("Invalid or expired invite")]
+    InvalidInvite,
+    #[error("Invalid or expired password reset link")]
+    InvalidResetLink,
+    #[error("An account with this email already exists")]
+    AlreadyRegistered,
+    #[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(". "))]
+    WeakPassword(Vec<PasswordViolation>),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for RegistrationError {
+    fn from(e: sqlx::Error) -> Self {
+        RegistrationError::Other(e.into())
+    }
+}
+
+impl ResponseError for RegistrationError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            RegistrationError::EmailDomainNotAllowed => StatusCode::FORBIDDEN,
+            RegistrationError::InvalidInvite
+            | RegistrationError::InvalidResetLink
+            | RegistrationError::WeakPassword(_) => StatusCode::BAD_REQUEST,
+            RegistrationError::AlreadyRegistered => StatusCode::CONFLICT,
+            RegistrationError::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let message = match self {
+            RegistrationError::Other(e) => {
+                error!("Registration failed: {e}");
+                "Internal server error".to_string()
+            }
+            other => other.to_string(),
+        };
+        let mut response = HttpResponse::build(self.status_code());
+        if let RegistrationError::WeakPassword(violations) = self {
+            // Lets clients point at each rule the password broke.
+            return response.json(json!({ "error": message, "violations": violations }));
+        }
+        response.json(json!({ "error": message }))
+    }
+}
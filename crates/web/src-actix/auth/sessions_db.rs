@@ -0,0 +1,150 @@
+use anyhow::Result;
+use log::warn;
+use sqlx::MySqlTransaction;
+
+const USER_SESSIONS_TABLE_SCHEMA: &str = include_str!(r#"../../sql/user_sessions.sql"#);
+
+/// How long a refresh token stays valid without being used.
+const REFRESH_TOKEN_TTL_DAYS: u32 = 30;
+
+/// A signed-in device, identified by its current refresh token.
+#[derive(Debug, Clone, Copy, sqlx::FromRow)]
+pub struct Session {
+    pub id: u64,
+    pub user_id: u32,
+}
+
+pub async fn initialize_table<'a>(transaction: &mut MySqlTransaction<'a>) -> Result<()> {
+    sqlx::query(USER_SESSIONS_TABLE_SCHEMA)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+pub async fn create_session_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+    token_hash: &str,
+) -> Result<u64> {
+    let session_id = sqlx::query(
+        r#"INSERT INTO user_sessions (user_id, token_hash, expires_at) VALUES (?, ?, NOW() + INTERVAL ? DAY)"#,
+    )
+    .bind(user_id)
+    .bind(token_hash)
+    .bind(REFRESH_TOKEN_TTL_DAYS)
+    .execute(&mut **transaction)
+    .await?
+    .last_insert_id();
+    Ok(session_id)
+}
+
+/// Swaps the session's refresh token for a new one and extends it.
+///
+/// Returns `None` when the token doesn't belong to a live session. A token that was already
+/// rotated out means it was copied, so the session it belonged to is ended as well.
+pub async fn rotate_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    token_hash: &str,
+    new_token_hash: &str,
+) -> Result<Option<Session>> {
+    let session: Option<Session> = sqlx::query_as(
+        r#"SELECT id, user_id FROM user_sessions WHERE token_hash = ? AND expires_at > NOW() FOR UPDATE"#,
+    )
+    .bind(token_hash)
+    .fetch_optional(&mut **transaction)
+    .await?;
+
+    let Some(session) = session else {
+        let reused: Option<Session> = sqlx::query_as(
+            r#"SELECT id, user_id FROM user_sessions WHERE previous_token_hash = ? FOR UPDATE"#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&mut **transaction)
+        .await?;
+        if let Some(reused) = reused {
+            warn!(
+                "Refresh token for session {} of user {} was used twice, ending the session",
+                reused.id, reused.user_id
+            );
+            delete_session_by_id_with_transaction(transaction, reused.id).await?;
+        }
+        return Ok(None);
+    };
+
+    sqlx::query(
+        r#"UPDATE user_sessions SET previous_token_hash = token_hash, token_hash = ?, last_used_at = NOW(), expires_at = NOW() + INTERVAL ? DAY WHERE id = ?"#,
+    )
+    .bind(new_token_hash)
+    .bind(REFRESH_TOKEN_TTL_DAYS)
+    .bind(session.id)
+    .execute(&mut **transaction)
+    .await?;
+    Ok(Some(session))
+}
+
+pub async fn delete_session_by_id_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    session_id: u64,
+) -> Result<()> {
+    sqlx::query(r#"DELETE FROM user_sessions WHERE id = ?"#)
+        .bind(session_id)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// Ends the session a refresh token belongs to. Returns false when there was none.
+pub async fn delete_session_by_token(token_hash: &str) -> Result<bool> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let found = sqlx::query(r#"DELETE FROM user_sessions WHERE token_hash = ?"#)
+        .bind(token_hash)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected()
+        > 0;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(found)
+}
+
+pub async fn delete_user_sessions_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+) -> Result<()> {
+    sqlx::query(r#"DELETE FROM user_sessions WHERE user_id = ?"#)
+        .bind(user_id)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// The user's current session version if the session is still live and the user may use it,
+/// for checking access tokens.
+pub async fn get_active_session_version(session_id: u64, user_id: u32) -> Result<Option<u32>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let version: Option<u32> = sqlx::query_scalar(
+        r#"SELECT u.session_version FROM user_sessions s JOIN users u ON u.id = s.user_id WHERE s.id = ? AND s.user_id = ? AND s.expires_at > NOW() AND u.needs_password_reset = 0"#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .fetch_optional(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(version)
+}
+
+/// Deletes sessions whose refresh token has expired. Returns how many were deleted.
+pub async fn remove_expired_sessions() -> Result<u64> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let deleted = sqlx::query(r#"DELETE FROM user_sessions WHERE expires_at <= NOW()"#)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(deleted)
+}
@@ -0,0 +1,230 @@
+use anyhow::{Result, anyhow};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+// Time-based one-time passwords (RFC 6238) as used by authenticator apps: HMAC-SHA1, 6 digits,
+// 30 second steps.
+const STEP_SECONDS: u64 = 30;
+const DIGITS: u32 = 6;
+/// Secret length recommended by RFC 4226.
+const SECRET_BYTES: usize = 20;
+/// Steps either side of the current one that are still accepted, for clock drift.
+const ALLOWED_DRIFT_STEPS: u64 = 1;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// A new random secret, base32 encoded as authenticator apps expect it.
+pub fn generate_secret() -> Result<String> {
+    let mut secret = [0u8; SECRET_BYTES];
+    getrandom::fill(&mut secret).map_err(|e| anyhow!("Failed to generate TOTP secret: {e}"))?;
+    Ok(base32_encode(&secret))
+}
+
+/// The `otpauth://` URI authenticator apps read from a QR code.
+pub fn otpauth_uri(secret: &str, issuer: &str, account: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(issuer),
+        percent_encode(account),
+        secret,
+        percent_encode(issuer),
+        DIGITS,
+        STEP_SECONDS
+    )
+}
+
+/// The time step `unix_time` falls in.
+pub fn step_at(unix_time: u64) -> u64 {
+    unix_time / STEP_SECONDS
+}
+
+/// Checks `code` against the steps around `unix_time` and returns the step it matched.
+///
+/// Steps up to and including `last_used_step` are refused so a code can't be used twice.
+pub fn verify(
+    secret: &str,
+    code: &str,
+    unix_time: u64,
+    last_used_step: Option<u64>,
+) -> Result<Option<u64>> {
+    let code = code.trim().replace(' ', "");
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(None);
+    }
+    let code: u32 = code.parse()?;
+    let key = base32_decode(secret).ok_or_else(|| anyhow!("Invalid TOTP secret"))?;
+    let current = step_at(unix_time);
+    let first = current.saturating_sub(ALLOWED_DRIFT_STEPS);
+    for step in first..=current + ALLOWED_DRIFT_STEPS {
+        if last_used_step.is_some_and(|last| step <= last) {
+            continue;
+        }
+        if hotp(&key, step) == code {
+            return Ok(Some(step));
+        }
+    }
+    Ok(None)
+}
+
+/// The HOTP value (RFC 4226) of `key` for `counter`.
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    binary % 10u32.pow(DIGITS)
+}
+
+/// RFC 4648 base32 without padding.
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+/// Decodes base32, ignoring case, spaces and padding. `None` if it isn't valid base32.
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in encoded.chars().filter(|c| *c != ' ' && *c != '=') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a as char == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SHA-1 key of the RFC 6238 test vectors.
+    const RFC_KEY: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn matches_rfc_6238_sha1_vectors() {
+        // The RFC lists 8 digit codes; 6 digit codes are their last 6 digits.
+        let vectors = [
+            (59, "94287082"),
+            (1111111109, "07081804"),
+            (1111111111, "14050471"),
+            (1234567890, "89005924"),
+            (2000000000, "69279037"),
+            (20000000000, "65353130"),
+        ];
+        let secret = base32_encode(RFC_KEY);
+        for (time, expected) in vectors {
+            let code = &expected[2..];
+            assert_eq!(format!("{:06}", hotp(RFC_KEY, step_at(time))), code);
+            assert_eq!(
+                verify(&secret, code, time, None).unwrap(),
+                Some(step_at(time))
+            );
+        }
+    }
+
+    #[test]
+    fn base32_matches_rfc_4648_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "MY"),
+            ("fo", "MZXQ"),
+            ("foo", "MZXW6"),
+            ("foob", "MZXW6YQ"),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI"),
+        ];
+        for (data, encoded) in vectors {
+            assert_eq!(base32_encode(data.as_bytes()), encoded);
+            assert_eq!(base32_decode(encoded).unwrap(), data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn base32_round_trips() {
+        for length in 0..=SECRET_BYTES {
+            let data: Vec<u8> = (0..length).map(|i| (i * 37 + 11) as u8).collect();
+            let encoded = base32_encode(&data);
+            assert_eq!(base32_decode(&encoded).unwrap(), data);
+            assert_eq!(base32_decode(&encoded.to_lowercase()).unwrap(), data);
+        }
+        assert_eq!(base32_decode("MZXW 6YTB OI==").unwrap(), b"foobar");
+        assert_eq!(base32_decode("MZXW1"), None);
+    }
+
+    #[test]
+    fn generated_secret_decodes_to_secret_bytes() {
+        let secret = generate_secret().unwrap();
+        assert_eq!(base32_decode(&secret).unwrap().len(), SECRET_BYTES);
+    }
+
+    #[test]
+    fn refuses_used_steps() {
+        let secret = base32_encode(RFC_KEY);
+        let time = 1111111111;
+        let step = step_at(time);
+        let code = format!("{:06}", hotp(RFC_KEY, step));
+
+        assert_eq!(
+            verify(&secret, &code, time, Some(step - 1)).unwrap(),
+            Some(step)
+        );
+        assert_eq!(verify(&secret, &code, time, Some(step)).unwrap(), None);
+        // A later step having been used refuses earlier codes still in the drift window.
+        assert_eq!(verify(&secret, &code, time, Some(step + 1)).unwrap(), None);
+    }
+
+    #[test]
+    fn accepts_one_step_of_drift() {
+        let secret = base32_encode(RFC_KEY);
+        let time = 1111111111;
+        let code = format!("{:06}", hotp(RFC_KEY, step_at(time)));
+
+        let next = time + STEP_SECONDS;
+        assert_eq!(
+            verify(&secret, &code, next, None).unwrap(),
+            Some(step_at(time))
+        );
+        assert_eq!(
+            verify(&secret, &code, time + 2 * STEP_SECONDS, None).unwrap(),
+            None
+        );
+        assert_eq!(verify(&secret, "12345", time, None).unwrap(), None);
+    }
+}
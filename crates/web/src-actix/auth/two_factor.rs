@@ -0,0 +1,314 @@
+use crate::auth::auth_service::hash_token;
+use crate::auth::login_guard::{ACCOUNT_LIMITER, account_key};
+use crate::auth::totp;
+use crate::auth::two_factor_db::{self, TotpState};
+use crate::auth::users_data::User;
+use actix_web::http::StatusCode;
+use actix_web::http::header;
+use actix_web::{HttpResponse, ResponseError};
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use log::{error, info};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::MySqlTransaction;
+use std::time::Duration;
+
+/// Shown next to the codes in authenticator apps.
+const TOTP_ISSUER: &str = "PO Tracker";
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Base32 characters per recovery code, shown in groups of four.
+const RECOVERY_CODE_LENGTH: usize = 12;
+
+/// What a user needs to add the account to an authenticator app, either by scanning
+/// `otpauth_uri` as a QR code or by typing in `secret`.
+#[derive(Debug, Serialize)]
+pub struct TotpSetup {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    /// Whether the user's role requires two-factor authentication.
+    pub required: bool,
+    /// Whether a setup is waiting to be confirmed with a code.
+    pub setup_pending: bool,
+    pub recovery_codes_left: u32,
+}
+
+/// Why a change to a user's two-factor authentication was refused.
+#[derive(Debug, thiserror::Error)]
+pub enum TwoFactorError {
+    #[error("Invalid two-factor code")]
+    InvalidCode,
+    #[error("Two-factor authentication is already enabled")]
+    AlreadyEnabled,
+    #[error("Two-factor authentication is not enabled")]
+    NotEnabled,
+    #[error("No two-factor setup is waiting to be confirmed")]
+    NoPendingSetup,
+    #[error("Two-factor authentication is required for your role")]
+    RequiredByRole,
+    #[error("Too many invalid codes, try again later")]
+    TooManyAttempts { retry_after: Duration },
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for TwoFactorError {
+    fn from(e: sqlx::Error) -> Self {
+        TwoFactorError::Other(e.into())
+    }
+}
+
+impl ResponseError for TwoFactorError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TwoFactorError::InvalidCode
+            | TwoFactorError::NotEnabled
+            | TwoFactorError::NoPendingSetup => StatusCode::BAD_REQUEST,
+            TwoFactorError::AlreadyEnabled => StatusCode::CONFLICT,
+            TwoFactorError::RequiredByRole => StatusCode::FORBIDDEN,
+            TwoFactorError::TooManyAttempts { .. } => StatusCode::TOO_MANY_REQUESTS,
+            TwoFactorError::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let TwoFactorError::TooManyAttempts { retry_after } = self {
+            response.insert_header((header::RETRY_AFTER, retry_after.as_secs().max(1)));
+        }
+        let message = match self {
+            TwoFactorError::Other(e) => {
+                error!("Two-factor request failed: {e}");
+                "Internal server error".to_string()
+            }
+            other => other.to_string(),
+        };
+        response.json(json!({ "error": message }))
+    }
+}
+
+/// Hands out a new secret for the user's authenticator app. It replaces the user's secret
+/// once `confirm_setup_with_transaction` gets a code for it.
+pub async fn begin_setup_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+    email: &str,
+) -> Result<TotpSetup> {
+    let secret = totp::generate_secret()?;
+    two_factor_db::set_pending_secret_with_transaction(transaction, user_id, &secret).await?;
+    Ok(TotpSetup {
+        otpauth_uri: totp::otpauth_uri(&secret, TOTP_ISSUER, email),
+        secret,
+    })
+}
+
+/// Enables two-factor authentication if `code` is right for the pending secret, and returns
+/// the user's new recovery codes. `None` when the code is wrong or nothing is pending.
+pub async fn confirm_setup_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+    state: &TotpState,
+    code: &str,
+) -> Result<Option<Vec<String>>> {
+    let Some(pending_secret) = &state.pending_secret else {
+        return Ok(None);
+    };
+    let Some(step) = totp::verify(pending_secret, code, unix_now(), None)? else {
+        return Ok(None);
+    };
+    two_factor_db::enable_with_transaction(transaction, user_id, step).await?;
+    let recovery_codes = new_recovery_codes_with_transaction(transaction, user_id).await?;
+    info!("User {} enabled two-factor authentication", user_id);
+    Ok(Some(recovery_codes))
+}
+
+/// Checks a code from the authenticator app, or else a recovery code, which is used up.
+pub async fn check_code_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+    state: &TotpState,
+    code: &str,
+) -> Result<bool> {
+    let Some(secret) = state.secret.as_deref().filter(|_| state.enabled) else {
+        return Ok(false);
+    };
+    if let Some(step) = totp::verify(secret, code, unix_now(), state.last_step)? {
+        two_factor_db::set_last_step_with_transaction(transaction, user_id, step).await?;
+        return Ok(true);
+    }
+    let used = two_factor_db::use_recovery_code_with_transaction(
+        transaction,
+        user_id,
+        &recovery_code_hash(user_id, code),
+    )
+    .await?;
+    if used {
+        info!("User {} used a recovery code", user_id);
+    }
+    Ok(used)
+}
+
+/// Replaces the user's recovery codes and returns the new ones. Only their hashes are stored,
+/// so this is the only time they can be shown.
+pub async fn new_recovery_codes_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+) -> Result<Vec<String>> {
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        codes.push(generate_recovery_code()?);
+    }
+    let hashes: Vec<String> = codes
+        .iter()
+        .map(|code| recovery_code_hash(user_id, code))
+        .collect();
+    two_factor_db::replace_recovery_codes_with_transaction(transaction, user_id, &hashes).await?;
+    Ok(codes)
+}
+
+pub async fn get_status(user: &User) -> Result<TwoFactorStatus> {
+    let user_id = user.id()?;
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let state = two_factor_db::get_totp_state_with_transaction(&mut transaction, user_id).await?;
+    let required =
+        two_factor_db::is_required_for_role_with_transaction(&mut transaction, user.role).await?;
+    let recovery_codes_left =
+        two_factor_db::count_unused_recovery_codes_with_transaction(&mut transaction, user_id)
+            .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(TwoFactorStatus {
+        enabled: state.enabled,
+        required,
+        setup_pending: state.pending_secret.is_some(),
+        recovery_codes_left,
+    })
+}
+
+/// Starts setting up an authenticator app for a signed-in user.
+pub async fn begin_setup(user: &User) -> Result<TotpSetup, TwoFactorError> {
+    let user_id = user.id()?;
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let state = two_factor_db::get_totp_state_with_transaction(&mut transaction, user_id).await?;
+    let setup = if state.enabled {
+        Err(TwoFactorError::AlreadyEnabled)
+    } else {
+        Ok(begin_setup_with_transaction(&mut transaction, user_id, &user.email).await?)
+    };
+    transaction.commit().await?;
+    pool.close().await;
+    setup
+}
+
+/// Finishes the setup started by `begin_setup` and returns the new recovery codes.
+pub async fn confirm_setup(user: &User, code: &str) -> Result<Vec<String>, TwoFactorError> {
+    let user_id = user.id()?;
+    check_limit(user)?;
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let state = two_factor_db::get_totp_state_with_transaction(&mut transaction, user_id).await?;
+    let recovery_codes = if state.enabled {
+        Err(TwoFactorError::AlreadyEnabled)
+    } else if state.pending_secret.is_none() {
+        Err(TwoFactorError::NoPendingSetup)
+    } else {
+        confirm_setup_with_transaction(&mut transaction, user_id, &state, code)
+            .await?
+            .ok_or_else(|| invalid_code(user))
+    };
+    transaction.commit().await?;
+    pool.close().await;
+    recovery_codes
+}
+
+/// Turns two-factor authentication off, unless the user's role requires it.
+pub async fn disable(user: &User, code: &str) -> Result<(), TwoFactorError> {
+    let user_id = user.id()?;
+    check_limit(user)?;
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let state = two_factor_db::get_totp_state_with_transaction(&mut transaction, user_id).await?;
+    let disabled =
+        if two_factor_db::is_required_for_role_with_transaction(&mut transaction, user.role).await?
+        {
+            Err(TwoFactorError::RequiredByRole)
+        } else if !state.enabled {
+            Err(TwoFactorError::NotEnabled)
+        } else if check_code_with_transaction(&mut transaction, user_id, &state, code).await? {
+            two_factor_db::disable_with_transaction(&mut transaction, user_id).await?;
+            info!("User {} disabled two-factor authentication", user_id);
+            Ok(())
+        } else {
+            Err(invalid_code(user))
+        };
+    transaction.commit().await?;
+    pool.close().await;
+    disabled
+}
+
+/// Replaces the user's recovery codes, after checking a code from the authenticator app.
+pub async fn regenerate_recovery_codes(
+    user: &User,
+    code: &str,
+) -> Result<Vec<String>, TwoFactorError> {
+    let user_id = user.id()?;
+    check_limit(user)?;
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let state = two_factor_db::get_totp_state_with_transaction(&mut transaction, user_id).await?;
+    let recovery_codes = if !state.enabled {
+        Err(TwoFactorError::NotEnabled)
+    } else if check_code_with_transaction(&mut transaction, user_id, &state, code).await? {
+        Ok(new_recovery_codes_with_transaction(&mut transaction, user_id).await?)
+    } else {
+        Err(invalid_code(user))
+    };
+    transaction.commit().await?;
+    pool.close().await;
+    recovery_codes
+}
+
+/// Wrong codes count toward the same limit as failed logins.
+fn check_limit(user: &User) -> Result<(), TwoFactorError> {
+    match ACCOUNT_LIMITER.retry_after(&account_key(&user.email)) {
+        Some(retry_after) => Err(TwoFactorError::TooManyAttempts { retry_after }),
+        None => Ok(()),
+    }
+}
+
+fn invalid_code(user: &User) -> TwoFactorError {
+    ACCOUNT_LIMITER.hit(&account_key(&user.email));
+    TwoFactorError::InvalidCode
+}
+
+fn generate_recovery_code() -> Result<String> {
+    let mut bytes = [0u8; RECOVERY_CODE_LENGTH * 5 / 8 + 1];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow!("Failed to generate recovery code: {e}"))?;
+    let encoded = totp::base32_encode(&bytes);
+    let groups: Vec<&str> = encoded.as_bytes()[..RECOVERY_CODE_LENGTH]
+        .chunks(4)
+        .map(|group| std::str::from_utf8(group).expect("base32 is ASCII"))
+        .collect();
+    Ok(groups.join("-"))
+}
+
+/// Recovery codes are compared without case, spaces or dashes, and salted with the user ID.
+fn recovery_code_hash(user_id: u32, code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    hash_token(&format!("{}:{}", user_id, normalized))
+}
+
+fn unix_now() -> u64 {
+    Utc::now().timestamp().max(0) as u64
+}
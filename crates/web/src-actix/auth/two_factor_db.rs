@@ -0,0 +1,212 @@
+use crate::auth::user_role::UserRole;
+use anyhow::Result;
+use sqlx::MySqlTransaction;
+
+const RECOVERY_CODES_TABLE_SCHEMA: &str = include_str!(r#"../../sql/user_recovery_codes.sql"#);
+const TWO_FACTOR_POLICY_TABLE_SCHEMA: &str = include_str!(r#"../../sql/two_factor_policy.sql"#);
+
+/// A user's authenticator app setup, from the `totp_*` columns of `users`.
+#[derive(Debug, Clone, Default, sqlx::FromRow)]
+pub struct TotpState {
+    #[sqlx(rename = "totp_enabled")]
+    pub enabled: bool,
+    #[sqlx(rename = "totp_secret")]
+    pub secret: Option<String>,
+    /// Secret handed out by a setup that hasn't been confirmed with a code yet.
+    #[sqlx(rename = "totp_pending_secret")]
+    pub pending_secret: Option<String>,
+    /// The last time step a code was accepted for, so a code can't be used twice.
+    #[sqlx(rename = "totp_last_step")]
+    pub last_step: Option<u64>,
+}
+
+/// Whether users with a role have to use two-factor authentication.
+#[derive(Debug, Clone, Copy, serde::Serialize, sqlx::FromRow)]
+pub struct TwoFactorPolicy {
+    pub role: UserRole,
+    pub required: bool,
+}
+
+pub async fn initialize_table<'a>(transaction: &mut MySqlTransaction<'a>) -> Result<()> {
+    sqlx::query(RECOVERY_CODES_TABLE_SCHEMA)
+        .execute(&mut **transaction)
+        .await?;
+    sqlx::query(TWO_FACTOR_POLICY_TABLE_SCHEMA)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// The user's TOTP state, locked until the transaction ends so two codes can't race.
+pub async fn get_totp_state_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+) -> Result<TotpState> {
+    let state: Option<TotpState> = sqlx::query_as(
+        r#"SELECT totp_enabled, totp_secret, totp_pending_secret, totp_last_step FROM users WHERE id = ? FOR UPDATE"#,
+    )
+    .bind(user_id)
+    .fetch_optional(&mut **transaction)
+    .await?;
+    Ok(state.unwrap_or_default())
+}
+
+pub async fn set_pending_secret_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+    secret: &str,
+) -> Result<()> {
+    sqlx::query(r#"UPDATE users SET totp_pending_secret = ? WHERE id = ?"#)
+        .bind(secret)
+        .bind(user_id)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// Makes the pending secret the user's secret, confirmed by a code for `step`.
+pub async fn enable_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+    step: u64,
+) -> Result<()> {
+    sqlx::query(
+        r#"UPDATE users SET totp_secret = totp_pending_secret, totp_pending_secret = NULL, totp_enabled = 1, totp_last_step = ? WHERE id = ? AND totp_pending_secret IS NOT NULL"#,
+    )
+    .bind(step)
+    .bind(user_id)
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
+}
+
+pub async fn set_last_step_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+    step: u64,
+) -> Result<()> {
+    sqlx::query(r#"UPDATE users SET totp_last_step = ? WHERE id = ?"#)
+        .bind(step)
+        .bind(user_id)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// Turns two-factor authentication off and drops the user's recovery codes.
+pub async fn disable_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+) -> Result<()> {
+    sqlx::query(
+        r#"UPDATE users SET totp_enabled = 0, totp_secret = NULL, totp_pending_secret = NULL, totp_last_step = NULL WHERE id = ?"#,
+    )
+    .bind(user_id)
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query(r#"DELETE FROM user_recovery_codes WHERE user_id = ?"#)
+        .bind(user_id)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// Replaces the user's recovery codes, used or not, with new ones.
+pub async fn replace_recovery_codes_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+    code_hashes: &[String],
+) -> Result<()> {
+    sqlx::query(r#"DELETE FROM user_recovery_codes WHERE user_id = ?"#)
+        .bind(user_id)
+        .execute(&mut **transaction)
+        .await?;
+    for code_hash in code_hashes {
+        sqlx::query(r#"INSERT INTO user_recovery_codes (user_id, code_hash) VALUES (?, ?)"#)
+            .bind(user_id)
+            .bind(code_hash)
+            .execute(&mut **transaction)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Marks an unused recovery code as used. Returns false when the user has no such code.
+pub async fn use_recovery_code_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+    code_hash: &str,
+) -> Result<bool> {
+    let used = sqlx::query(
+        r#"UPDATE user_recovery_codes SET used_at = NOW() WHERE user_id = ? AND code_hash = ? AND used_at IS NULL LIMIT 1"#,
+    )
+    .bind(user_id)
+    .bind(code_hash)
+    .execute(&mut **transaction)
+    .await?
+    .rows_affected()
+        == 1;
+    Ok(used)
+}
+
+pub async fn count_unused_recovery_codes_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    user_id: u32,
+) -> Result<u32> {
+    let count: i64 = sqlx::query_scalar(
+        r#"SELECT COUNT(*) FROM user_recovery_codes WHERE user_id = ? AND used_at IS NULL"#,
+    )
+    .bind(user_id)
+    .fetch_one(&mut **transaction)
+    .await?;
+    Ok(count as u32)
+}
+
+pub async fn is_required_for_role_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    role: UserRole,
+) -> Result<bool> {
+    let required: Option<bool> =
+        sqlx::query_scalar(r#"SELECT required FROM two_factor_policy WHERE role = ?"#)
+            .bind(role)
+            .fetch_optional(&mut **transaction)
+            .await?;
+    Ok(required.unwrap_or(false))
+}
+
+/// The policy of every role. Roles without a row don't require two-factor authentication.
+pub async fn get_policies() -> Result<Vec<TwoFactorPolicy>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let rows: Vec<TwoFactorPolicy> =
+        sqlx::query_as(r#"SELECT role, required FROM two_factor_policy"#)
+            .fetch_all(&mut *transaction)
+            .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    let policies = UserRole::values()
+        .into_iter()
+        .map(|role| TwoFactorPolicy {
+            role,
+            required: rows
+                .iter()
+                .any(|policy| policy.role == role && policy.required),
+        })
+        .collect();
+    Ok(policies)
+}
+
+pub async fn set_policy(role: UserRole, required: bool) -> Result<()> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    sqlx::query(
+        r#"INSERT INTO two_factor_policy (role, required) VALUES (?, ?) ON DUPLICATE KEY UPDATE required = VALUES(required)"#,
+    )
+    .bind(role)
+    .bind(required)
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(())
+}
@@ -19,6 +19,14 @@ impl UserRole {
     pub fn is_buyer(&self) -> bool {
         *self == UserRole::Buyer
     }
+    /// How the role is written in emails.
+    pub fn label(&self) -> &'static str {
+        match self {
+            UserRole::Admin => "admin",
+            UserRole::Buyer => "buyer",
+            UserRole::Warehouse => "warehouse user",
+        }
+    }
     pub fn values() -> [Self; 3] {
         [UserRole::Admin, UserRole::Warehouse, UserRole::Buyer]
     }
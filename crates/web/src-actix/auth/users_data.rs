@@ -1,13 +1,76 @@
-use crate::auth::auth_service::generate_jwt_token;
-use crate::auth::jwt_data::AuthResponse;
+use crate::auth::auth_service::{
+    generate_challenge_token, generate_jwt_token, generate_token, hash_password, hash_token,
+    needs_rehash, validate_challenge_token, verify_password, ACCESS_TOKEN_MINUTES,
+    CHALLENGE_TOKEN_MINUTES,
+};
+use crate::auth::auth_source::AuthSource;
+use crate::auth::directory::{self, DirectoryUser, RoleMapping};
+use crate::auth::email_service::EmailService;
+use crate::auth::invites_db::{self, UserInvite};
+use crate::auth::jwt_data::{
+    AuthResponse, LoginResponse, TwoFactorChallenge, TwoFactorLoginResponse, TwoFactorStep,
+};
+use crate::auth::login_attempts_db::{record_attempt, FailureReason, LoginClient};
+use crate::auth::login_error::LoginError;
+use crate::auth::login_guard::{account_key, lockout_seconds, ACCOUNT_LIMITER};
+use crate::auth::oidc::{self, OidcConfig};
+use crate::auth::password_policy::{PasswordOwner, PasswordPolicy};
+use crate::auth::registration_error::RegistrationError;
+use crate::auth::two_factor::{self, TotpSetup};
 use crate::auth::user_role::UserRole;
-use crate::auth::users_db;
+use crate::auth::{sessions_db, two_factor_db, users_db};
+use actix_web::web;
 use anyhow::{anyhow, Result};
-use log::{error, info};
-use obsidian_scheduler::timer_trait::Timer;
+use chrono::Utc;
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
-use sqlx::MySqlTransaction;
-use std::time::Duration;
+use sqlx::{MySqlPool, MySqlTransaction};
+use std::sync::LazyLock;
+
+/// Checked against when there is no account for an email, to keep the timing the same.
+static DUMMY_PASSWORD_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password("not a real password").expect("Failed to hash password"));
+
+/// How long the link in a password reset email works.
+const PASSWORD_RESET_MINUTES: i64 = 60;
+
+/// `;`-separated email domains anyone can register with. Other addresses need an invite.
+const REGISTRATION_DOMAINS_VAR: &str = "REGISTRATION_ALLOWED_DOMAINS";
+const DEFAULT_REGISTRATION_DOMAINS: &str = "mardens.com";
+
+/// Whether `email` may register without an invite.
+fn is_registration_domain_allowed(email: &str) -> bool {
+    let Some((_, domain)) = email.trim().rsplit_once('@') else {
+        return false;
+    };
+    let domains = std::env::var(REGISTRATION_DOMAINS_VAR)
+        .ok()
+        .filter(|domains| !domains.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_REGISTRATION_DOMAINS.to_string());
+    domains
+        .split(';')
+        .map(str::trim)
+        .any(|allowed| !allowed.is_empty() && allowed.eq_ignore_ascii_case(domain))
+}
+
+/// Set to `true` to let a directory or single sign-on login take over a local account with the
+/// same email. Those logins are refused otherwise, since whoever controls that email in the
+/// directory or identity provider would get the local account and its role.
+const LINK_LOCAL_ACCOUNTS_VAR: &str = "EXTERNAL_LOGIN_LINKS_LOCAL_ACCOUNTS";
+
+fn links_local_accounts() -> bool {
+    std::env::var(LINK_LOCAL_ACCOUNTS_VAR)
+        .map(|value| value.trim().eq_ignore_ascii_case("true") || value.trim() == "1")
+        .unwrap_or(false)
+}
+
+/// A password hash nobody knows the password to, for users whose password is checked
+/// elsewhere. Hashed on a blocking thread like other passwords.
+async fn unusable_password_hash() -> Result<String> {
+    web::block(|| Ok(bcrypt::hash(generate_token(), bcrypt::DEFAULT_COST)?))
+        .await
+        .map_err(|e| anyhow!("Password hashing failed to run: {}", e))?
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
 pub struct User {
@@ -16,10 +79,22 @@ pub struct User {
     pub first_name: String,
     pub last_name: String,
     pub email: String,
+    /// Plain text when registering, the hash once loaded; never sent back to clients.
+    #[serde(skip_serializing)]
     pub password: String,
     pub role: UserRole,
     pub has_confirmed_email: bool,
     pub needs_password_reset: bool,
+    #[serde(default)]
+    #[sqlx(try_from = "String")]
+    pub auth_source: AuthSource,
+    /// Self-registered users can't log in until an admin approves them.
+    #[serde(default)]
+    pub is_approved: bool,
+    /// Whether `password` was hashed from the peppered password. Older hashes are replaced
+    /// at the next login.
+    #[serde(skip)]
+    pub password_peppered: bool,
 }
 
 impl PartialEq for User {
@@ -53,8 +128,24 @@ impl User {
         Ok(())
     }
 
-    pub async fn register(&mut self) -> Result<u32> {
-        let hashed_password = bcrypt::hash(&self.password, bcrypt::DEFAULT_COST)?;
+    /// Creates a local account.
+    ///
+    /// With an invite token, the user gets the role the admin invited them with and can log in
+    /// right away, as the invite email already proved the address is theirs. Without one,
+    /// only emails of the allowed domains can register; they have to confirm their email and
+    /// are then left pending, with the least privileged role, until an admin approves them.
+    pub async fn register(&mut self, invite_token: Option<&str>) -> Result<u32, RegistrationError> {
+        if let Some(invite_token) = invite_token {
+            return self.register_invited(invite_token).await;
+        }
+        if !is_registration_domain_allowed(&self.email) {
+            return Err(RegistrationError::EmailDomainNotAllowed);
+        }
+        let hashed_password = self.check_and_hash_password().await?;
+        self.role = UserRole::default();
+        self.has_confirmed_email = false;
+        self.is_approved = false;
+
         let pool = crate::app_db::create_pool().await?;
         let mut transaction = pool.begin().await?;
 
@@ -69,93 +160,834 @@ impl User {
             user_id,
         )
         .await?;
+        let confirmation = crate::auth::email_service::EmailService::confirmation_email(
+            self.email.as_str(),
+            token.as_str(),
+            self.first_name.as_str(),
+        )?;
+        crate::email_outbox::enqueue_with_transaction(
+            &mut transaction,
+            "confirmation",
+            &confirmation,
+        )
+        .await?;
 
         // Submit and clean up the transaction
         transaction.commit().await?;
         pool.close().await;
+        crate::email_outbox::deliver_soon();
 
-        let email_service = crate::auth::email_service::EmailService::new()?;
-        email_service
-            .send_confirmation_email(
-                self.email.as_str(),
-                token.as_str(),
-                self.first_name.as_str(),
-            )
-            .await?;
+        info!("User {} registered and is waiting for approval", self.email);
+        Ok(user_id)
+    }
 
-        // Start a 1-hour timer to clean up the request
-        let timer = obsidian_scheduler::callback::CallbackTimer::new(
-            move |handler| {
-                let user_id = user_id;
-                async move {
-                    let pool = crate::app_db::create_pool().await?;
-                    let mut transaction = pool.begin().await?;
+    async fn register_invited(&mut self, invite_token: &str) -> Result<u32, RegistrationError> {
+        // Checked before the invite is used up, so a weak password doesn't cost the invite.
+        let hashed_password = self.check_and_hash_password().await?;
+        let pool = crate::app_db::create_pool().await?;
+        let mut transaction = pool.begin().await?;
+        let Some(invite) = invites_db::take_invite_with_transaction(
+            &mut transaction,
+            &self.email,
+            &hash_token(invite_token),
+        )
+        .await?
+        else {
+            return Err(RegistrationError::InvalidInvite);
+        };
+        // The invite proves the address, so a registration of it that was never confirmed
+        // gives way.
+        Self::drop_unconfirmed_user_by_email_with_transaction(&mut transaction, &self.email)
+            .await?;
+        crate::auth::registration_db::remove_request_by_email_with_transaction(
+            &mut transaction,
+            &self.email,
+        )
+        .await?;
 
-                    users_db::delete_user_with_transaction(&mut transaction, user_id).await?;
-                    if let Err(e) = crate::auth::registration_db::remove_request_with_transaction(
-                        &mut transaction,
-                        user_id,
-                    )
-                    .await
-                    {
-                        error!("Failed to cleanup expired registration request: {e}")
-                    } else {
-                        // Stop the timer from repeating
-                        handler.stop();
-                    }
+        self.role = invite.role;
+        self.has_confirmed_email = true;
+        self.is_approved = true;
+        let user_id =
+            users_db::register_with_transaction(&mut transaction, self, hashed_password.as_str())
+                .await?;
+        transaction.commit().await?;
+        pool.close().await;
 
-                    transaction.commit().await?;
-                    pool.close().await;
-                    Ok(())
-                }
-            },
-            Duration::from_hours(1),
+        info!(
+            "Invited user {} registered as {:?} (ID: {})",
+            self.email, self.role, user_id
         );
-        timer.start().await?;
-
         Ok(user_id)
     }
 
+    /// Turns the password away unless it follows the password policy, and hashes it. Runs on
+    /// a blocking thread, as the breach check reads files and hashing takes a while.
+    async fn check_and_hash_password(&self) -> Result<String, RegistrationError> {
+        let user = self.clone();
+        web::block(move || {
+            let owner = PasswordOwner {
+                email: &user.email,
+                first_name: &user.first_name,
+                last_name: &user.last_name,
+            };
+            let violations = PasswordPolicy::from_config().check(&user.password, &owner)?;
+            if !violations.is_empty() {
+                return Err(RegistrationError::WeakPassword(violations));
+            }
+            Ok(hash_password(&user.password)?)
+        })
+        .await
+        .map_err(|e| anyhow!("Password check failed to run: {}", e))?
+    }
+
+    /// Invites `email` to register with `role`, and queues the invite email. Inviting an email
+    /// again sends a new link and the old one stops working.
+    pub async fn invite(
+        email: &str,
+        role: UserRole,
+        invited_by: &User,
+    ) -> Result<UserInvite, RegistrationError> {
+        let email = email.trim();
+        if users_db::get_user_by_email(email)
+            .await?
+            .is_some_and(|user| user.has_confirmed_email)
+        {
+            return Err(RegistrationError::AlreadyRegistered);
+        }
+
+        let token = generate_token();
+        let pool = crate::app_db::create_pool().await?;
+        let mut transaction = pool.begin().await?;
+        let invite = invites_db::insert_invite_with_transaction(
+            &mut transaction,
+            email,
+            &hash_token(&token),
+            role,
+            invited_by.id()?,
+        )
+        .await?;
+        let inviter_name = format!("{} {}", invited_by.first_name, invited_by.last_name);
+        let message = EmailService::invite_email(email, &token, role, inviter_name.trim())?;
+        crate::email_outbox::enqueue_with_transaction(&mut transaction, "invite", &message).await?;
+        transaction.commit().await?;
+        pool.close().await;
+        crate::email_outbox::deliver_soon();
+
+        info!("User {} invited {} as {:?}", invited_by.email, email, role);
+        Ok(invite)
+    }
+
     pub async fn confirm_email(email: &str, token: &str) -> Result<()> {
         crate::auth::registration_db::confirm_request(email, token).await?;
         Ok(())
     }
 
-    pub async fn login(email: &str, password: &str) -> Result<AuthResponse> {
-        let user: User = match users_db::get_user_by_email(email).await? {
-            Some(user) => user,
+    /// Checks the credentials and starts a session. Every attempt is recorded in
+    /// `login_attempts` with the client's IP address and user agent.
+    ///
+    /// Failed attempts count toward the per-email limit and the account's lockout. Locking
+    /// an account queues an email with a link that unlocks it.
+    ///
+    /// Users with two-factor authentication, or whose role requires it, get a challenge
+    /// instead of a session, to finish with `complete_two_factor_login`.
+    pub async fn login(
+        email: &str,
+        password: &str,
+        client: &LoginClient,
+    ) -> Result<LoginResponse, LoginError> {
+        let account_key = account_key(email);
+        if let Some(retry_after) = ACCOUNT_LIMITER.retry_after(&account_key) {
+            record_attempt(email, None, client, Some(FailureReason::RateLimited)).await?;
+            return Err(LoginError::TooManyAttempts { retry_after });
+        }
+
+        let Some(user) = users_db::get_user_by_email(email).await? else {
+            // Take as long as checking a real password so the response time doesn't reveal
+            // which emails have an account.
+            let password = password.to_string();
+            let _ = web::block(move || bcrypt::verify(password, &DUMMY_PASSWORD_HASH)).await;
+            ACCOUNT_LIMITER.hit(&account_key);
+            record_attempt(email, None, client, Some(FailureReason::UnknownEmail)).await?;
+            return Err(LoginError::InvalidCredentials);
+        };
+        let user_id = user.id()?;
+
+        let pool = crate::app_db::create_pool().await?;
+        let mut transaction = pool.begin().await?;
+
+        if let Some(locked_until) =
+            users_db::get_locked_until_with_transaction(&mut transaction, user_id).await?
+        {
+            transaction.commit().await?;
+            pool.close().await;
+            record_attempt(
+                email,
+                Some(user_id),
+                client,
+                Some(FailureReason::Locked),
+            )
+            .await?;
+            let retry_after = (locked_until - Utc::now()).to_std().unwrap_or_default();
+            return Err(LoginError::TooManyAttempts { retry_after });
+        }
+
+        if !user.validate_password(password).await? {
+            let locked = Self::count_failed_login_with_transaction(&mut transaction, &user).await?;
+            transaction.commit().await?;
+            pool.close().await;
+            if locked {
+                crate::email_outbox::deliver_soon();
+            }
+            record_attempt(
+                email,
+                Some(user_id),
+                client,
+                Some(FailureReason::WrongPassword),
+            )
+            .await?;
+            return Err(LoginError::InvalidCredentials);
+        }
+        if needs_rehash(&user.password, user.password_peppered) {
+            // The only time the password is at hand to hash again.
+            let password = password.to_string();
+            let hashed_password = web::block(move || hash_password(&password))
+                .await
+                .map_err(|e| anyhow!("Password hashing failed to run: {}", e))??;
+            users_db::update_password_hash_with_transaction(
+                &mut transaction,
+                user_id,
+                &hashed_password,
+            )
+            .await?;
+            debug!("Rehashed the password of user {}", user_id);
+        }
+
+        Self::finish_login(pool, transaction, &user, email, client).await
+    }
+
+    /// Logs in with a domain account checked by the directory instead of a password stored
+    /// here. The user is created on their first login, and their name and role are brought in
+    /// line with the directory on every login.
+    ///
+    /// Two-factor authentication applies just like for other logins; the directory's own
+    /// lockout policy takes the place of ours for wrong passwords.
+    pub async fn login_with_directory(
+        username: &str,
+        password: &str,
+        client: &LoginClient,
+    ) -> Result<LoginResponse, LoginError> {
+        let Some(directory) = directory::from_config()? else {
+            return Err(LoginError::DirectoryDisabled);
+        };
+        let account_key = account_key(username);
+        if let Some(retry_after) = ACCOUNT_LIMITER.retry_after(&account_key) {
+            record_attempt(username, None, client, Some(FailureReason::RateLimited)).await?;
+            return Err(LoginError::TooManyAttempts { retry_after });
+        }
+
+        let Some(directory_user) = directory.authenticate(username, password).await? else {
+            ACCOUNT_LIMITER.hit(&account_key);
+            record_attempt(username, None, client, Some(FailureReason::WrongPassword)).await?;
+            return Err(LoginError::InvalidCredentials);
+        };
+        ACCOUNT_LIMITER.clear(&account_key);
+        let email = directory_user.email.as_str();
+        let Some(role) = RoleMapping::from_config()?.role_for(&directory_user.groups) else {
+            record_attempt(email, None, client, Some(FailureReason::NoDirectoryRole)).await?;
+            return Err(LoginError::NoDirectoryRole);
+        };
+
+        Self::login_external_user(&directory_user, role, AuthSource::Directory, client).await
+    }
+
+    /// Logs in with the identity provider's answer to a single sign-on started by
+    /// `oidc::begin_login`. Like directory users, the user is created on their first login and
+    /// their name and role follow the identity provider's claims on every login.
+    ///
+    /// Unlike the directory, the identity provider can't be asked about users later, so a
+    /// user removed there keeps their sessions here until they expire or are revoked.
+    pub async fn login_with_oidc(
+        code: &str,
+        state: &str,
+        client: &LoginClient,
+    ) -> Result<LoginResponse, LoginError> {
+        let Some(config) = OidcConfig::from_config()? else {
+            return Err(LoginError::OidcDisabled);
+        };
+        let Some(oidc_user) = oidc::complete_login(&config, code, state).await? else {
+            return Err(LoginError::InvalidOidcLogin);
+        };
+        let email = oidc_user.email.as_str();
+        let Some(role) = OidcConfig::role_mapping()?.role_for(&oidc_user.groups) else {
+            record_attempt(email, None, client, Some(FailureReason::NoOidcRole)).await?;
+            return Err(LoginError::NoOidcRole);
+        };
+
+        Self::login_external_user(&oidc_user, role, AuthSource::Oidc, client).await
+    }
+
+    /// Rest of a directory or single sign-on login: creates the user or brings them in line
+    /// with `external_user`, then logs them in like any other user.
+    async fn login_external_user(
+        external_user: &DirectoryUser,
+        role: UserRole,
+        auth_source: AuthSource,
+        client: &LoginClient,
+    ) -> Result<LoginResponse, LoginError> {
+        let email = external_user.email.as_str();
+        let pool = crate::app_db::create_pool().await?;
+        let mut transaction = pool.begin().await?;
+        let existing =
+            users_db::get_user_by_email_with_transaction(&mut transaction, email).await?;
+        let user_id = match existing {
+            Some(user) => {
+                let user_id = user.id()?;
+                if user.auth_source == AuthSource::Local {
+                    // Only an owner who confirmed the email can have their account linked.
+                    if !links_local_accounts() || !user.has_confirmed_email {
+                        transaction.rollback().await?;
+                        pool.close().await;
+                        warn!(
+                            "Refused {} login for {}, which has a local account",
+                            auth_source.as_str(),
+                            email
+                        );
+                        record_attempt(
+                            email,
+                            Some(user_id),
+                            client,
+                            Some(FailureReason::LocalAccountExists),
+                        )
+                        .await?;
+                        return Err(LoginError::LocalAccountExists);
+                    }
+                    // The local password stops working once the directory or identity provider
+                    // takes over.
+                    users_db::set_password_with_transaction(
+                        &mut transaction,
+                        user_id,
+                        &unusable_password_hash().await?,
+                    )
+                    .await?;
+                    info!(
+                        "User {} now logs in through {}",
+                        user_id,
+                        auth_source.as_str()
+                    );
+                }
+                users_db::sync_external_user_with_transaction(
+                    &mut transaction,
+                    user_id,
+                    external_user,
+                    role,
+                    auth_source,
+                )
+                .await?;
+                user_id
+            }
             None => {
-                return Err(anyhow!(
-                    "User with email {} not found",
-                    email.replace("\n", "")
-                ));
+                let user_id = users_db::create_external_user_with_transaction(
+                    &mut transaction,
+                    external_user,
+                    role,
+                    auth_source,
+                    &unusable_password_hash().await?,
+                )
+                .await?;
+                info!(
+                    "Created user {} for {} account {}",
+                    user_id,
+                    auth_source.as_str(),
+                    email
+                );
+                user_id
             }
         };
+        let user = users_db::get_user_by_id_with_transaction(&mut transaction, user_id)
+            .await?
+            .ok_or_else(|| anyhow!("User {} disappeared during login", user_id))?;
 
-        if !user.validate_password(password).await? {
-            return Err(anyhow!("Invalid password"));
+        if let Some(locked_until) =
+            users_db::get_locked_until_with_transaction(&mut transaction, user_id).await?
+        {
+            transaction.commit().await?;
+            pool.close().await;
+            record_attempt(email, Some(user_id), client, Some(FailureReason::Locked)).await?;
+            let retry_after = (locked_until - Utc::now()).to_std().unwrap_or_default();
+            return Err(LoginError::TooManyAttempts { retry_after });
         }
 
-        if !user.has_confirmed_email {
-            return Err(anyhow!("Email not confirmed"));
+        Self::finish_login(pool, transaction, &user, email, client).await
+    }
+
+    /// Rest of a login once the password was right: turns away accounts that can't log in
+    /// yet, asks for the second factor if needed, and otherwise starts the session.
+    async fn finish_login(
+        pool: MySqlPool,
+        mut transaction: MySqlTransaction<'static>,
+        user: &User,
+        email: &str,
+        client: &LoginClient,
+    ) -> Result<LoginResponse, LoginError> {
+        let user_id = user.id()?;
+        let account_key = account_key(email);
+
+        // The password was right, so these can be told apart without giving anything away.
+        let refused = if !user.has_confirmed_email {
+            Some((
+                FailureReason::EmailNotConfirmed,
+                LoginError::EmailNotConfirmed,
+            ))
+        } else if !user.is_approved {
+            Some((FailureReason::PendingApproval, LoginError::PendingApproval))
+        } else if user.needs_password_reset {
+            Some((
+                FailureReason::PasswordResetRequired,
+                LoginError::PasswordResetRequired,
+            ))
+        } else {
+            None
+        };
+        if let Some((reason, error)) = refused {
+            users_db::clear_failed_logins_with_transaction(&mut transaction, user_id).await?;
+            ACCOUNT_LIMITER.clear(&account_key);
+            transaction.commit().await?;
+            pool.close().await;
+            record_attempt(email, Some(user_id), client, Some(reason)).await?;
+            return Err(error);
         }
 
-        if user.needs_password_reset {
-            return Err(anyhow!("Password reset required"));
+        // Failed logins are only cleared once the second factor passes, so that knowing the
+        // password doesn't buy more guesses at the code.
+        let totp =
+            two_factor_db::get_totp_state_with_transaction(&mut transaction, user_id).await?;
+        let two_factor = if totp.enabled {
+            Some(TwoFactorStep::Verify)
+        } else if two_factor_db::is_required_for_role_with_transaction(&mut transaction, user.role)
+            .await?
+        {
+            Some(TwoFactorStep::Setup)
+        } else {
+            None
+        };
+        if let Some(two_factor) = two_factor {
+            transaction.commit().await?;
+            pool.close().await;
+            record_attempt(
+                email,
+                Some(user_id),
+                client,
+                Some(FailureReason::TwoFactorPending),
+            )
+            .await?;
+            let challenge_token = generate_challenge_token(user_id, &user.email, two_factor)
+                .map_err(|e| anyhow!("Failed to generate challenge token: {e}"))?;
+            return Ok(LoginResponse::TwoFactorRequired(TwoFactorChallenge {
+                two_factor,
+                challenge_token,
+                expires_in: CHALLENGE_TOKEN_MINUTES * 60,
+            }));
         }
 
+        users_db::clear_failed_logins_with_transaction(&mut transaction, user_id).await?;
+        ACCOUNT_LIMITER.clear(&account_key);
+        let response =
+            Self::start_session_with_transaction(&mut transaction, user_id, &user.email).await?;
+        transaction.commit().await?;
+        pool.close().await;
+        record_attempt(email, Some(user_id), client, None).await?;
+
         info!("User logged in: {} (ID: {:?})", user.email, user.id);
+        Ok(LoginResponse::Authenticated(response))
+    }
+
+    /// Second step of a login that got a two-factor challenge. Takes a code from the
+    /// authenticator app or a recovery code, or for a `setup` challenge, the first code from
+    /// the app set up with `begin_login_two_factor_setup`, in which case the response carries
+    /// the new recovery codes.
+    ///
+    /// Wrong codes count toward the lockout just like wrong passwords.
+    pub async fn complete_two_factor_login(
+        challenge_token: &str,
+        code: &str,
+        client: &LoginClient,
+    ) -> Result<TwoFactorLoginResponse, LoginError> {
+        let challenge =
+            validate_challenge_token(challenge_token).map_err(|_| LoginError::InvalidChallenge)?;
+        let user_id = challenge.sub as u32;
+        let email = challenge.email.as_str();
+        let account_key = account_key(email);
+        if let Some(retry_after) = ACCOUNT_LIMITER.retry_after(&account_key) {
+            record_attempt(
+                email,
+                Some(user_id),
+                client,
+                Some(FailureReason::RateLimited),
+            )
+            .await?;
+            return Err(LoginError::TooManyAttempts { retry_after });
+        }
+
+        let pool = crate::app_db::create_pool().await?;
+        let mut transaction = pool.begin().await?;
+        let Some(user) = users_db::get_user_by_id_with_transaction(&mut transaction, user_id)
+            .await?
+            .filter(|user| user.email == challenge.email && !user.needs_password_reset)
+        else {
+            transaction.commit().await?;
+            pool.close().await;
+            return Err(LoginError::InvalidChallenge);
+        };
+
+        if let Some(locked_until) =
+            users_db::get_locked_until_with_transaction(&mut transaction, user_id).await?
+        {
+            transaction.commit().await?;
+            pool.close().await;
+            record_attempt(email, Some(user_id), client, Some(FailureReason::Locked)).await?;
+            let retry_after = (locked_until - Utc::now()).to_std().unwrap_or_default();
+            return Err(LoginError::TooManyAttempts { retry_after });
+        }
+
+        let totp =
+            two_factor_db::get_totp_state_with_transaction(&mut transaction, user_id).await?;
+        // `Some` once the code passed, holding the recovery codes when they are new.
+        let passed = match challenge.two_factor {
+            TwoFactorStep::Verify if totp.enabled => {
+                two_factor::check_code_with_transaction(&mut transaction, user_id, &totp, code)
+                    .await?
+                    .then_some(None)
+            }
+            TwoFactorStep::Setup if !totp.enabled && totp.pending_secret.is_some() => {
+                two_factor::confirm_setup_with_transaction(&mut transaction, user_id, &totp, code)
+                    .await?
+                    .map(Some)
+            }
+            _ => {
+                transaction.commit().await?;
+                pool.close().await;
+                return Err(LoginError::InvalidChallenge);
+            }
+        };
+        let Some(recovery_codes) = passed else {
+            let locked = Self::count_failed_login_with_transaction(&mut transaction, &user).await?;
+            transaction.commit().await?;
+            pool.close().await;
+            if locked {
+                crate::email_outbox::deliver_soon();
+            }
+            record_attempt(
+                email,
+                Some(user_id),
+                client,
+                Some(FailureReason::WrongTwoFactorCode),
+            )
+            .await?;
+            return Err(LoginError::InvalidTwoFactorCode);
+        };
+
+        users_db::clear_failed_logins_with_transaction(&mut transaction, user_id).await?;
+        ACCOUNT_LIMITER.clear(&account_key);
+        let auth =
+            Self::start_session_with_transaction(&mut transaction, user_id, &user.email).await?;
+        transaction.commit().await?;
+        pool.close().await;
+        record_attempt(email, Some(user_id), client, None).await?;
+
+        info!(
+            "User logged in with two-factor authentication: {} (ID: {})",
+            user.email, user_id
+        );
+        Ok(TwoFactorLoginResponse {
+            auth,
+            recovery_codes,
+        })
+    }
+
+    /// Hands out a secret for the authenticator app during a login whose challenge asks for
+    /// two-factor authentication to be set up.
+    pub async fn begin_login_two_factor_setup(
+        challenge_token: &str,
+    ) -> Result<TotpSetup, LoginError> {
+        let challenge =
+            validate_challenge_token(challenge_token).map_err(|_| LoginError::InvalidChallenge)?;
+        if challenge.two_factor != TwoFactorStep::Setup {
+            return Err(LoginError::InvalidChallenge);
+        }
+        let user_id = challenge.sub as u32;
+        let pool = crate::app_db::create_pool().await?;
+        let mut transaction = pool.begin().await?;
+        let totp =
+            two_factor_db::get_totp_state_with_transaction(&mut transaction, user_id).await?;
+        if totp.enabled {
+            transaction.commit().await?;
+            pool.close().await;
+            return Err(LoginError::InvalidChallenge);
+        }
+        let setup =
+            two_factor::begin_setup_with_transaction(&mut transaction, user_id, &challenge.email)
+                .await?;
+        transaction.commit().await?;
+        pool.close().await;
+        Ok(setup)
+    }
+
+    /// Counts a failed password or code toward the user's limit and lockout. Locking the
+    /// account queues an email with a link that unlocks it; returns whether it did.
+    async fn count_failed_login_with_transaction<'a>(
+        transaction: &mut MySqlTransaction<'a>,
+        user: &User,
+    ) -> Result<bool> {
+        let user_id = user.id()?;
+        ACCOUNT_LIMITER.hit(&account_key(&user.email));
+        let failed_logins =
+            users_db::add_failed_login_with_transaction(transaction, user_id).await?;
+        let Some(seconds) = lockout_seconds(failed_logins) else {
+            return Ok(false);
+        };
+        let token = generate_token();
+        users_db::lock_with_transaction(transaction, user_id, seconds, &hash_token(&token)).await?;
+        let unlock_email =
+            EmailService::unlock_email(&user.email, &token, &user.first_name, seconds / 60)?;
+        crate::email_outbox::enqueue_with_transaction(transaction, "unlock", &unlock_email).await?;
+        warn!(
+            "Locked user {} for {}s after {} failed logins",
+            user_id, seconds, failed_logins
+        );
+        Ok(true)
+    }
 
-        let token = generate_jwt_token(user.id()?, &user.email)
+    async fn start_session_with_transaction<'a>(
+        transaction: &mut MySqlTransaction<'a>,
+        user_id: u32,
+        email: &str,
+    ) -> Result<AuthResponse> {
+        let refresh_token = generate_token();
+        let session_id = sessions_db::create_session_with_transaction(
+            transaction,
+            user_id,
+            &hash_token(&refresh_token),
+        )
+        .await?;
+        let version = users_db::get_session_version_with_transaction(transaction, user_id).await?;
+        users_db::touch_last_online_with_transaction(transaction, user_id).await?;
+        Self::auth_response(user_id, email, session_id, version, refresh_token)
+    }
+
+    /// Queues an email with a link to choose a new password, if `email` belongs to a local
+    /// account with a confirmed email. Answers the same either way, so it doesn't tell which
+    /// emails have an account.
+    pub async fn request_password_reset(email: &str) -> Result<()> {
+        let email = email.trim();
+        let pool = crate::app_db::create_pool().await?;
+        let mut transaction = pool.begin().await?;
+        let user = users_db::get_user_by_email_with_transaction(&mut transaction, email)
+            .await?
+            .filter(|user| user.auth_source == AuthSource::Local && user.has_confirmed_email);
+        let Some(user) = user else {
+            transaction.commit().await?;
+            pool.close().await;
+            debug!("No password reset for {}", email.replace("\n", ""));
+            return Ok(());
+        };
+        let user_id = user.id()?;
+        let token = generate_token();
+        users_db::set_password_reset_token_with_transaction(
+            &mut transaction,
+            user_id,
+            &hash_token(&token),
+            PASSWORD_RESET_MINUTES,
+        )
+        .await?;
+        let message = EmailService::password_reset_email(
+            &user.email,
+            &token,
+            &user.first_name,
+            PASSWORD_RESET_MINUTES,
+        )?;
+        crate::email_outbox::enqueue_with_transaction(&mut transaction, "password_reset", &message)
+            .await?;
+        transaction.commit().await?;
+        pool.close().await;
+        crate::email_outbox::deliver_soon();
+        info!("Queued a password reset email for user {}", user_id);
+        Ok(())
+    }
+
+    /// Sets a new password with the token from the password reset email. The password has to
+    /// follow the password policy, and every session of the user ends.
+    pub async fn reset_password(
+        email: &str,
+        token: &str,
+        password: &str,
+    ) -> Result<(), RegistrationError> {
+        let email = email.trim();
+        let pool = crate::app_db::create_pool().await?;
+        let mut transaction = pool.begin().await?;
+        let Some(user_id) = users_db::get_password_reset_user_id_with_transaction(
+            &mut transaction,
+            email,
+            &hash_token(token),
+        )
+        .await?
+        else {
+            return Err(RegistrationError::InvalidResetLink);
+        };
+        let mut user = users_db::get_user_by_id_with_transaction(&mut transaction, user_id)
+            .await?
+            .ok_or_else(|| anyhow!("User {} disappeared during password reset", user_id))?;
+        user.password = password.to_string();
+        let hashed_password = user.check_and_hash_password().await?;
+        users_db::reset_password_with_transaction(&mut transaction, user_id, &hashed_password)
+            .await?;
+        users_db::bump_session_version_with_transaction(&mut transaction, user_id).await?;
+        sessions_db::delete_user_sessions_with_transaction(&mut transaction, user_id).await?;
+        transaction.commit().await?;
+        pool.close().await;
+        ACCOUNT_LIMITER.clear(&account_key(email));
+        info!("User {} reset their password", user_id);
+        Ok(())
+    }
+
+    /// Lifts a lockout with the token from the unlock email. Returns false when the token
+    /// doesn't match the latest unlock email.
+    pub async fn unlock(email: &str, token: &str) -> Result<bool> {
+        let unlocked = users_db::unlock_with_token(email, &hash_token(token)).await?;
+        if unlocked {
+            ACCOUNT_LIMITER.clear(&account_key(email));
+            info!(
+                "Account {} unlocked from the unlock email",
+                email.replace("\n", "")
+            );
+        }
+        Ok(unlocked)
+    }
+
+    /// Trades a refresh token for a new access token and refresh token. Returns `None` when the
+    /// refresh token is no longer valid and the user has to log in again.
+    pub async fn refresh(refresh_token: &str) -> Result<Option<AuthResponse>> {
+        let new_refresh_token = generate_token();
+        let pool = crate::app_db::create_pool().await?;
+        let mut transaction = pool.begin().await?;
+        let session = sessions_db::rotate_with_transaction(
+            &mut transaction,
+            &hash_token(refresh_token),
+            &hash_token(&new_refresh_token),
+        )
+        .await?;
+        let Some(session) = session else {
+            transaction.commit().await?;
+            pool.close().await;
+            return Ok(None);
+        };
+
+        let user =
+            users_db::get_user_by_id_with_transaction(&mut transaction, session.user_id).await?;
+        let user = match user.filter(|user| !user.needs_password_reset) {
+            Some(user) if user.auth_source == AuthSource::Directory => {
+                Self::recheck_directory_user_with_transaction(&mut transaction, user).await?
+            }
+            user => user,
+        };
+        let Some(user) = user else {
+            sessions_db::delete_session_by_id_with_transaction(&mut transaction, session.id)
+                .await?;
+            transaction.commit().await?;
+            pool.close().await;
+            return Ok(None);
+        };
+        let version =
+            users_db::get_session_version_with_transaction(&mut transaction, session.user_id)
+                .await?;
+        transaction.commit().await?;
+        pool.close().await;
+
+        Ok(Some(Self::auth_response(
+            session.user_id,
+            &user.email,
+            session.id,
+            version,
+            new_refresh_token,
+        )?))
+    }
+
+    /// Checks that a directory user still has an account with access, and brings their name
+    /// and role in line with it, so leavers and role changes take effect within one access
+    /// token's lifetime.
+    async fn recheck_directory_user_with_transaction<'a>(
+        transaction: &mut MySqlTransaction<'a>,
+        mut user: User,
+    ) -> Result<Option<User>> {
+        let Some(directory) = directory::from_config()? else {
+            return Ok(None);
+        };
+        let Some(directory_user) = directory.find_by_email(&user.email).await? else {
+            info!("User {} is no longer in the directory", user.email);
+            return Ok(None);
+        };
+        let Some(role) = RoleMapping::from_config()?.role_for(&directory_user.groups) else {
+            info!("User {} no longer has a role in the directory", user.email);
+            return Ok(None);
+        };
+        users_db::sync_external_user_with_transaction(
+            transaction,
+            user.id()?,
+            &directory_user,
+            role,
+            AuthSource::Directory,
+        )
+        .await?;
+        user.first_name = directory_user.first_name;
+        user.last_name = directory_user.last_name;
+        user.role = role;
+        Ok(Some(user))
+    }
+
+    /// Ends the session a refresh token belongs to.
+    pub async fn logout(refresh_token: &str) -> Result<()> {
+        sessions_db::delete_session_by_token(&hash_token(refresh_token)).await?;
+        Ok(())
+    }
+
+    /// Ends every session of the user, including access tokens that haven't expired yet.
+    pub async fn logout_everywhere(user_id: u32) -> Result<()> {
+        let pool = crate::app_db::create_pool().await?;
+        let mut transaction = pool.begin().await?;
+        users_db::bump_session_version_with_transaction(&mut transaction, user_id).await?;
+        sessions_db::delete_user_sessions_with_transaction(&mut transaction, user_id).await?;
+        transaction.commit().await?;
+        pool.close().await;
+        info!("Ended all sessions of user {}", user_id);
+        Ok(())
+    }
+
+    fn auth_response(
+        user_id: u32,
+        email: &str,
+        session_id: u64,
+        session_version: u32,
+        refresh_token: String,
+    ) -> Result<AuthResponse> {
+        let token = generate_jwt_token(user_id, email, session_id, session_version)
             .map_err(|e| anyhow!("Failed to generate JWT token: {e}"))?;
 
         Ok(AuthResponse {
             token,
             token_type: "Bearer".to_string(),
+            expires_in: ACCESS_TOKEN_MINUTES * 60,
+            refresh_token,
         })
     }
 
+    /// Checks the password against the stored hash on a blocking thread, as bcrypt takes a
+    /// while on purpose.
     pub async fn validate_password(&self, password: &str) -> Result<bool> {
-        Ok(bcrypt::verify(password, &self.password)?)
+        let password = password.to_string();
+        let hash = self.password.clone();
+        let peppered = self.password_peppered;
+        web::block(move || verify_password(&password, &hash, peppered))
+            .await
+            .map_err(|e| anyhow!("Password check failed to run: {}", e))?
     }
 }
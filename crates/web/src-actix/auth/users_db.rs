@@ -1,5 +1,10 @@
+use crate::auth::auth_source::AuthSource;
+use crate::auth::directory::DirectoryUser;
+use crate::auth::user_role::UserRole;
 use crate::auth::users_data::User;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use sqlx::MySqlTransaction;
 
 const USERS_TABLE_SCHEMA: &str = include_str!(r#"../../sql/users.sql"#);
@@ -8,6 +13,24 @@ pub async fn initialize_table<'a>(transaction: &mut MySqlTransaction<'a>) -> Res
     sqlx::query(USERS_TABLE_SCHEMA)
         .execute(&mut **transaction)
         .await?;
+    let added_columns = [
+        ("session_version", "INT UNSIGNED DEFAULT 0 NOT NULL"),
+        ("failed_logins", "INT UNSIGNED DEFAULT 0 NOT NULL"),
+        ("locked_until", "TIMESTAMP NULL"),
+        ("unlock_token_hash", "CHAR(64) NULL"),
+        ("totp_secret", "VARCHAR(64) NULL"),
+        ("totp_pending_secret", "VARCHAR(64) NULL"),
+        ("totp_enabled", "BOOLEAN DEFAULT FALSE NOT NULL"),
+        ("totp_last_step", "BIGINT UNSIGNED NULL"),
+        ("auth_source", "VARCHAR(16) DEFAULT 'local' NOT NULL"),
+        ("is_approved", "BOOLEAN DEFAULT TRUE NOT NULL"),
+        ("password_peppered", "BOOLEAN DEFAULT FALSE NOT NULL"),
+        ("password_reset_token_hash", "CHAR(64) NULL"),
+        ("password_reset_expires_at", "TIMESTAMP NULL"),
+    ];
+    for (column, definition) in added_columns {
+        crate::app_db::add_column_if_missing(transaction, "users", column, definition).await?;
+    }
     Ok(())
 }
 
@@ -69,17 +92,20 @@ pub async fn get_users() -> Result<Vec<User>> {
     Ok(users)
 }
 
+/// Inserts a local user. `hashed_password` comes from `auth_service::hash_password`.
 pub async fn register_with_transaction<'a>(
     transaction: &mut MySqlTransaction<'a>,
     user: &User,
     hashed_password: &str,
 ) -> Result<u32> {
-    let uid = sqlx::query(r#"INSERT INTO users (first_name, last_name, email, password, role) VALUES (?, ?, ?, ?, ?)"#)
+    let uid = sqlx::query(r#"INSERT INTO users (first_name, last_name, email, password, password_peppered, role, has_confirmed_email, is_approved) VALUES (?, ?, ?, ?, 1, ?, ?, ?)"#)
         .bind(&user.first_name)
         .bind(&user.last_name)
         .bind(&user.email)
         .bind(hashed_password)
         .bind(&user.role)
+        .bind(user.has_confirmed_email)
+        .bind(user.is_approved)
         .execute(&mut **transaction)
         .await?
         .last_insert_id();
@@ -105,23 +131,333 @@ pub async fn set_confirmed_email_with_transaction<'a>(transaction: &mut MySqlTra
     Ok(())
 }
 
-pub async fn delete_user_with_transaction<'a>(
+/// Deletes a user only if they never confirmed their email.
+pub async fn delete_unconfirmed_user_with_transaction<'a>(
     transaction: &mut MySqlTransaction<'a>,
     uid: u32,
 ) -> Result<()> {
-    sqlx::query(r#"DELETE FROM users WHERE id = ?"#)
+    sqlx::query(r#"DELETE FROM users WHERE id = ? AND has_confirmed_email = 0"#)
         .bind(uid)
         .execute(&mut **transaction)
         .await?;
     Ok(())
 }
 
-pub async fn delete_user(uid: u32) -> Result<()> {
+pub async fn get_session_version_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    uid: u32,
+) -> Result<u32> {
+    let version: u32 = sqlx::query_scalar(r#"SELECT session_version FROM users WHERE id = ?"#)
+        .bind(uid)
+        .fetch_one(&mut **transaction)
+        .await?;
+    Ok(version)
+}
+
+/// Invalidates every access token issued to the user so far.
+pub async fn bump_session_version_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    uid: u32,
+) -> Result<()> {
+    sqlx::query(r#"UPDATE users SET session_version = session_version + 1 WHERE id = ?"#)
+        .bind(uid)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// When the user's lockout ends, if they are locked out right now.
+pub async fn get_locked_until_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    uid: u32,
+) -> Result<Option<DateTime<Utc>>> {
+    let locked_until: Option<DateTime<Utc>> = sqlx::query_scalar(
+        r#"SELECT locked_until FROM users WHERE id = ? AND locked_until > NOW()"#,
+    )
+    .bind(uid)
+    .fetch_optional(&mut **transaction)
+    .await?;
+    Ok(locked_until)
+}
+
+/// Counts a failed login and returns the number of failures since the last successful one.
+pub async fn add_failed_login_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    uid: u32,
+) -> Result<u32> {
+    sqlx::query(r#"UPDATE users SET failed_logins = failed_logins + 1 WHERE id = ?"#)
+        .bind(uid)
+        .execute(&mut **transaction)
+        .await?;
+    let failed_logins: u32 = sqlx::query_scalar(r#"SELECT failed_logins FROM users WHERE id = ?"#)
+        .bind(uid)
+        .fetch_one(&mut **transaction)
+        .await?;
+    Ok(failed_logins)
+}
+
+/// Locks the user out for `seconds`; the unlock email carries the token behind
+/// `unlock_token_hash`.
+pub async fn lock_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    uid: u32,
+    seconds: u32,
+    unlock_token_hash: &str,
+) -> Result<()> {
+    sqlx::query(
+        r#"UPDATE users SET locked_until = NOW() + INTERVAL ? SECOND, unlock_token_hash = ? WHERE id = ?"#,
+    )
+    .bind(seconds)
+    .bind(unlock_token_hash)
+    .bind(uid)
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
+}
+
+pub async fn clear_failed_logins_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    uid: u32,
+) -> Result<()> {
+    sqlx::query(
+        r#"UPDATE users SET failed_logins = 0, locked_until = NULL, unlock_token_hash = NULL WHERE id = ?"#,
+    )
+    .bind(uid)
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
+}
+
+/// Lifts the lockout of the user with `email` if `unlock_token_hash` matches the latest unlock
+/// email. Returns false when it doesn't.
+pub async fn unlock_with_token(email: &str, unlock_token_hash: &str) -> Result<bool> {
     let pool = crate::app_db::create_pool().await?;
     let mut transaction = pool.begin().await?;
-    delete_user_with_transaction(&mut transaction, uid).await?;
+    let unlocked = sqlx::query(
+        r#"UPDATE users SET failed_logins = 0, locked_until = NULL, unlock_token_hash = NULL WHERE email = ? AND unlock_token_hash = ?"#,
+    )
+    .bind(email)
+    .bind(unlock_token_hash)
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected()
+        == 1;
     transaction.commit().await?;
     pool.close().await;
+    Ok(unlocked)
+}
+
+/// Stores the token behind the latest password reset email; earlier reset links stop working.
+pub async fn set_password_reset_token_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    uid: u32,
+    token_hash: &str,
+    minutes: i64,
+) -> Result<()> {
+    sqlx::query(
+        r#"UPDATE users SET password_reset_token_hash = ?, password_reset_expires_at = NOW() + INTERVAL ? MINUTE WHERE id = ?"#,
+    )
+    .bind(token_hash)
+    .bind(minutes)
+    .bind(uid)
+    .execute(&mut **transaction)
+    .await?;
     Ok(())
 }
 
+/// The local user with `email` whose latest password reset email carries `token_hash`, if it
+/// hasn't expired. Locks the user's row until the transaction ends.
+pub async fn get_password_reset_user_id_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    email: &str,
+    token_hash: &str,
+) -> Result<Option<u32>> {
+    let uid: Option<u32> = sqlx::query_scalar(
+        r#"SELECT id FROM users WHERE email = ? AND auth_source = 'local' AND password_reset_token_hash = ? AND password_reset_expires_at > NOW() LIMIT 1 FOR UPDATE"#,
+    )
+    .bind(email)
+    .bind(token_hash)
+    .fetch_optional(&mut **transaction)
+    .await?;
+    Ok(uid)
+}
+
+/// Sets the password chosen through a password reset email and uses the reset token up. A
+/// lockout and a required reset are cleared along with it.
+pub async fn reset_password_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    uid: u32,
+    hashed_password: &str,
+) -> Result<()> {
+    sqlx::query(
+        r#"UPDATE users SET password = ?, password_peppered = 1, needs_password_reset = 0, password_reset_token_hash = NULL, password_reset_expires_at = NULL, failed_logins = 0, locked_until = NULL, unlock_token_hash = NULL WHERE id = ?"#,
+    )
+    .bind(hashed_password)
+    .bind(uid)
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
+}
+
+pub async fn touch_last_online_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    uid: u32,
+) -> Result<()> {
+    sqlx::query(r#"UPDATE users SET last_online = NOW() WHERE id = ?"#)
+        .bind(uid)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+pub async fn touch_last_online(uid: u32) -> Result<()> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    touch_last_online_with_transaction(&mut transaction, uid).await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(())
+}
+
+/// Creates the account of a user logging in through the directory or single sign-on for the
+/// first time. The directory or identity provider checks their password and vouches for
+/// their email.
+pub async fn create_external_user_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    external_user: &DirectoryUser,
+    role: UserRole,
+    auth_source: AuthSource,
+    unusable_password_hash: &str,
+) -> Result<u32> {
+    let uid = sqlx::query(
+        r#"INSERT INTO users (first_name, last_name, email, password, role, has_confirmed_email, auth_source) VALUES (?, ?, ?, ?, ?, 1, ?)"#,
+    )
+    .bind(&external_user.first_name)
+    .bind(&external_user.last_name)
+    .bind(&external_user.email)
+    .bind(unusable_password_hash)
+    .bind(role)
+    .bind(auth_source.as_str())
+    .execute(&mut **transaction)
+    .await?
+    .last_insert_id();
+    Ok(uid as u32)
+}
+
+/// Brings a user's name and role in line with the directory or identity provider.
+pub async fn sync_external_user_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    uid: u32,
+    external_user: &DirectoryUser,
+    role: UserRole,
+    auth_source: AuthSource,
+) -> Result<()> {
+    sqlx::query(
+        r#"UPDATE users SET first_name = ?, last_name = ?, role = ?, has_confirmed_email = 1, needs_password_reset = 0, is_approved = 1, auth_source = ? WHERE id = ?"#,
+    )
+    .bind(&external_user.first_name)
+    .bind(&external_user.last_name)
+    .bind(role)
+    .bind(auth_source.as_str())
+    .bind(uid)
+    .execute(&mut **transaction)
+    .await?;
+    // The directory vouches for the address, so a pending email confirmation is moot.
+    crate::auth::registration_db::remove_requests_for_user_with_transaction(transaction, uid)
+        .await?;
+    Ok(())
+}
+
+/// Replaces a password hash with one from `auth_service::hash_password`, made from the same
+/// password with the current pepper and cost.
+pub async fn update_password_hash_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    uid: u32,
+    hashed_password: &str,
+) -> Result<()> {
+    sqlx::query(r#"UPDATE users SET password = ?, password_peppered = 1 WHERE id = ?"#)
+        .bind(hashed_password)
+        .bind(uid)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_password_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    uid: u32,
+    hashed_password: &str,
+) -> Result<()> {
+    sqlx::query(r#"UPDATE users SET password = ? WHERE id = ?"#)
+        .bind(hashed_password)
+        .bind(uid)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// A self-registered user waiting for an admin to approve them.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PendingUser {
+    pub id: u32,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub has_confirmed_email: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Users waiting for approval, oldest first.
+pub async fn get_pending_users() -> Result<Vec<PendingUser>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let users: Vec<PendingUser> = sqlx::query_as(
+        r#"SELECT id, first_name, last_name, email, has_confirmed_email, created_at FROM users WHERE is_approved = 0 ORDER BY created_at"#,
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(users)
+}
+
+/// Lets a pending user log in with the role an admin picked for them. Returns whether the
+/// user was waiting for approval.
+pub async fn approve_user(uid: u32, role: UserRole) -> Result<bool> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let approved = sqlx::query(
+        r#"UPDATE users SET is_approved = 1, role = ? WHERE id = ? AND is_approved = 0"#,
+    )
+    .bind(role)
+    .bind(uid)
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected()
+        > 0;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(approved)
+}
+
+/// Deletes a pending user along with their registration request. Returns whether the user
+/// was waiting for approval; approved users are left alone.
+pub async fn reject_user(uid: u32) -> Result<bool> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let rejected = sqlx::query(r#"DELETE FROM users WHERE id = ? AND is_approved = 0"#)
+        .bind(uid)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected()
+        > 0;
+    if rejected {
+        sqlx::query(r#"DELETE FROM registration_requests WHERE user_id = ?"#)
+            .bind(uid)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(rejected)
+}
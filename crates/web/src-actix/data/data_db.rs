@@ -1,4 +1,5 @@
 use anyhow::Result;
+use po_tracker_share_lib::taxonomy::{Taxonomy, TaxonomyEntry};
 use serde::Serialize;
 use sqlx::MySqlTransaction;
 
@@ -104,3 +105,53 @@ pub async fn get_all_seasons() -> Result<Vec<Season>> {
     pool.close().await;
     Ok(seasons)
 }
+
+/// Loads the full department/category/subcategory tree for resolving manifest values.
+pub async fn get_taxonomy() -> Result<Taxonomy> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let departments: Vec<Department> =
+        sqlx::query_as(r#"SELECT id, name, code FROM departments ORDER BY name"#)
+            .fetch_all(&mut *transaction)
+            .await?;
+    let categories: Vec<Category> =
+        sqlx::query_as(r#"SELECT id, name, code, department_id FROM categories ORDER BY name"#)
+            .fetch_all(&mut *transaction)
+            .await?;
+    let subcategories: Vec<Subcategory> =
+        sqlx::query_as(r#"SELECT id, name, code, category_id FROM subcategories ORDER BY name"#)
+            .fetch_all(&mut *transaction)
+            .await?;
+    transaction.commit().await?;
+    pool.close().await;
+
+    Ok(Taxonomy {
+        departments: departments
+            .into_iter()
+            .map(|d| TaxonomyEntry {
+                id: d.id,
+                name: d.name,
+                code: d.code,
+                parent_id: None,
+            })
+            .collect(),
+        categories: categories
+            .into_iter()
+            .map(|c| TaxonomyEntry {
+                id: c.id,
+                name: c.name,
+                code: c.code,
+                parent_id: Some(c.department_id),
+            })
+            .collect(),
+        subcategories: subcategories
+            .into_iter()
+            .map(|s| TaxonomyEntry {
+                id: s.id,
+                name: s.name,
+                code: s.code,
+                parent_id: Some(s.category_id),
+            })
+            .collect(),
+    })
+}
@@ -1,8 +1,15 @@
-use actix_web::{get, web, HttpResponse, Responder, Result};
+use crate::auth::auth_middleware::validator;
+use crate::util::json_config::json_config;
+use actix_web::{get, post, web, HttpResponse, Responder, Result};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use po_tracker_share_lib::manifest::ManifestRow;
 use serde_json::json;
 
 use super::data_db;
 
+/// Batches of manifest rows are far larger than the default 4 KiB JSON body.
+const RESOLVE_JSON_LIMIT: usize = 16 * 1024 * 1024;
+
 #[get("/department")]
 pub async fn get_departments() -> Result<impl Responder> {
     let departments = data_db::get_all_departments()
@@ -37,13 +44,30 @@ pub async fn get_seasons() -> Result<impl Responder> {
     Ok(HttpResponse::Ok().json(seasons))
 }
 
+/// Resolves the department, category and subcategory of a batch of manifest rows. Needs a
+/// login, as a batch can be large and every row is matched against the whole taxonomy.
+#[post("/resolve")]
+pub async fn resolve_taxonomy(rows: web::Json<Vec<ManifestRow>>) -> Result<impl Responder> {
+    let taxonomy = data_db::get_taxonomy()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(taxonomy.resolve_rows(&rows)))
+}
+
 pub fn configure(cfg: &mut actix_web::web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(validator);
     cfg.service(
         actix_web::web::scope("/data")
             .service(get_departments)
             .service(get_categories)
             .service(get_subcategories)
             .service(get_seasons)
+            .service(
+                web::scope("")
+                    .wrap(auth)
+                    .app_data(json_config(RESOLVE_JSON_LIMIT))
+                    .service(resolve_taxonomy),
+            )
             .default_service(actix_web::web::to(|| async {
                 HttpResponse::NotFound().json(json!({
                     "error": "API endpoint not found".to_string(),
@@ -0,0 +1,7 @@
+mod outbox_db;
+mod outbox_endpoint;
+mod outbox_worker;
+
+pub use outbox_db::{enqueue_with_transaction, initialize_table, purge_sent};
+pub use outbox_endpoint::configure;
+pub use outbox_worker::{deliver_due, deliver_soon};
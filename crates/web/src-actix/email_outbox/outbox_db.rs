@@ -0,0 +1,259 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use lettre::Message;
+use serde::{Deserialize, Serialize};
+use sqlx::MySqlTransaction;
+use sqlx::types::Json;
+
+const EMAIL_OUTBOX_TABLE_SCHEMA: &str = include_str!(r#"../../sql/email_outbox.sql"#);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum OutboxStatus {
+    /// Waiting for its first or next attempt.
+    Pending,
+    Sent,
+    /// Gave up after the maximum number of attempts.
+    Failed,
+}
+
+/// A queued email, without the message itself.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct OutboxEntry {
+    pub id: u64,
+    pub kind: String,
+    pub sender: String,
+    pub recipients: Json<Vec<String>>,
+    pub subject: String,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+/// A queued email with the formatted message, as handed to the transport.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OutboxMessage {
+    pub sender: String,
+    pub recipients: Json<Vec<String>>,
+    pub message: Vec<u8>,
+    pub attempts: u32,
+}
+
+const ENTRY_COLUMNS: &str = "id, kind, sender, recipients, subject, status, attempts, last_error, next_attempt_at, created_at, sent_at";
+
+pub async fn initialize_table<'a>(transaction: &mut MySqlTransaction<'a>) -> Result<()> {
+    sqlx::query(EMAIL_OUTBOX_TABLE_SCHEMA)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// Queues a message for delivery; `kind` names the email for the admin views, e.g.
+/// `confirmation`.
+pub async fn enqueue_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    kind: &str,
+    message: &Message,
+) -> Result<u64> {
+    let envelope = message.envelope();
+    let sender = envelope
+        .from()
+        .ok_or_else(|| anyhow!("Email has no sender"))?
+        .to_string();
+    let recipients: Vec<String> = envelope.to().iter().map(ToString::to_string).collect();
+    let subject = message
+        .headers()
+        .get_raw("Subject")
+        .unwrap_or_default()
+        .to_string();
+    let id = sqlx::query(
+        r#"INSERT INTO email_outbox (kind, sender, recipients, subject, message) VALUES (?, ?, ?, ?, ?)"#,
+    )
+    .bind(kind)
+    .bind(sender)
+    .bind(Json(recipients))
+    .bind(subject)
+    .bind(message.formatted())
+    .execute(&mut **transaction)
+    .await?
+    .last_insert_id();
+    Ok(id)
+}
+
+/// Ids of pending messages whose next attempt is due, oldest first.
+pub async fn get_due_ids(limit: u32) -> Result<Vec<u64>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let ids: Vec<u64> = sqlx::query_scalar(
+        r#"SELECT id FROM email_outbox WHERE status = 'pending' AND next_attempt_at <= NOW() ORDER BY next_attempt_at, id LIMIT ?"#,
+    )
+    .bind(limit)
+    .fetch_all(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(ids)
+}
+
+/// Claims a due message for one attempt by counting the attempt and pushing its next attempt
+/// out by `lease_seconds`. Returns `None` when it is no longer due, e.g. because another run
+/// claimed it first.
+pub async fn claim(id: u64, lease_seconds: u32) -> Result<Option<OutboxMessage>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let claimed = sqlx::query(
+        r#"UPDATE email_outbox SET attempts = attempts + 1, next_attempt_at = NOW() + INTERVAL ? SECOND WHERE id = ? AND status = 'pending' AND next_attempt_at <= NOW()"#,
+    )
+    .bind(lease_seconds)
+    .bind(id)
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected()
+        == 1;
+    let message = if claimed {
+        sqlx::query_as(
+            r#"SELECT sender, recipients, message, attempts FROM email_outbox WHERE id = ?"#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *transaction)
+        .await?
+    } else {
+        None
+    };
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(message)
+}
+
+pub async fn mark_sent(id: u64) -> Result<()> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    sqlx::query(
+        r#"UPDATE email_outbox SET status = 'sent', sent_at = NOW(), last_error = NULL WHERE id = ?"#,
+    )
+    .bind(id)
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(())
+}
+
+/// Records a failed attempt. With `retry_in` the message is tried again after that many
+/// seconds, otherwise it is marked as failed.
+pub async fn mark_attempt_failed(id: u64, error: &str, retry_in: Option<u32>) -> Result<()> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    match retry_in {
+        Some(seconds) => {
+            sqlx::query(
+                r#"UPDATE email_outbox SET last_error = ?, next_attempt_at = NOW() + INTERVAL ? SECOND WHERE id = ?"#,
+            )
+            .bind(error)
+            .bind(seconds)
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        None => {
+            sqlx::query(
+                r#"UPDATE email_outbox SET status = 'failed', last_error = ? WHERE id = ?"#,
+            )
+            .bind(error)
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        }
+    }
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(())
+}
+
+pub async fn get_entries(status: Option<OutboxStatus>, limit: u32) -> Result<Vec<OutboxEntry>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let entries: Vec<OutboxEntry> = match status {
+        Some(status) => sqlx::query_as(&format!(
+            r#"SELECT {ENTRY_COLUMNS} FROM email_outbox WHERE status = ? ORDER BY id DESC LIMIT ?"#
+        ))
+        .bind(status)
+        .bind(limit)
+        .fetch_all(&mut *transaction)
+        .await?,
+        None => {
+            sqlx::query_as(&format!(
+                r#"SELECT {ENTRY_COLUMNS} FROM email_outbox ORDER BY id DESC LIMIT ?"#
+            ))
+            .bind(limit)
+            .fetch_all(&mut *transaction)
+            .await?
+        }
+    };
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(entries)
+}
+
+pub async fn get_entry(id: u64) -> Result<Option<(OutboxEntry, Vec<u8>)>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let entry: Option<OutboxEntry> = sqlx::query_as(&format!(
+        r#"SELECT {ENTRY_COLUMNS} FROM email_outbox WHERE id = ?"#
+    ))
+    .bind(id)
+    .fetch_optional(&mut *transaction)
+    .await?;
+    let entry = match entry {
+        Some(entry) => {
+            let message: Vec<u8> =
+                sqlx::query_scalar(r#"SELECT message FROM email_outbox WHERE id = ?"#)
+                    .bind(id)
+                    .fetch_one(&mut *transaction)
+                    .await?;
+            Some((entry, message))
+        }
+        None => None,
+    };
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(entry)
+}
+
+/// Puts a message back in the queue for an immediate attempt with a fresh attempt count.
+/// Returns false when there is no such message.
+pub async fn requeue(id: u64) -> Result<bool> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let found = sqlx::query(
+        r#"UPDATE email_outbox SET status = 'pending', attempts = 0, next_attempt_at = NOW() WHERE id = ?"#,
+    )
+    .bind(id)
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected()
+        == 1;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(found)
+}
+
+/// Deletes messages that were sent more than `days` days ago. Returns how many were deleted.
+pub async fn purge_sent(days: u32) -> Result<u64> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let deleted = sqlx::query(
+        r#"DELETE FROM email_outbox WHERE status = 'sent' AND sent_at < NOW() - INTERVAL ? DAY"#,
+    )
+    .bind(days)
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(deleted)
+}
@@ -0,0 +1,79 @@
+use crate::auth::auth_middleware::{require_admin, validator};
+use crate::email_outbox::outbox_db::{self, OutboxStatus};
+use crate::email_outbox::outbox_worker::deliver_soon;
+use actix_web::{HttpRequest, HttpResponse, Responder, Result, get, post, web};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use serde::Deserialize;
+use serde_json::json;
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 1000;
+
+#[derive(Deserialize, Debug)]
+pub struct OutboxQuery {
+    pub status: Option<OutboxStatus>,
+    pub limit: Option<u32>,
+}
+
+#[get("")]
+pub async fn get_outbox(
+    req: HttpRequest,
+    query: web::Query<OutboxQuery>,
+) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let entries = outbox_db::get_entries(query.status, limit)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+/// A queued email including the formatted message.
+#[get("/{id}")]
+pub async fn get_outbox_entry(req: HttpRequest, id: web::Path<u64>) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    let entry = outbox_db::get_entry(id.into_inner())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    match entry {
+        Some((entry, message)) => Ok(HttpResponse::Ok().json(json!({
+            "entry": entry,
+            "message": String::from_utf8_lossy(&message),
+        }))),
+        None => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Email not found".to_string(),
+        }))),
+    }
+}
+
+/// Queues the email again for an immediate attempt.
+#[post("/{id}/resend")]
+pub async fn resend_outbox_entry(req: HttpRequest, id: web::Path<u64>) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    let found = outbox_db::requeue(id.into_inner())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !found {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "error": "Email not found".to_string(),
+        })));
+    }
+    deliver_soon();
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(validator);
+    cfg.service(
+        web::scope("/email-outbox")
+            .wrap(auth)
+            .service(get_outbox)
+            .service(get_outbox_entry)
+            .service(resend_outbox_entry)
+            .default_service(web::to(|| async {
+                HttpResponse::NotFound().json(json!({
+                    "error": "API endpoint not found".to_string(),
+                }))
+            })),
+    );
+}
@@ -0,0 +1,89 @@
+use crate::auth::mail_transport::{self, MailTransport};
+use crate::email_outbox::outbox_db::{self, OutboxMessage};
+use anyhow::Result;
+use lettre::address::Envelope;
+use log::{error, info, warn};
+use std::sync::Arc;
+
+/// Messages delivered per run.
+const BATCH_SIZE: u32 = 20;
+/// How long a claimed message is held back from other runs while it is being sent.
+const LEASE_SECONDS: u32 = 300;
+/// Attempts before a message is marked as failed.
+const MAX_ATTEMPTS: u32 = 8;
+/// Delay after the first failed attempt; it doubles with every further attempt.
+const BASE_RETRY_SECONDS: u32 = 30;
+const MAX_RETRY_SECONDS: u32 = 60 * 60;
+
+/// Delivers due messages in the background now instead of waiting for the scheduler, e.g.
+/// right after a confirmation email was queued.
+pub fn deliver_soon() {
+    tokio::spawn(async {
+        if let Err(e) = deliver_due().await {
+            error!("Failed to deliver queued email: {e}");
+        }
+    });
+}
+
+/// Sends every message that is due, one batch at a time. Run by the scheduler's
+/// `deliver_email` job.
+pub async fn deliver_due() -> Result<()> {
+    let ids = outbox_db::get_due_ids(BATCH_SIZE).await?;
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let transport = mail_transport::from_config()?;
+    for id in ids {
+        let Some(message) = outbox_db::claim(id, LEASE_SECONDS).await? else {
+            continue;
+        };
+        match send(transport.clone(), &message).await {
+            Ok(()) => {
+                outbox_db::mark_sent(id).await?;
+                info!("Delivered queued email {}", id);
+            }
+            Err(e) => {
+                let retry_in = retry_delay(message.attempts);
+                match retry_in {
+                    Some(seconds) => warn!(
+                        "Attempt {} to deliver email {} failed, retrying in {}s: {e}",
+                        message.attempts, id, seconds
+                    ),
+                    None => error!(
+                        "Giving up on email {} after {} attempts: {e}",
+                        id, message.attempts
+                    ),
+                }
+                outbox_db::mark_attempt_failed(id, &e.to_string(), retry_in).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn send(transport: Arc<dyn MailTransport>, message: &OutboxMessage) -> Result<()> {
+    let envelope = Envelope::new(
+        Some(message.sender.parse()?),
+        message
+            .recipients
+            .iter()
+            .map(|recipient| recipient.parse())
+            .collect::<Result<_, _>>()?,
+    )?;
+    let raw = message.message.clone();
+    // The SMTP transport blocks, so keep it off the async workers.
+    tokio::task::spawn_blocking(move || transport.send_raw(&envelope, &raw)).await?
+}
+
+/// Seconds until the next attempt after `attempts` failed ones, or `None` to give up.
+fn retry_delay(attempts: u32) -> Option<u32> {
+    if attempts >= MAX_ATTEMPTS {
+        return None;
+    }
+    let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+    Some(
+        BASE_RETRY_SECONDS
+            .saturating_mul(factor)
+            .min(MAX_RETRY_SECONDS),
+    )
+}
@@ -1,9 +1,8 @@
 use crate::util::asset_endpoint::AssetsAppConfig;
 use actix_cors::Cors;
-use actix_web::{http::header, middleware, web, App, HttpResponse, HttpServer, Responder};
+use actix_web::{http::header, middleware, web, App, HttpServer, Responder};
 use anyhow::Result;
 use log::*;
-use serde_json::json;
 use vite_actix::proxy_vite_options::ProxyViteOptions;
 use vite_actix::start_vite_server;
 
@@ -12,6 +11,11 @@ mod auth;
 mod status_endpoint;
 mod util;
 mod data;
+mod email_outbox;
+mod scheduler;
+mod po;
+
+pub use auth::mail_transport;
 
 pub static DEBUG: bool = cfg!(debug_assertions);
 const PORT: u16 = 8522;
@@ -27,6 +31,7 @@ pub async fn run() -> Result<()> {
         .init();
 
     app_db::initialize_database().await?;
+    scheduler::start().await?;
 
     let server = HttpServer::new(move || {
         let cors = Cors::default()
@@ -47,22 +52,14 @@ pub async fn run() -> Result<()> {
         App::new()
             .wrap(cors)
             .wrap(middleware::Logger::default())
-            .app_data(
-                web::JsonConfig::default()
-                    .limit(4096)
-                    .error_handler(|err, _req| {
-                        let error = json!({ "error": format!("{}", err) });
-                        actix_web::error::InternalError::from_response(
-                            err,
-                            HttpResponse::BadRequest().json(error),
-                        )
-                        .into()
-                    }),
-            )
+            .app_data(util::json_config::json_config(4096))
             .service(
                 web::scope("api")
                     .configure(status_endpoint::configure)
                     .configure(data::configure)
+                    .configure(po::configure)
+                    .configure(email_outbox::configure)
+                    .configure(scheduler::configure)
                     .configure(auth::configure),
             )
             .configure_frontend_routes()
@@ -0,0 +1,6 @@
+mod po_email;
+mod po_endpoint;
+mod po_sent_log_db;
+
+pub use po_endpoint::configure;
+pub use po_sent_log_db::initialize_table;
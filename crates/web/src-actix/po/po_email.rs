@@ -0,0 +1,219 @@
+use crate::auth::email_service::{EmailAttachment, EmailService, PoEmail};
+use crate::auth::users_data::User;
+use crate::po::po_sent_log_db::{self, SentLogEntry};
+use anyhow::{Result, anyhow};
+use po_tracker_share_lib::manifest::{MANIFEST_FIELDS, ManifestRow};
+use po_tracker_share_lib::po_pdf::render_po_pdf;
+use po_tracker_share_lib::save_file::SaveFile;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct SendPoEmailBody {
+    pub save_file: SaveFile,
+    /// The vendor contact's address.
+    pub to: String,
+    #[serde(default)]
+    pub contact_name: Option<String>,
+    #[serde(default)]
+    pub cc: Vec<String>,
+    /// Defaults to `Purchase Order {po_number}`.
+    #[serde(default)]
+    pub subject: Option<String>,
+    /// Note from the buyer shown above the PO details.
+    #[serde(default)]
+    pub message: String,
+}
+
+impl SendPoEmailBody {
+    /// Checks the request before anything is rendered or sent.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.save_file.po_number.trim().is_empty() {
+            return Err("The PO has no PO number".to_string());
+        }
+        for address in std::iter::once(&self.to).chain(&self.cc) {
+            address
+                .parse::<lettre::Address>()
+                .map_err(|_| format!("Invalid email address: {}", address))?;
+        }
+        Ok(())
+    }
+}
+
+/// Queues the PO email with the PDF and the normalized manifest attached, and records it in
+/// the PO's sent-log.
+pub async fn send_po_email(body: SendPoEmailBody, sender: &User) -> Result<SentLogEntry> {
+    let save_file = body.save_file;
+    let po_number = save_file.po_number.trim().to_string();
+    let lines = save_file.lines();
+    if lines.is_empty() {
+        return Err(anyhow!("PO {} has no manifest lines to send", po_number));
+    }
+
+    let file_stem = format!("PO-{}", file_safe(&po_number));
+    let attachments = vec![
+        EmailAttachment {
+            filename: format!("{}.pdf", file_stem),
+            content_type: "application/pdf".to_string(),
+            body: render_po_pdf(&save_file, &lines),
+        },
+        EmailAttachment {
+            filename: format!("{}-manifest.csv", file_stem),
+            content_type: "text/csv".to_string(),
+            body: manifest_csv(&lines)?,
+        },
+    ];
+    let attachment_names: Vec<String> = attachments
+        .iter()
+        .map(|attachment| attachment.filename.clone())
+        .collect();
+
+    let mut context = tera::Context::new();
+    context.insert("po_number", &po_number);
+    context.insert("contact_name", &body.contact_name);
+    context.insert("message", &body.message.trim());
+    context.insert("vendor", &save_file.vendor);
+    context.insert("order_date", &save_file.order_date);
+    context.insert("ship_date", &save_file.ship_date);
+    context.insert("cancel_date", &save_file.cancel_date);
+    context.insert("terms", &save_file.terms);
+    context.insert("fob_type", &save_file.fob_type);
+    context.insert("fob_point", &save_file.fob_point);
+    context.insert("ship_to_address", &save_file.ship_to_address);
+    context.insert("attachments", &attachment_names);
+    context.insert(
+        "sender_name",
+        &format!("{} {}", sender.first_name, sender.last_name),
+    );
+    context.insert("reply_to", &sender.email);
+
+    let subject = body
+        .subject
+        .filter(|subject| !subject.trim().is_empty())
+        .unwrap_or_else(|| format!("Purchase Order {}", po_number));
+    let message = EmailService::po_email(PoEmail {
+        to: &body.to,
+        cc: &body.cc,
+        reply_to: &sender.email,
+        subject: &subject,
+        context,
+        attachments,
+    })?;
+    let message_id = message
+        .headers()
+        .get_raw("Message-ID")
+        .unwrap_or_default()
+        .to_string();
+
+    let recipients: Vec<String> = std::iter::once(body.to).chain(body.cc).collect();
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    crate::email_outbox::enqueue_with_transaction(&mut transaction, "po", &message).await?;
+    let entry = po_sent_log_db::insert_entry_with_transaction(
+        &mut transaction,
+        &po_number,
+        &recipients,
+        &subject,
+        &attachment_names,
+        &message_id,
+        sender.id()?,
+    )
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    crate::email_outbox::deliver_soon();
+    Ok(entry)
+}
+
+/// The manifest lines as CSV with the template fields as columns.
+fn manifest_csv(lines: &[ManifestRow]) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(MANIFEST_FIELDS)?;
+    for line in lines {
+        writer.write_record(
+            MANIFEST_FIELDS
+                .iter()
+                .map(|field| line.field(field).unwrap_or_default()),
+        )?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+/// Replaces characters that aren't safe in an attachment file name.
+fn file_safe(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::mail_transport::{MailTransport, MemoryTransport};
+
+    #[test]
+    fn po_email_reaches_the_vendor_with_attachments() {
+        let mut context = tera::Context::new();
+        for key in [
+            "po_number",
+            "message",
+            "vendor",
+            "order_date",
+            "ship_date",
+            "cancel_date",
+            "terms",
+            "fob_type",
+            "fob_point",
+            "ship_to_address",
+            "sender_name",
+            "reply_to",
+        ] {
+            context.insert(key, "");
+        }
+        context.insert("contact_name", &None::<String>);
+        context.insert("attachments", &["PO-12345.pdf", "PO-12345-manifest.xlsx"]);
+        let cc = vec!["ap@acme.example.test".to_string()];
+        let message = EmailService::po_email(PoEmail {
+            to: "orders@acme.example.test",
+            cc: &cc,
+            reply_to: "blake.buyer@example.test",
+            subject: "Purchase Order 12345",
+            context,
+            attachments: vec![
+                EmailAttachment {
+                    filename: "PO-12345.pdf".to_string(),
+                    content_type: "application/pdf".to_string(),
+                    body: b"%PDF-1.4".to_vec(),
+                },
+                EmailAttachment {
+                    filename: "PO-12345-manifest.xlsx".to_string(),
+                    content_type:
+                        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+                            .to_string(),
+                    body: b"PK".to_vec(),
+                },
+            ],
+        })
+        .unwrap();
+
+        let transport = MemoryTransport::default();
+        transport.send(&message).unwrap();
+        let sent = transport.take().remove(0);
+        assert_eq!(
+            sent.to,
+            ["orders@acme.example.test", "ap@acme.example.test"]
+        );
+        assert_eq!(sent.subject, "Purchase Order 12345");
+        assert!(sent.raw.contains("To: orders@acme.example.test"));
+        assert!(sent.raw.contains("Cc: ap@acme.example.test"));
+        assert!(sent.raw.contains("Reply-To: blake.buyer@example.test"));
+        assert!(sent.raw.contains("filename=\"PO-12345.pdf\""));
+        assert!(sent.raw.contains("filename=\"PO-12345-manifest.xlsx\""));
+    }
+}
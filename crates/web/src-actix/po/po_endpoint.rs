@@ -0,0 +1,73 @@
+use crate::auth::auth_middleware::{require_buyer, validator};
+use crate::po::po_email::{SendPoEmailBody, send_po_email};
+use crate::po::po_sent_log_db;
+use crate::util::json_config::json_config;
+use actix_web::web::Json;
+use actix_web::{HttpRequest, HttpResponse, Responder, Result, get, post, web};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use po_tracker_share_lib::manifest::ManifestRow;
+use po_tracker_share_lib::po_pdf::render_po_pdf;
+use po_tracker_share_lib::save_file::SaveFile;
+use po_tracker_share_lib::summary::summarize;
+use serde_json::json;
+
+/// Manifests are posted whole, so allow far more than the default 4 KiB JSON body.
+const PO_JSON_LIMIT: usize = 16 * 1024 * 1024;
+
+#[post("/summary")]
+pub async fn get_summary(rows: Json<Vec<ManifestRow>>) -> Result<impl Responder> {
+    let summary = summarize(&rows.into_inner());
+    Ok(HttpResponse::Ok().json(summary))
+}
+
+/// Renders the printable PO for a save file, using its stored lines with the buyer's edits.
+#[post("/pdf")]
+pub async fn get_pdf(save_file: Json<SaveFile>) -> Result<impl Responder> {
+    let save_file = save_file.into_inner();
+    let pdf = web::block(move || render_po_pdf(&save_file, &save_file.lines())).await?;
+    Ok(HttpResponse::Ok().content_type("application/pdf").body(pdf))
+}
+
+/// Queues the PO email to a vendor contact and returns the sent-log entry. Buyers and admins
+/// only.
+#[post("/email")]
+pub async fn email_po(req: HttpRequest, body: Json<SendPoEmailBody>) -> Result<impl Responder> {
+    let sender = require_buyer(&req).await?;
+    let body = body.into_inner();
+    if let Err(error) = body.validate() {
+        return Ok(HttpResponse::BadRequest().json(json!({ "error": error })));
+    }
+    let entry = send_po_email(body, &sender)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(entry))
+}
+
+#[get("/{po_number}/sent-log")]
+pub async fn get_sent_log(po_number: web::Path<String>) -> Result<impl Responder> {
+    let entries = po_sent_log_db::get_entries(po_number.trim())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(validator);
+    cfg.service(
+        web::scope("/po")
+            .app_data(json_config(PO_JSON_LIMIT))
+            .service(
+                web::scope("")
+                    .wrap(auth)
+                    .service(get_summary)
+                    .service(get_pdf)
+                    .service(email_po)
+                    .service(get_sent_log),
+            )
+            .default_service(web::to(|| async {
+                HttpResponse::NotFound().json(json!({
+                    "error": "API endpoint not found".to_string(),
+                }))
+            })),
+    );
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::MySqlTransaction;
+use sqlx::types::Json;
+
+const PO_SENT_LOG_TABLE_SCHEMA: &str = include_str!(r#"../../sql/po_sent_log.sql"#);
+
+/// One email of a PO to a vendor.
+#[derive(Debug, Serialize, Clone, sqlx::FromRow)]
+pub struct SentLogEntry {
+    pub id: u64,
+    pub po_number: String,
+    pub recipients: Json<Vec<String>>,
+    pub subject: String,
+    pub attachments: Json<Vec<String>>,
+    pub message_id: String,
+    pub sent_by: u32,
+    pub sent_at: DateTime<Utc>,
+}
+
+pub async fn initialize_table<'a>(transaction: &mut MySqlTransaction<'a>) -> Result<()> {
+    sqlx::query(PO_SENT_LOG_TABLE_SCHEMA)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// Records a PO email; it's written in the transaction that queues the email, so the log
+/// only lists emails that are on their way.
+pub async fn insert_entry_with_transaction<'a>(
+    transaction: &mut MySqlTransaction<'a>,
+    po_number: &str,
+    recipients: &[String],
+    subject: &str,
+    attachments: &[String],
+    message_id: &str,
+    sent_by: u32,
+) -> Result<SentLogEntry> {
+    let id = sqlx::query(
+        r#"INSERT INTO po_sent_log (po_number, recipients, subject, attachments, message_id, sent_by) VALUES (?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(po_number)
+    .bind(Json(recipients))
+    .bind(subject)
+    .bind(Json(attachments))
+    .bind(message_id)
+    .bind(sent_by)
+    .execute(&mut **transaction)
+    .await?
+    .last_insert_id();
+    let entry: SentLogEntry = sqlx::query_as(r#"SELECT * FROM po_sent_log WHERE id = ?"#)
+        .bind(id)
+        .fetch_one(&mut **transaction)
+        .await?;
+    Ok(entry)
+}
+
+pub async fn get_entries(po_number: &str) -> Result<Vec<SentLogEntry>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let entries: Vec<SentLogEntry> = sqlx::query_as(
+        r#"SELECT * FROM po_sent_log WHERE po_number = ? ORDER BY sent_at DESC, id DESC"#,
+    )
+    .bind(po_number)
+    .fetch_all(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(entries)
+}
@@ -0,0 +1,50 @@
+use crate::scheduler::jobs::JOBS;
+use crate::scheduler::jobs_db;
+use anyhow::Result;
+use log::{debug, error, info};
+use obsidian_scheduler::callback::CallbackTimer;
+use obsidian_scheduler::timer_trait::Timer;
+use std::time::Duration;
+
+/// How often the scheduler checks for due jobs.
+const TICK: Duration = Duration::from_secs(5);
+/// How long a claimed job is held back from other runs; a run that takes longer than this is
+/// assumed to have died and may be started again.
+const LEASE_SECONDS: u32 = 15 * 60;
+
+/// Registers the jobs and starts the single timer that runs them when they are due.
+///
+/// Schedules live in the `scheduled_jobs` table, so they survive restarts and several
+/// server instances can share them without running a job twice.
+pub async fn start() -> Result<()> {
+    jobs_db::register_jobs(JOBS).await?;
+    let timer = CallbackTimer::new(|_handle| run_due(), TICK);
+    timer.start().await?;
+    info!(
+        "Scheduler started with {} jobs, checking every {}s",
+        JOBS.len(),
+        TICK.as_secs()
+    );
+    Ok(())
+}
+
+/// Runs every job that is due, one after the other.
+async fn run_due() -> Result<()> {
+    let names: Vec<&str> = JOBS.iter().map(|job| job.name()).collect();
+    let claimed = jobs_db::claim_due(&names, LEASE_SECONDS).await?;
+    for job in JOBS {
+        if !claimed.iter().any(|name| name == job.name()) {
+            continue;
+        }
+        debug!("Running job {}", job.name());
+        let error = match job.run().await {
+            Ok(()) => None,
+            Err(e) => {
+                error!("Job {} failed: {e}", job.name());
+                Some(e.to_string())
+            }
+        };
+        jobs_db::finish(job.name(), error.as_deref()).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use crate::auth::{invites_db, login_attempts_db, oidc_db, registration_db, sessions_db};
+use crate::email_outbox;
+use anyhow::Result;
+use log::info;
+use std::time::Duration;
+
+/// How long sent email stays in the outbox for troubleshooting.
+const SENT_EMAIL_RETENTION_DAYS: u32 = 30;
+/// How long login attempts are kept for auditing.
+const LOGIN_ATTEMPT_RETENTION_DAYS: u32 = 90;
+
+/// Periodic work run by the scheduler. Each job has a row in `scheduled_jobs` that keeps
+/// its schedule across restarts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Job {
+    /// Drops registrations whose email wasn't confirmed in time.
+    ExpireRegistrations,
+    /// Deletes invites nobody used in time.
+    ExpireInvites,
+    /// Deletes sessions whose refresh token has expired.
+    ExpireSessions,
+    /// Deletes single sign-on logins nobody came back from.
+    ExpireOidcLogins,
+    /// Delivers queued email from the outbox.
+    DeliverEmail,
+    /// Deletes old sent email from the outbox.
+    PurgeSentEmail,
+    /// Deletes old entries from the login audit table.
+    PurgeLoginAttempts,
+}
+
+/// Every job the scheduler runs.
+pub const JOBS: &[Job] = &[
+    Job::ExpireRegistrations,
+    Job::ExpireInvites,
+    Job::ExpireSessions,
+    Job::ExpireOidcLogins,
+    Job::DeliverEmail,
+    Job::PurgeSentEmail,
+    Job::PurgeLoginAttempts,
+];
+
+impl Job {
+    pub fn name(self) -> &'static str {
+        match self {
+            Job::ExpireRegistrations => "expire_registrations",
+            Job::ExpireInvites => "expire_invites",
+            Job::ExpireSessions => "expire_sessions",
+            Job::ExpireOidcLogins => "expire_oidc_logins",
+            Job::DeliverEmail => "deliver_email",
+            Job::PurgeSentEmail => "purge_sent_email",
+            Job::PurgeLoginAttempts => "purge_login_attempts",
+        }
+    }
+
+    pub fn interval(self) -> Duration {
+        match self {
+            Job::ExpireRegistrations => Duration::from_mins(5),
+            Job::ExpireInvites | Job::ExpireSessions | Job::ExpireOidcLogins => {
+                Duration::from_hours(1)
+            }
+            Job::DeliverEmail => Duration::from_secs(15),
+            Job::PurgeSentEmail | Job::PurgeLoginAttempts => Duration::from_hours(24),
+        }
+    }
+
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Job::ExpireRegistrations => {
+                let dropped = registration_db::remove_expired_requests().await?;
+                if dropped > 0 {
+                    info!("Dropped {} expired registration requests", dropped);
+                }
+            }
+            Job::ExpireInvites => {
+                let deleted = invites_db::remove_expired_invites().await?;
+                if deleted > 0 {
+                    info!("Deleted {} expired invites", deleted);
+                }
+            }
+            Job::ExpireSessions => {
+                let deleted = sessions_db::remove_expired_sessions().await?;
+                if deleted > 0 {
+                    info!("Deleted {} expired sessions", deleted);
+                }
+            }
+            Job::ExpireOidcLogins => {
+                let deleted = oidc_db::remove_expired_logins().await?;
+                if deleted > 0 {
+                    info!("Deleted {} expired single sign-on logins", deleted);
+                }
+            }
+            Job::DeliverEmail => email_outbox::deliver_due().await?,
+            Job::PurgeSentEmail => {
+                let deleted = email_outbox::purge_sent(SENT_EMAIL_RETENTION_DAYS).await?;
+                if deleted > 0 {
+                    info!("Deleted {} sent emails from the outbox", deleted);
+                }
+            }
+            Job::PurgeLoginAttempts => {
+                let deleted =
+                    login_attempts_db::purge_attempts(LOGIN_ATTEMPT_RETENTION_DAYS).await?;
+                if deleted > 0 {
+                    info!("Deleted {} old login attempts", deleted);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use crate::scheduler::jobs::Job;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::MySqlTransaction;
+
+const SCHEDULED_JOBS_TABLE_SCHEMA: &str = include_str!(r#"../../sql/scheduled_jobs.sql"#);
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ScheduledJob {
+    pub name: String,
+    pub interval_seconds: u32,
+    pub next_run_at: DateTime<Utc>,
+    /// Set while a run is in progress; a run that died is retried once this passes.
+    pub locked_until: Option<DateTime<Utc>>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    /// The error of the last run, if it failed.
+    pub last_error: Option<String>,
+}
+
+pub async fn initialize_table<'a>(transaction: &mut MySqlTransaction<'a>) -> Result<()> {
+    sqlx::query(SCHEDULED_JOBS_TABLE_SCHEMA)
+        .execute(&mut **transaction)
+        .await?;
+    Ok(())
+}
+
+/// Adds rows for new jobs and updates the interval of existing ones. A job keeps its next run
+/// time, so restarting the server doesn't run everything again.
+pub async fn register_jobs(jobs: &[Job]) -> Result<()> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    for job in jobs {
+        let interval = job.interval().as_secs() as u32;
+        sqlx::query(
+            r#"INSERT INTO scheduled_jobs (name, interval_seconds) VALUES (?, ?) ON DUPLICATE KEY UPDATE interval_seconds = ?"#,
+        )
+        .bind(job.name())
+        .bind(interval)
+        .bind(interval)
+        .execute(&mut *transaction)
+        .await?;
+    }
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(())
+}
+
+/// Locks every due job in `names` for `lease_seconds` and returns the names of the ones this
+/// call got. Jobs that are running elsewhere, or that another call claimed first, are skipped.
+pub async fn claim_due(names: &[&str], lease_seconds: u32) -> Result<Vec<String>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let mut claimed = Vec::new();
+    for name in names {
+        let rows = sqlx::query(
+            r#"UPDATE scheduled_jobs SET locked_until = NOW() + INTERVAL ? SECOND WHERE name = ? AND next_run_at <= NOW() AND (locked_until IS NULL OR locked_until <= NOW())"#,
+        )
+        .bind(lease_seconds)
+        .bind(name)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+        if rows == 1 {
+            claimed.push(name.to_string());
+        }
+    }
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(claimed)
+}
+
+/// Releases a claimed job and schedules its next run one interval from now.
+pub async fn finish(name: &str, error: Option<&str>) -> Result<()> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    sqlx::query(
+        r#"UPDATE scheduled_jobs SET locked_until = NULL, last_run_at = NOW(), last_error = ?, next_run_at = NOW() + INTERVAL interval_seconds SECOND WHERE name = ?"#,
+    )
+    .bind(error)
+    .bind(name)
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(())
+}
+
+pub async fn get_jobs() -> Result<Vec<ScheduledJob>> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let jobs = sqlx::query_as(
+        r#"SELECT name, interval_seconds, next_run_at, locked_until, last_run_at, last_error FROM scheduled_jobs ORDER BY name"#,
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(jobs)
+}
+
+/// Makes a job due right away. Returns false when there is no such job.
+pub async fn run_now(name: &str) -> Result<bool> {
+    let pool = crate::app_db::create_pool().await?;
+    let mut transaction = pool.begin().await?;
+    let found = sqlx::query(r#"UPDATE scheduled_jobs SET next_run_at = NOW() WHERE name = ?"#)
+        .bind(name)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected()
+        == 1;
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(found)
+}
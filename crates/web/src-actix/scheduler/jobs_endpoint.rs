@@ -0,0 +1,44 @@
+use crate::auth::auth_middleware::{require_admin, validator};
+use crate::scheduler::jobs_db;
+use actix_web::{HttpRequest, HttpResponse, Responder, Result, get, post, web};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use serde_json::json;
+
+#[get("")]
+pub async fn get_jobs(req: HttpRequest) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    let jobs = jobs_db::get_jobs()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(jobs))
+}
+
+/// Makes the job due so it runs on the scheduler's next check.
+#[post("/{name}/run")]
+pub async fn run_job(req: HttpRequest, name: web::Path<String>) -> Result<impl Responder> {
+    require_admin(&req).await?;
+    let found = jobs_db::run_now(&name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !found {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "error": "Job not found".to_string(),
+        })));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(validator);
+    cfg.service(
+        web::scope("/scheduled-jobs")
+            .wrap(auth)
+            .service(get_jobs)
+            .service(run_job)
+            .default_service(web::to(|| async {
+                HttpResponse::NotFound().json(json!({
+                    "error": "API endpoint not found".to_string(),
+                }))
+            })),
+    );
+}
@@ -0,0 +1,8 @@
+mod job_runner;
+mod jobs;
+mod jobs_db;
+mod jobs_endpoint;
+
+pub use job_runner::start;
+pub use jobs_db::initialize_table;
+pub use jobs_endpoint::configure;
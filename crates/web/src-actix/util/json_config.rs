@@ -0,0 +1,19 @@
+use actix_web::{HttpResponse, web};
+use serde_json::json;
+
+/// JSON extractor config that answers malformed or oversized bodies with `{"error": ...}`.
+///
+/// Scopes that need a larger body limit must use this too, since their config replaces the
+/// app-wide one along with its error handler.
+pub fn json_config(limit: usize) -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(limit)
+        .error_handler(|err, _req| {
+            let error = json!({ "error": format!("{}", err) });
+            actix_web::error::InternalError::from_response(
+                err,
+                HttpResponse::BadRequest().json(error),
+            )
+            .into()
+        })
+}
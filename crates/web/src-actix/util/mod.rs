@@ -1,2 +1,3 @@
-
-pub mod asset_endpoint;
\ No newline at end of file
+pub mod asset_endpoint;
+pub mod json_config;
+pub mod rate_limit;
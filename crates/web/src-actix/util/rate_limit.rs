@@ -0,0 +1,105 @@
+use actix_web::HttpRequest;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// Set to `true` when the server runs behind a reverse proxy, so the client address is taken
+/// from `Forwarded`/`X-Forwarded-For` instead of being the proxy's. Without a proxy those
+/// headers come from the client and can't be trusted.
+const TRUST_FORWARDED_FOR_VAR: &str = "TRUST_FORWARDED_FOR";
+
+static TRUST_FORWARDED_FOR: LazyLock<bool> = LazyLock::new(|| {
+    std::env::var(TRUST_FORWARDED_FOR_VAR)
+        .map(|value| value.trim().eq_ignore_ascii_case("true") || value.trim() == "1")
+        .unwrap_or(false)
+});
+
+/// Keys tracked before old ones are swept out.
+const SWEEP_THRESHOLD: usize = 1024;
+
+/// Allows at most `max_hits` hits per key within a sliding window.
+///
+/// Counts are kept in memory, so they are per server instance and start over on restart.
+pub struct RateLimiter {
+    max_hits: usize,
+    window: Duration,
+    hits: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_hits: usize, window: Duration) -> Self {
+        Self {
+            max_hits,
+            window,
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// How long until `key` may be hit again, or `None` if it is under the limit.
+    pub fn retry_after(&self, key: &str) -> Option<Duration> {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().expect("rate limiter lock poisoned");
+        let times = hits.get_mut(key)?;
+        self.drop_expired(times, now);
+        if times.len() < self.max_hits {
+            return None;
+        }
+        times
+            .front()
+            .map(|oldest| self.window.saturating_sub(now.duration_since(*oldest)))
+    }
+
+    /// Counts a hit against `key`.
+    pub fn hit(&self, key: &str) {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().expect("rate limiter lock poisoned");
+        if hits.len() >= SWEEP_THRESHOLD {
+            hits.retain(|_, times| {
+                self.drop_expired(times, now);
+                !times.is_empty()
+            });
+        }
+        let times = hits.entry(key.to_string()).or_default();
+        self.drop_expired(times, now);
+        times.push_back(now);
+    }
+
+    /// Counts a hit against `key` unless it is over the limit, in which case it returns how
+    /// long until it may be hit again.
+    pub fn check_and_hit(&self, key: &str) -> Result<(), Duration> {
+        if let Some(wait) = self.retry_after(key) {
+            return Err(wait);
+        }
+        self.hit(key);
+        Ok(())
+    }
+
+    /// Forgets the hits against `key`.
+    pub fn clear(&self, key: &str) {
+        self.hits
+            .lock()
+            .expect("rate limiter lock poisoned")
+            .remove(key);
+    }
+
+    fn drop_expired(&self, times: &mut VecDeque<Instant>, now: Instant) {
+        while times
+            .front()
+            .is_some_and(|time| now.duration_since(*time) >= self.window)
+        {
+            times.pop_front();
+        }
+    }
+}
+
+/// The IP address of the client that made the request.
+pub fn client_ip(req: &HttpRequest) -> String {
+    if *TRUST_FORWARDED_FOR
+        && let Some(ip) = req.connection_info().realip_remote_addr()
+    {
+        return ip.to_string();
+    }
+    req.peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
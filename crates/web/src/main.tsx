@@ -5,6 +5,8 @@ import ReactDOM from "react-dom/client";
 import "./css/index.css";
 import {HeroUIProvider} from "@heroui/react";
 import {ConfirmEmail} from "./pages/ConfirmEmail.tsx";
+import {UnlockAccount} from "./pages/UnlockAccount.tsx";
+import {ResetPassword} from "./pages/ResetPassword.tsx";
 import {ProtectedRoute} from "./components/ProtectedRoute.tsx";
 import {Login} from "./pages/Login.tsx";
 import {Register} from "./pages/Register.tsx";
@@ -31,6 +33,8 @@ export function MainContentRenderer()
                 <Route path={"/login"} element={<Login/>}/>
                 <Route path={"/register"} element={<Register/>}/>
                 <Route path={"/confirm-email"} element={<ConfirmEmail/>}/>
+                <Route path={"/unlock-account"} element={<UnlockAccount/>}/>
+                <Route path={"/reset-password"} element={<ResetPassword/>}/>
                 <Route element={<ProtectedRoute/>}>
                     <Route path={"/"} element={<Dashboard/>}/>
                 </Route>
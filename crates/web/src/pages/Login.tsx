@@ -1,8 +1,8 @@
-import {addToast, Button, Form, Image, Input, Link, Spinner} from "@heroui/react";
+import {addToast, Button, Form, Image, Input, Link, Spinner, Tab, Tabs} from "@heroui/react";
 import {Icon} from "@iconify-icon/react";
 import {FormEvent, useEffect, useState} from "react";
 import {useNavigate} from "react-router-dom";
-import {useAuthentication} from "../providers/AuthenticationProvider.tsx";
+import {LoginProviders, TotpSetup, TwoFactorChallenge, User, useAuthentication} from "../providers/AuthenticationProvider.tsx";
 import Logo from "../images/favicon.ico";
 
 const validateEmail = (value: string) =>
@@ -15,24 +15,121 @@ const validateEmail = (value: string) =>
     return null;
 };
 
+// The state of a single sign-on in progress, checked when the identity provider sends the user back
+const OIDC_STATE_KEY = "pocf_oidc_state";
+
 export function Login()
 {
     const navigate = useNavigate();
-    const {login, isLoading: authLoading, isAuthenticated} = useAuthentication();
+    const {login, loginWithDirectory, getLoginProviders, beginOidcLogin, loginWithOidc, completeTwoFactorLogin, beginTwoFactorSetup, isLoading: authLoading, isAuthenticated} = useAuthentication();
 
     const [email, setEmail] = useState("");
     const [password, setPassword] = useState("");
     const [showPassword, setShowPassword] = useState(false);
     const [isSubmitting, setIsSubmitting] = useState(false);
 
-    // Redirect authenticated users to main app
+    // Staff can sign in with their domain account when the server has a directory set up
+    const [providers, setProviders] = useState<LoginProviders>({password: true, directory: false, oidc: false});
+    const [useDirectory, setUseDirectory] = useState(false);
+
+    // Second step, when the password was right but a code is needed
+    const [challenge, setChallenge] = useState<TwoFactorChallenge | null>(null);
+    const [totpSetup, setTotpSetup] = useState<TotpSetup | null>(null);
+    const [code, setCode] = useState("");
+    const [recoveryCodes, setRecoveryCodes] = useState<string[] | null>(null);
+
+    // Redirect authenticated users to main app, unless they still have to see their recovery codes
     useEffect(() =>
     {
-        if (!authLoading && isAuthenticated)
+        if (!authLoading && isAuthenticated && !challenge)
         {
             navigate("/", {replace: true});
         }
-    }, [authLoading, isAuthenticated, navigate]);
+    }, [authLoading, isAuthenticated, challenge, navigate]);
+
+    useEffect(() =>
+    {
+        getLoginProviders()
+            .then(setProviders)
+            .catch(() => setProviders({password: true, directory: false, oidc: false}));
+    }, [getLoginProviders]);
+
+    const handleLoginResult = async (result: User | TwoFactorChallenge | undefined) =>
+    {
+        if (result && "challenge_token" in result)
+        {
+            setChallenge(result);
+            if (result.two_factor === "setup")
+            {
+                setTotpSetup(await beginTwoFactorSetup(result.challenge_token));
+            }
+        } else if (result)
+        {
+            navigate("/po-number", {replace: true});
+        } else
+        {
+            addToast({
+                title: "Login Failed",
+                description: "Please check your credentials and try again.",
+                color: "danger"
+            });
+        }
+    };
+
+    const showLoginError = (err: unknown) =>
+    {
+        addToast({
+            title: "Login Error",
+            description: err instanceof Error ? err.message : "An unexpected error occurred",
+            color: "danger"
+        });
+    };
+
+    // Back from the identity provider with a code, or an error
+    useEffect(() =>
+    {
+        const params = new URLSearchParams(window.location.search);
+        const code = params.get("code");
+        const state = params.get("state");
+        const error = params.get("error");
+        if (!code && !error) return;
+
+        // Drop the code from the address bar so a reload doesn't try to use it again
+        window.history.replaceState(null, "", window.location.pathname);
+        const expectedState = sessionStorage.getItem(OIDC_STATE_KEY);
+        sessionStorage.removeItem(OIDC_STATE_KEY);
+        if (error || !code || !state || state !== expectedState)
+        {
+            addToast({
+                title: "Single Sign-On Failed",
+                description: params.get("error_description") || error || "The sign-in didn't come back as expected, please try again.",
+                color: "danger"
+            });
+            return;
+        }
+
+        setIsSubmitting(true);
+        loginWithOidc(code, state)
+            .then(handleLoginResult)
+            .catch(showLoginError)
+            .finally(() => setIsSubmitting(false));
+        // Only when landing here from the identity provider
+    }, []);
+
+    const handleOidcLogin = async () =>
+    {
+        setIsSubmitting(true);
+        try
+        {
+            const authorization = await beginOidcLogin(`${window.location.origin}/login`);
+            sessionStorage.setItem(OIDC_STATE_KEY, authorization.state);
+            window.location.assign(authorization.authorization_url);
+        } catch (err)
+        {
+            showLoginError(err);
+            setIsSubmitting(false);
+        }
+    };
 
     const handleSubmit = async (e: FormEvent<HTMLFormElement>) =>
     {
@@ -42,9 +139,12 @@ export function Login()
         const emailValue = formData.email as string;
         const passwordValue = formData.password as string;
 
-        // Validate email
-        const emailError = validateEmail(emailValue);
-        if (emailError) return;
+        // Validate email, directory usernames don't have to be one
+        if (!useDirectory)
+        {
+            const emailError = validateEmail(emailValue);
+            if (emailError) return;
+        } else if (!emailValue) return;
 
         if (!passwordValue) return;
 
@@ -52,22 +152,41 @@ export function Login()
 
         try
         {
-            const user = await login(emailValue, passwordValue);
-            if (user)
+            const result = useDirectory
+                ? await loginWithDirectory(emailValue, passwordValue)
+                : await login(emailValue, passwordValue);
+            await handleLoginResult(result);
+        } catch (err)
+        {
+            showLoginError(err);
+        } finally
+        {
+            setIsSubmitting(false);
+        }
+    };
+
+    const handleCodeSubmit = async (e: FormEvent<HTMLFormElement>) =>
+    {
+        e.preventDefault();
+        if (!challenge || !code) return;
+
+        setIsSubmitting(true);
+
+        try
+        {
+            const result = await completeTwoFactorLogin(challenge.challenge_token, code);
+            if (result.recoveryCodes)
             {
-                navigate("/po-number", {replace: true});
+                setRecoveryCodes(result.recoveryCodes);
             } else
             {
-                addToast({
-                    title: "Login Failed",
-                    description: "Please check your credentials and try again.",
-                    color: "danger"
-                });
+                navigate("/po-number", {replace: true});
             }
         } catch (err)
         {
+            setCode("");
             addToast({
-                title: "Login Error",
+                title: "Verification Failed",
                 description: err instanceof Error ? err.message : "An unexpected error occurred",
                 color: "danger"
             });
@@ -100,116 +219,265 @@ export function Login()
                 </div>
             </div>
             <div className="w-full max-w-md">
-                {/* Header */}
-                <div className="flex flex-col items-center mb-8">
-                    <h1 className="font-headers font-black text-5xl text-primary uppercase tracking-wide">
-                        Sign In
-                    </h1>
-                    <p className="font-text text-lg text-foreground/70 mt-2">
-                        Welcome back! Please enter your credentials.
-                    </p>
-                </div>
+                {recoveryCodes ? (
+                    <div className="flex flex-col gap-6">
+                        <div className="flex flex-col items-center">
+                            <h1 className="font-headers font-black text-4xl text-primary uppercase tracking-wide">
+                                Recovery Codes
+                            </h1>
+                            <p className="font-text text-lg text-foreground/70 mt-2 text-center">
+                                Keep these somewhere safe. Each one signs you in once if you lose your authenticator app. They won't be shown again.
+                            </p>
+                        </div>
+                        <div className="grid grid-cols-2 gap-2 font-mono text-lg border-2 border-primary/50 p-4">
+                            {recoveryCodes.map(recoveryCode => <span key={recoveryCode}>{recoveryCode}</span>)}
+                        </div>
+                        <Button
+                            radius="none"
+                            color="primary"
+                            size="lg"
+                            className="font-headers font-bold text-lg uppercase"
+                            onPress={() => navigate("/po-number", {replace: true})}
+                            fullWidth
+                        >
+                            Continue
+                        </Button>
+                    </div>
+                ) : challenge ? (
+                    <div className="flex flex-col gap-6">
+                        <div className="flex flex-col items-center">
+                            <h1 className="font-headers font-black text-4xl text-primary uppercase tracking-wide">
+                                {challenge.two_factor === "setup" ? "Set Up 2FA" : "Verify It's You"}
+                            </h1>
+                            <p className="font-text text-lg text-foreground/70 mt-2 text-center">
+                                {challenge.two_factor === "setup"
+                                    ? "Your account requires two-factor authentication. Add it to your authenticator app, then enter the code it shows."
+                                    : "Enter the code from your authenticator app, or one of your recovery codes."}
+                            </p>
+                        </div>
+
+                        {challenge.two_factor === "setup" && (
+                            totpSetup ? (
+                                <div className="flex flex-col gap-2 border-2 border-primary/50 p-4 break-all">
+                                    <p className="font-headers font-bold uppercase">Secret key</p>
+                                    <p className="font-mono text-lg">{totpSetup.secret}</p>
+                                    <p className="font-headers font-bold uppercase mt-2">Setup link</p>
+                                    <Link href={totpSetup.otpauth_uri} className="font-mono text-sm">{totpSetup.otpauth_uri}</Link>
+                                </div>
+                            ) : (
+                                <div className="flex justify-center"><Spinner color="primary"/></div>
+                            )
+                        )}
 
-                {/* Login Form */}
-                <Form
-                    onSubmit={handleSubmit}
-                    validationBehavior="native"
-                    className="flex flex-col gap-6"
-                >
-                    {/* Email Input */}
-                    <Input
-                        name="email"
-                        type="email"
-                        label="Email"
-                        labelPlacement="outside"
-                        radius="none"
-                        size="lg"
-                        placeholder="you@mardens.com"
-                        value={email}
-                        onValueChange={setEmail}
-                        autoComplete="one-time-code"
-                        isRequired
-                        validate={validateEmail}
-                        startContent={
-                            <Icon icon="tabler:mail" width={20} height={20} className="text-foreground/50"/>
-                        }
-                        classNames={{
-                            label: "font-headers font-bold text-lg uppercase",
-                            input: "font-text text-lg",
-                            inputWrapper: "border-2 border-primary/50 hover:border-primary focus-within:border-primary transition-colors"
-                        }}
-                        isDisabled={isSubmitting}
-                    />
-
-                    {/* Password Input */}
-                    <Input
-                        name="password"
-                        type={showPassword ? "text" : "password"}
-                        label="Password"
-                        labelPlacement="outside"
-                        radius="none"
-                        size="lg"
-                        placeholder="Enter your password"
-                        value={password}
-                        onValueChange={setPassword}
-                        autoComplete="one-time-code"
-                        isRequired
-                        errorMessage="Password is required"
-                        startContent={
-                            <Icon icon="tabler:lock" width={20} height={20} className="text-foreground/50"/>
-                        }
-                        endContent={
+                        <Form
+                            onSubmit={handleCodeSubmit}
+                            validationBehavior="native"
+                            className="flex flex-col gap-6"
+                        >
+                            <Input
+                                name="code"
+                                label="Code"
+                                labelPlacement="outside"
+                                radius="none"
+                                size="lg"
+                                placeholder="123456"
+                                value={code}
+                                onValueChange={setCode}
+                                autoComplete="one-time-code"
+                                autoFocus
+                                isRequired
+                                startContent={
+                                    <Icon icon="tabler:shield-lock" width={20} height={20} className="text-foreground/50"/>
+                                }
+                                classNames={{
+                                    label: "font-headers font-bold text-lg uppercase",
+                                    input: "font-text text-lg",
+                                    inputWrapper: "border-2 border-primary/50 hover:border-primary focus-within:border-primary transition-colors"
+                                }}
+                                isDisabled={isSubmitting}
+                            />
                             <Button
-                                isIconOnly
-                                variant="light"
-                                size="sm"
+                                type="submit"
                                 radius="none"
-                                onPress={() => setShowPassword(!showPassword)}
+                                color="primary"
+                                size="lg"
+                                className="font-headers font-bold text-lg uppercase mt-4"
+                                isLoading={isSubmitting}
+                                isDisabled={isSubmitting || (challenge.two_factor === "setup" && !totpSetup)}
+                                fullWidth
                             >
-                                <Icon
-                                    icon={showPassword ? "tabler:eye-off" : "tabler:eye"}
-                                    width={20}
-                                    height={20}
-                                    className="text-foreground/50"
-                                />
+                                {isSubmitting ? "Verifying..." : "Verify"}
                             </Button>
-                        }
-                        classNames={{
-                            label: "font-headers font-bold text-lg uppercase",
-                            input: "font-text text-lg",
-                            inputWrapper: "border-2 border-primary/50 hover:border-primary focus-within:border-primary transition-colors"
-                        }}
-                        isDisabled={isSubmitting}
-                    />
-
-                    {/* Submit Button */}
-                    <Button
-                        type="submit"
-                        radius="none"
-                        color="primary"
-                        size="lg"
-                        className="font-headers font-bold text-lg uppercase mt-4"
-                        isLoading={isSubmitting}
-                        isDisabled={isSubmitting}
-                        startContent={!isSubmitting && <Icon icon="tabler:login" width={20} height={20}/>}
-                        fullWidth
-                    >
-                        {isSubmitting ? "Signing In..." : "Sign In"}
-                    </Button>
-
-                    {/* Register Link */}
-                    <div className="flex justify-center mt-4">
-                        <p className="font-text text-foreground/70">
-                            Don't have an account?{" "}
-                            <Link
-                                href="/register"
-                                className="font-bold text-primary hover:underline"
+                            <Button
+                                variant="light"
+                                radius="none"
+                                onPress={() =>
+                                {
+                                    setChallenge(null);
+                                    setTotpSetup(null);
+                                    setCode("");
+                                }}
+                                fullWidth
                             >
-                                Create one
-                            </Link>
-                        </p>
+                                Back to sign in
+                            </Button>
+                        </Form>
                     </div>
-                </Form>
+                ) : (
+                    <>
+                        {/* Header */}
+                        <div className="flex flex-col items-center mb-8">
+                            <h1 className="font-headers font-black text-5xl text-primary uppercase tracking-wide">
+                                Sign In
+                            </h1>
+                            <p className="font-text text-lg text-foreground/70 mt-2">
+                                {useDirectory
+                                    ? "Sign in with your Mardens network account."
+                                    : "Welcome back! Please enter your credentials."}
+                            </p>
+                        </div>
+
+                        {providers.directory && (
+                            <Tabs
+                                aria-label="Sign in with"
+                                radius="none"
+                                color="primary"
+                                fullWidth
+                                className="mb-6"
+                                selectedKey={useDirectory ? "directory" : "password"}
+                                onSelectionChange={key => setUseDirectory(key === "directory")}
+                                isDisabled={isSubmitting}
+                            >
+                                <Tab key="password" title="Email"/>
+                                <Tab key="directory" title="Network Account"/>
+                            </Tabs>
+                        )}
+
+                        {/* Login Form */}
+                        <Form
+                            onSubmit={handleSubmit}
+                            validationBehavior="native"
+                            className="flex flex-col gap-6"
+                        >
+                            {/* Email Input, or the username of the network account */}
+                            <Input
+                                name="email"
+                                type={useDirectory ? "text" : "email"}
+                                label={useDirectory ? "Username" : "Email"}
+                                labelPlacement="outside"
+                                radius="none"
+                                size="lg"
+                                placeholder={useDirectory ? "jsmith" : "you@mardens.com"}
+                                value={email}
+                                onValueChange={setEmail}
+                                autoComplete="one-time-code"
+                                isRequired
+                                validate={useDirectory ? undefined : validateEmail}
+                                startContent={
+                                    <Icon icon={useDirectory ? "tabler:user" : "tabler:mail"} width={20} height={20} className="text-foreground/50"/>
+                                }
+                                classNames={{
+                                    label: "font-headers font-bold text-lg uppercase",
+                                    input: "font-text text-lg",
+                                    inputWrapper: "border-2 border-primary/50 hover:border-primary focus-within:border-primary transition-colors"
+                                }}
+                                isDisabled={isSubmitting}
+                            />
+
+                            {/* Password Input */}
+                            <Input
+                                name="password"
+                                type={showPassword ? "text" : "password"}
+                                label="Password"
+                                labelPlacement="outside"
+                                radius="none"
+                                size="lg"
+                                placeholder="Enter your password"
+                                value={password}
+                                onValueChange={setPassword}
+                                autoComplete="one-time-code"
+                                isRequired
+                                errorMessage="Password is required"
+                                startContent={
+                                    <Icon icon="tabler:lock" width={20} height={20} className="text-foreground/50"/>
+                                }
+                                endContent={
+                                    <Button
+                                        isIconOnly
+                                        variant="light"
+                                        size="sm"
+                                        radius="none"
+                                        onPress={() => setShowPassword(!showPassword)}
+                                    >
+                                        <Icon
+                                            icon={showPassword ? "tabler:eye-off" : "tabler:eye"}
+                                            width={20}
+                                            height={20}
+                                            className="text-foreground/50"
+                                        />
+                                    </Button>
+                                }
+                                classNames={{
+                                    label: "font-headers font-bold text-lg uppercase",
+                                    input: "font-text text-lg",
+                                    inputWrapper: "border-2 border-primary/50 hover:border-primary focus-within:border-primary transition-colors"
+                                }}
+                                isDisabled={isSubmitting}
+                            />
+
+                            {/* Submit Button */}
+                            <Button
+                                type="submit"
+                                radius="none"
+                                color="primary"
+                                size="lg"
+                                className="font-headers font-bold text-lg uppercase mt-4"
+                                isLoading={isSubmitting}
+                                isDisabled={isSubmitting}
+                                startContent={!isSubmitting && <Icon icon="tabler:login" width={20} height={20}/>}
+                                fullWidth
+                            >
+                                {isSubmitting ? "Signing In..." : "Sign In"}
+                            </Button>
+
+                            {/* Single Sign-On */}
+                            {providers.oidc && (
+                                <Button
+                                    variant="bordered"
+                                    radius="none"
+                                    color="primary"
+                                    size="lg"
+                                    className="font-headers font-bold text-lg uppercase"
+                                    isDisabled={isSubmitting}
+                                    startContent={<Icon icon="tabler:building" width={20} height={20}/>}
+                                    onPress={handleOidcLogin}
+                                    fullWidth
+                                >
+                                    Sign in with {providers.oidc_name || "Single Sign-On"}
+                                </Button>
+                            )}
+
+                            {/* Register Link, network accounts are created on their first sign in */}
+                            {!useDirectory && <div className="flex flex-col items-center gap-1 mt-4">
+                                <p className="font-text text-foreground/70">
+                                    Don't have an account?{" "}
+                                    <Link
+                                        href="/register"
+                                        className="font-bold text-primary hover:underline"
+                                    >
+                                        Create one
+                                    </Link>
+                                </p>
+                                <Link
+                                    href="/reset-password"
+                                    className="font-text text-sm text-primary hover:underline"
+                                >
+                                    Forgot your password?
+                                </Link>
+                            </div>}
+                        </Form>
+                    </>
+                )}
             </div>
         </div>
     );
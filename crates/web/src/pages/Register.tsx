@@ -1,8 +1,8 @@
-import {addToast, Button, Form, Image, Input, Link, Select, SelectItem, Spinner} from "@heroui/react";
+import {addToast, Button, Form, Image, Input, Link, Spinner} from "@heroui/react";
 import {Icon} from "@iconify-icon/react";
 import {FormEvent, useEffect, useState} from "react";
-import {useNavigate} from "react-router-dom";
-import {useAuthentication, UserRegistrationRequest, UserRole} from "../providers/AuthenticationProvider.tsx";
+import {useNavigate, useSearchParams} from "react-router-dom";
+import {DEFAULT_PASSWORD_POLICY, PasswordPolicy, RegistrationResult, useAuthentication, UserRegistrationRequest} from "../providers/AuthenticationProvider.tsx";
 import Logo from "../images/favicon.ico";
 
 const validateEmail = (value: string) =>
@@ -15,28 +15,37 @@ const validateEmail = (value: string) =>
     return null;
 };
 
-const validatePassword = (value: string) =>
+export const validatePassword = (value: string, policy: PasswordPolicy) =>
 {
     if (!value) return "Password is required";
-    if (value.length < 8) return "Password must be at least 8 characters";
+    if ([...value].length < policy.min_length) return `Password must be at least ${policy.min_length} characters`;
+    if ([...value].length > policy.max_length) return `Password can't be longer than ${policy.max_length} characters`;
+    const classes = [/\p{Ll}/u, /\p{Lu}/u, /[0-9]/, /[^\p{L}\p{N}]/u].filter(pattern => pattern.test(value)).length;
+    if (classes < policy.min_character_classes)
+    {
+        return `Password must use at least ${policy.min_character_classes} of lowercase letters, uppercase letters, digits and symbols`;
+    }
     return null;
 };
 
 export function Register()
 {
     const navigate = useNavigate();
-    const {register, isLoading: authLoading, isAuthenticated} = useAuthentication();
+    const [searchParams] = useSearchParams();
+    const {register, getPasswordPolicy, isLoading: authLoading, isAuthenticated} = useAuthentication();
+    const [passwordPolicy, setPasswordPolicy] = useState<PasswordPolicy>(DEFAULT_PASSWORD_POLICY);
 
     const [firstName, setFirstName] = useState("");
     const [lastName, setLastName] = useState("");
-    const [email, setEmail] = useState("");
+    // Invite emails link here with the invited address and the invite token
+    const inviteToken = searchParams.get("invite") ?? undefined;
+    const [email, setEmail] = useState(searchParams.get("email") ?? "");
     const [password, setPassword] = useState("");
     const [confirmPassword, setConfirmPassword] = useState("");
-    const [role, setRole] = useState<UserRole>(UserRole.Buyer);
     const [showPassword, setShowPassword] = useState(false);
     const [showConfirmPassword, setShowConfirmPassword] = useState(false);
     const [isSubmitting, setIsSubmitting] = useState(false);
-    const [success, setSuccess] = useState(false);
+    const [result, setResult] = useState<RegistrationResult | null>(null);
 
     // Redirect authenticated users to main app
     useEffect(() =>
@@ -47,6 +56,11 @@ export function Register()
         }
     }, [authLoading, isAuthenticated, navigate]);
 
+    useEffect(() =>
+    {
+        getPasswordPolicy().then(setPasswordPolicy).catch(() => setPasswordPolicy(DEFAULT_PASSWORD_POLICY));
+    }, [getPasswordPolicy]);
+
     const validateConfirmPassword = (value: string) =>
     {
         if (!value) return "Please confirm your password";
@@ -61,8 +75,8 @@ export function Register()
         const formData = Object.fromEntries(new FormData(e.currentTarget));
 
         // Validate all fields
-        const emailError = validateEmail(formData.email as string);
-        const passwordError = validatePassword(formData.password as string);
+        const emailError = inviteToken ? null : validateEmail(formData.email as string);
+        const passwordError = validatePassword(formData.password as string, passwordPolicy);
         const confirmError = validateConfirmPassword(formData.confirmPassword as string);
 
         if (emailError || passwordError || confirmError) return;
@@ -77,11 +91,10 @@ export function Register()
                 lastName: (formData.lastName as string).trim(),
                 email: (formData.email as string).trim(),
                 password: formData.password as string,
-                role
+                inviteToken
             };
 
-            await register(userData);
-            setSuccess(true);
+            setResult(await register(userData));
         } catch (err)
         {
             addToast({
@@ -105,7 +118,7 @@ export function Register()
     }
 
     // Success state
-    if (success)
+    if (result)
     {
         return (
             <div className="flex flex-col items-center justify-center w-full min-h-[calc(100vh-2rem)] p-8">
@@ -119,7 +132,9 @@ export function Register()
                         Registration Successful
                     </h1>
                     <p className="font-text text-lg text-foreground/70 mb-8">
-                        Your account has been created. Please check your email to confirm your account before signing in.
+                        {result.pending_approval
+                            ? "Your account has been created. Please check your email to confirm your account. An administrator has to approve it before you can sign in."
+                            : "Your account has been created. You can sign in now."}
                     </p>
                     <Button
                         radius="none"
@@ -156,7 +171,7 @@ export function Register()
                         Create Account
                     </h1>
                     <p className="font-text text-lg text-foreground/70 mt-2">
-                        Fill in your details to get started.
+                        {inviteToken ? "You've been invited! Fill in your details to get started." : "Fill in your details to get started."}
                     </p>
                 </div>
 
@@ -226,7 +241,8 @@ export function Register()
                         onValueChange={setEmail}
                         autoComplete="one-time-code"
                         isRequired
-                        validate={validateEmail}
+                        isReadOnly={!!inviteToken}
+                        validate={inviteToken ? undefined : validateEmail}
                         startContent={
                             <Icon icon="tabler:mail" width={20} height={20} className="text-foreground/50"/>
                         }
@@ -238,42 +254,6 @@ export function Register()
                         isDisabled={isSubmitting}
                     />
 
-                    {/* Role Select */}
-                    <Select
-                        name="role"
-                        label="Role"
-                        labelPlacement="outside"
-                        radius="none"
-                        size="lg"
-                        placeholder="Select your role"
-                        selectedKeys={[role.toString()]}
-                        onSelectionChange={keys =>
-                        {
-                            const key = [...keys][0] as string | undefined;
-                            if (key !== undefined)
-                            {
-                                setRole(parseInt(key) as UserRole);
-                            }
-                        }}
-                        isRequired
-                        classNames={{
-                            label: "font-headers font-bold text-sm uppercase",
-                            innerWrapper: "font-text text-lg",
-                            trigger: "border-2 border-primary/50 hover:border-primary transition-colors",
-                            popoverContent: "rounded-none"
-                        }}
-                        listboxProps={{
-                            itemClasses: {
-                                base: "rounded-none"
-                            }
-                        }}
-                        isDisabled={isSubmitting}
-                    >
-                        <SelectItem key={UserRole.Buyer.toString()}>Buyer</SelectItem>
-                        <SelectItem key={UserRole.Warehouse.toString()}>Warehouse</SelectItem>
-                        <SelectItem key={UserRole.Admin.toString()}>Admin</SelectItem>
-                    </Select>
-
                     {/* Password Input */}
                     <Input
                         name="password"
@@ -282,12 +262,12 @@ export function Register()
                         labelPlacement="outside"
                         radius="none"
                         size="lg"
-                        placeholder="At least 8 characters"
+                        placeholder={`At least ${passwordPolicy.min_length} characters`}
                         value={password}
                         onValueChange={setPassword}
                         autoComplete="one-time-code"
                         isRequired
-                        validate={validatePassword}
+                        validate={value => validatePassword(value, passwordPolicy)}
                         startContent={
                             <Icon icon="tabler:lock" width={20} height={20} className="text-foreground/50"/>
                         }
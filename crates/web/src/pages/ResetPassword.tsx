@@ -0,0 +1,166 @@
+import {addToast, Button, Form, Input, Link} from "@heroui/react";
+import {Icon} from "@iconify-icon/react";
+import {FormEvent, useEffect, useState} from "react";
+import {useSearchParams} from "react-router-dom";
+import {DEFAULT_PASSWORD_POLICY, PasswordPolicy, useAuthentication} from "../providers/AuthenticationProvider.tsx";
+import {validatePassword} from "./Register.tsx";
+
+const inputClassNames = {
+    label: "font-headers font-bold text-sm uppercase",
+    input: "font-text text-lg",
+    inputWrapper: "border-2 border-primary/50 hover:border-primary focus-within:border-primary transition-colors"
+};
+
+// Without a token this asks for the email to send a reset link to; the link in the reset email
+// comes back here with the email and token to choose the new password.
+export function ResetPassword()
+{
+    const [searchParams] = useSearchParams();
+    const token = searchParams.get("token");
+    const {getPasswordPolicy} = useAuthentication();
+    const [passwordPolicy, setPasswordPolicy] = useState<PasswordPolicy>(DEFAULT_PASSWORD_POLICY);
+    const [email, setEmail] = useState(searchParams.get("email") ?? "");
+    const [password, setPassword] = useState("");
+    const [confirmPassword, setConfirmPassword] = useState("");
+    const [isSubmitting, setIsSubmitting] = useState(false);
+    const [isDone, setIsDone] = useState(false);
+
+    useEffect(() =>
+    {
+        if (!token) return;
+        getPasswordPolicy().then(setPasswordPolicy).catch(() => setPasswordPolicy(DEFAULT_PASSWORD_POLICY));
+    }, [token, getPasswordPolicy]);
+
+    const handleSubmit = async (e: FormEvent<HTMLFormElement>) =>
+    {
+        e.preventDefault();
+        if (token && (validatePassword(password, passwordPolicy) || password !== confirmPassword)) return;
+
+        setIsSubmitting(true);
+        try
+        {
+            const response = await fetch(token ? "/api/auth/password-reset/confirm" : "/api/auth/password-reset", {
+                method: "POST",
+                headers: {
+                    "Content-Type": "application/json"
+                },
+                body: JSON.stringify(token ? {email, token, password} : {email: email.trim()})
+            });
+            if (!response.ok)
+            {
+                const body = await response.json().catch(() => ({}));
+                throw new Error(body.error ?? "An unexpected error occurred");
+            }
+            setIsDone(true);
+        } catch (err)
+        {
+            addToast({
+                title: "Password Reset Error",
+                description: err instanceof Error ? err.message : "An unexpected error occurred",
+                color: "danger"
+            });
+        } finally
+        {
+            setIsSubmitting(false);
+        }
+    };
+
+    if (isDone)
+    {
+        return (
+            <div className={"h-dvh flex items-center justify-center"}>
+                <div className={"p-6 border border-gray-300 rounded-lg shadow-lg max-w-md"}>
+                    <h1 className={"flex justify-center gap-2 text-2xl font-bold mb-4"}><Icon icon={"mdi:check-circle"} className={"text-success-500 text-4xl"}/> {token ? "Password Changed!" : "Check Your Email"}</h1>
+                    <p className={"mb-4"}>
+                        {token
+                            ? "Your password has been changed and you have been signed out everywhere. You can sign in with your new password now."
+                            : "If an account uses this email, we sent it a link to choose a new password."}
+                    </p>
+                    <p className={"text-sm text-gray-600"}>Go to the <a href="/login" className={"text-blue-500 underline"}>sign in page</a>.</p>
+                </div>
+            </div>
+        );
+    }
+
+    return (
+        <div className={"h-dvh flex items-center justify-center"}>
+            <div className={"p-6 border border-gray-300 rounded-lg shadow-lg w-full max-w-md"}>
+                <h1 className={"font-headers font-black text-3xl text-primary uppercase tracking-wide mb-2"}>Reset Password</h1>
+                <p className={"font-text text-foreground/70 mb-6"}>
+                    {token ? "Choose a new password for your account." : "Enter your email and we'll send you a link to choose a new password."}
+                </p>
+                <Form onSubmit={handleSubmit} validationBehavior="native" className="flex flex-col gap-5">
+                    <Input
+                        name="email"
+                        type="email"
+                        label="Email"
+                        labelPlacement="outside"
+                        radius="none"
+                        size="lg"
+                        placeholder="you@mardens.com"
+                        value={email}
+                        onValueChange={setEmail}
+                        isRequired
+                        isReadOnly={!!token}
+                        startContent={<Icon icon="tabler:mail" width={20} height={20} className="text-foreground/50"/>}
+                        classNames={inputClassNames}
+                        isDisabled={isSubmitting}
+                    />
+                    {token && (
+                        <>
+                            <Input
+                                name="password"
+                                type="password"
+                                label="New Password"
+                                labelPlacement="outside"
+                                radius="none"
+                                size="lg"
+                                placeholder={`At least ${passwordPolicy.min_length} characters`}
+                                value={password}
+                                onValueChange={setPassword}
+                                autoComplete="new-password"
+                                isRequired
+                                validate={value => validatePassword(value, passwordPolicy)}
+                                startContent={<Icon icon="tabler:lock" width={20} height={20} className="text-foreground/50"/>}
+                                classNames={inputClassNames}
+                                isDisabled={isSubmitting}
+                            />
+                            <Input
+                                name="confirmPassword"
+                                type="password"
+                                label="Confirm Password"
+                                labelPlacement="outside"
+                                radius="none"
+                                size="lg"
+                                placeholder="Re-enter your password"
+                                value={confirmPassword}
+                                onValueChange={setConfirmPassword}
+                                autoComplete="new-password"
+                                isRequired
+                                validate={value => value !== password ? "Passwords do not match" : null}
+                                startContent={<Icon icon="tabler:lock-check" width={20} height={20} className="text-foreground/50"/>}
+                                classNames={inputClassNames}
+                                isDisabled={isSubmitting}
+                            />
+                        </>
+                    )}
+                    <Button
+                        type="submit"
+                        radius="none"
+                        color="primary"
+                        size="lg"
+                        className="font-headers font-bold text-lg uppercase"
+                        isLoading={isSubmitting}
+                        fullWidth
+                    >
+                        {token ? "Change Password" : "Send Reset Link"}
+                    </Button>
+                    <p className="font-text text-foreground/70 self-center">
+                        Remembered it?{" "}
+                        <Link href="/login" className="font-bold text-primary hover:underline">Sign in</Link>
+                    </p>
+                </Form>
+            </div>
+        </div>
+    );
+}
@@ -0,0 +1,61 @@
+import {Icon} from "@iconify-icon/react";
+import {useSearchParams} from "react-router-dom";
+import {useEffect, useState} from "react";
+import {Spinner} from "@heroui/react";
+
+export function UnlockAccount()
+{
+    const [searchParams] = useSearchParams();
+    const email = searchParams.get("email");
+    const token = searchParams.get("token");
+    const [isLoading, setIsLoading] = useState(Boolean(email && token));
+    const [hasError, setHasError] = useState(!email || !token);
+
+    useEffect(() =>
+    {
+        if (!email || !token) return;
+
+        setIsLoading(true);
+        fetch("/api/auth/unlock", {
+            method: "POST",
+            headers: {
+                "Content-Type": "application/json"
+            },
+            body: JSON.stringify({email, token})
+        })
+            .then(res => setHasError(!res.ok))
+            .catch(() => setHasError(true))
+            .finally(() => setIsLoading(false));
+    }, [email, token]);
+
+    if (isLoading) return (
+        <div className={"h-dvh flex items-center justify-center"}>
+            <div className={"p-6 border border-gray-300 rounded-lg shadow-lg"}>
+                <h1 className={"flex justify-center gap-2 text-2xl font-bold mb-4"}><Spinner/> Unlocking Account...</h1>
+            </div>
+        </div>
+    );
+
+    if (hasError)
+    {
+        return (
+            <div className={"h-dvh flex items-center justify-center"}>
+                <div className={"p-6 border border-gray-300 rounded-lg shadow-lg"}>
+                    <h1 className={"flex justify-center gap-2 text-2xl font-bold mb-4"}><Icon icon={"mdi:alert-circle"} className={"text-danger-500 text-4xl"}/> Error Unlocking Account!</h1>
+                    <p className={"mb-4"}>This unlock link is invalid or has already been used. Your account unlocks on its own once the lockout ends.</p>
+                    <p className={"text-sm text-gray-600"}>You can <a href="/" className={"text-blue-500 underline"}>return to home page</a>.</p>
+                </div>
+            </div>
+        );
+    }
+
+    return (
+        <div className={"h-dvh flex items-center justify-center"}>
+            <div className={"p-6 border border-gray-300 rounded-lg shadow-lg"}>
+                <h1 className={"flex justify-center gap-2 text-2xl font-bold mb-4"}><Icon icon={"mdi:check-circle"} className={"text-success-500 text-4xl"}/> Account Unlocked!</h1>
+                <p className={"mb-4"}>Your account has been unlocked. You can sign in again now.</p>
+                <p className={"text-sm text-gray-600"}>Go to the <a href="/login" className={"text-blue-500 underline"}>sign in page</a>.</p>
+            </div>
+        </div>
+    );
+}
@@ -22,12 +22,76 @@ export type UserRegistrationRequest = {
     lastName: string;
     email: string;
     password: string;
-    role: UserRole;
+    // Token from an invite email; invited users get the invite's role and skip approval
+    inviteToken?: string;
+}
+
+export type RegistrationResult = {
+    message: string;
+    // Self-registered users can't sign in until an administrator approves them
+    pending_approval: boolean;
 }
 
 type LoginResponse = {
     token: string;
     token_type: string;
+    expires_in: number;
+    refresh_token: string;
+}
+
+export type TwoFactorStep = "verify" | "setup";
+
+// Returned by login instead of tokens when the user still has to enter a code
+export type TwoFactorChallenge = {
+    two_factor: TwoFactorStep;
+    challenge_token: string;
+    expires_in: number;
+}
+
+export type TotpSetup = {
+    secret: string;
+    otpauth_uri: string;
+}
+
+type TwoFactorLoginResponse = LoginResponse & {
+    recovery_codes?: string[];
+}
+
+export type TwoFactorLoginResult = {
+    user?: User;
+    // Only set when the authenticator app was set up during this login
+    recoveryCodes?: string[];
+}
+
+// Which ways of signing in the server has turned on
+export type LoginProviders = {
+    password: boolean;
+    directory: boolean;
+    oidc: boolean;
+    // Shown on the single sign-on button
+    oidc_name?: string | null;
+}
+
+// The rules new passwords have to follow
+export type PasswordPolicy = {
+    min_length: number;
+    max_length: number;
+    // How many of lowercase letters, uppercase letters, digits and symbols are needed
+    min_character_classes: number;
+    breach_check: boolean;
+}
+
+export const DEFAULT_PASSWORD_POLICY: PasswordPolicy = {
+    min_length: 10,
+    max_length: 128,
+    min_character_classes: 3,
+    breach_check: false
+};
+
+// Where to send the user for single sign-on, and the state the redirect back has to carry
+export type OidcAuthorization = {
+    authorization_url: string;
+    state: string;
 }
 
 type MeResponse = {
@@ -39,15 +103,19 @@ type JWTClaims = {
     email: string;
     exp: number;
     iat: number;
+    sid: number;
+    ver: number;
 }
 
 // ============== Token Storage Utilities ==============
 
 const TOKEN_KEY = "pocf_auth_token";
+const REFRESH_TOKEN_KEY = "pocf_refresh_token";
 
-function storeToken(token: string): void
+function storeTokens(response: LoginResponse): void
 {
-    localStorage.setItem(TOKEN_KEY, token);
+    localStorage.setItem(TOKEN_KEY, response.token);
+    localStorage.setItem(REFRESH_TOKEN_KEY, response.refresh_token);
 }
 
 function getStoredToken(): string | null
@@ -55,9 +123,15 @@ function getStoredToken(): string | null
     return localStorage.getItem(TOKEN_KEY);
 }
 
+function getStoredRefreshToken(): string | null
+{
+    return localStorage.getItem(REFRESH_TOKEN_KEY);
+}
+
 function clearStoredToken(): void
 {
     localStorage.removeItem(TOKEN_KEY);
+    localStorage.removeItem(REFRESH_TOKEN_KEY);
 }
 
 function decodeJWT(token: string): JWTClaims | null
@@ -94,6 +168,62 @@ function isTokenExpired(token: string): boolean
     return Date.now() >= expirationTime;
 }
 
+// Refresh tokens are single-use, so concurrent callers share one request
+let pendingRefresh: Promise<string | null> | null = null;
+
+/**
+ * Trades the stored refresh token for a new access token and refresh token.
+ * Returns the new access token, or null if the session has ended.
+ */
+function refreshTokens(): Promise<string | null>
+{
+    if (pendingRefresh) return pendingRefresh;
+
+    pendingRefresh = (async () =>
+    {
+        const refreshToken = getStoredRefreshToken();
+        if (!refreshToken) return null;
+
+        const response = await fetch(`/api/auth/refresh`, {
+            method: "POST",
+            headers: {
+                "Content-Type": "application/json"
+            },
+            body: JSON.stringify({refresh_token: refreshToken})
+        });
+
+        if (response.status === 401)
+        {
+            clearStoredToken();
+            return null;
+        }
+
+        if (!response.ok)
+        {
+            throw new Error("Failed to refresh session");
+        }
+
+        const data: LoginResponse = await response.json();
+        storeTokens(data);
+        return data.token;
+    })().finally(() =>
+    {
+        pendingRefresh = null;
+    });
+
+    return pendingRefresh;
+}
+
+/**
+ * Returns an access token that hasn't expired, refreshing it if needed.
+ */
+async function getValidToken(): Promise<string | null>
+{
+    const token = getStoredToken();
+    if (token && !isTokenExpired(token)) return token;
+    return refreshTokens();
+}
+
 function getUserFromToken(token: string): User | null
 {
     const claims = decodeJWT(token);
@@ -112,11 +242,19 @@ interface AuthenticationContextType
     isAuthenticated: boolean;
     isLoading: boolean;
     currentUser: User | null;
-    login: (email: string, password: string) => Promise<User | undefined>;
-    register: (user: UserRegistrationRequest) => Promise<void>;
-    logout: () => void;
+    login: (email: string, password: string) => Promise<User | TwoFactorChallenge | undefined>;
+    loginWithDirectory: (username: string, password: string) => Promise<User | TwoFactorChallenge | undefined>;
+    getLoginProviders: () => Promise<LoginProviders>;
+    getPasswordPolicy: () => Promise<PasswordPolicy>;
+    beginOidcLogin: (redirectUri: string) => Promise<OidcAuthorization>;
+    loginWithOidc: (code: string, state: string) => Promise<User | TwoFactorChallenge | undefined>;
+    completeTwoFactorLogin: (challengeToken: string, code: string) => Promise<TwoFactorLoginResult>;
+    beginTwoFactorSetup: (challengeToken: string) => Promise<TotpSetup>;
+    register: (user: UserRegistrationRequest) => Promise<RegistrationResult>;
+    logout: () => Promise<void>;
+    logoutEverywhere: () => Promise<void>;
     me: () => Promise<User | undefined>;
-    getToken: () => string | null;
+    getToken: () => Promise<string | null>;
 }
 
 const AuthenticationContext = createContext<AuthenticationContextType | undefined>(undefined);
@@ -133,15 +271,19 @@ export function AuthenticationProvider({children}: { children: ReactNode })
     {
         const initializeAuth = async () =>
         {
-            const token = getStoredToken();
-
-            if (!token)
+            let token: string | null;
+            try
             {
+                token = await getValidToken();
+            } catch
+            {
+                // Network error, keep the refresh token to try again later
+                console.warn("Could not refresh session with server");
                 setIsLoading(false);
                 return;
             }
 
-            if (isTokenExpired(token))
+            if (!token)
             {
                 clearStoredToken();
                 setIsLoading(false);
@@ -186,27 +328,32 @@ export function AuthenticationProvider({children}: { children: ReactNode })
         initializeAuth();
     }, []);
 
-    const login = useCallback(async (email: string, password: string): Promise<User | undefined> =>
+    // Shared by the password and directory logins, which answer the same way
+    const signIn = useCallback(async (url: string, body: object): Promise<User | TwoFactorChallenge | undefined> =>
     {
 
         try
         {
-            const response = await fetch(`/api/auth/login`, {
+            const response = await fetch(url, {
                 method: "POST",
                 headers: {
                     "Content-Type": "application/json"
                 },
-                body: JSON.stringify({email, password})
+                body: JSON.stringify(body)
             });
 
             if (!response.ok)
             {
                 const errorData = await response.json().catch(() => ({}));
-                throw new Error(errorData.message || "Login failed");
+                throw new Error(errorData.error || errorData.message || "Login failed");
             }
 
-            const data: LoginResponse = await response.json();
-            storeToken(data.token);
+            const data: LoginResponse | TwoFactorChallenge = await response.json();
+            if ("challenge_token" in data)
+            {
+                return data;
+            }
+            storeTokens(data);
 
             const user = getUserFromToken(data.token);
             if (user)
@@ -224,7 +371,109 @@ export function AuthenticationProvider({children}: { children: ReactNode })
         }
     }, []);
 
-    const register = useCallback(async (userData: UserRegistrationRequest): Promise<void> =>
+    const login = useCallback((email: string, password: string): Promise<User | TwoFactorChallenge | undefined> =>
+    {
+        return signIn(`/api/auth/login`, {email, password});
+    }, [signIn]);
+
+    const loginWithDirectory = useCallback((username: string, password: string): Promise<User | TwoFactorChallenge | undefined> =>
+    {
+        return signIn(`/api/auth/login/directory`, {username, password});
+    }, [signIn]);
+
+    const getLoginProviders = useCallback(async (): Promise<LoginProviders> =>
+    {
+        const response = await fetch(`/api/auth/providers`);
+        if (!response.ok)
+        {
+            return {password: true, directory: false, oidc: false};
+        }
+        return await response.json();
+    }, []);
+
+    const getPasswordPolicy = useCallback(async (): Promise<PasswordPolicy> =>
+    {
+        const response = await fetch(`/api/auth/password-policy`);
+        if (!response.ok)
+        {
+            return DEFAULT_PASSWORD_POLICY;
+        }
+        return await response.json();
+    }, []);
+
+    const beginOidcLogin = useCallback(async (redirectUri: string): Promise<OidcAuthorization> =>
+    {
+        const response = await fetch(`/api/auth/oidc/authorize`, {
+            method: "POST",
+            headers: {
+                "Content-Type": "application/json"
+            },
+            body: JSON.stringify({redirect_uri: redirectUri})
+        });
+
+        if (!response.ok)
+        {
+            const errorData = await response.json().catch(() => ({}));
+            throw new Error(errorData.error || "Failed to start single sign-on");
+        }
+
+        return await response.json();
+    }, []);
+
+    const loginWithOidc = useCallback((code: string, state: string): Promise<User | TwoFactorChallenge | undefined> =>
+    {
+        return signIn(`/api/auth/login/oidc`, {code, state});
+    }, [signIn]);
+
+    const completeTwoFactorLogin = useCallback(async (challengeToken: string, code: string): Promise<TwoFactorLoginResult> =>
+    {
+        const response = await fetch(`/api/auth/login/two-factor`, {
+            method: "POST",
+            headers: {
+                "Content-Type": "application/json"
+            },
+            body: JSON.stringify({challenge_token: challengeToken, code})
+        });
+
+        if (!response.ok)
+        {
+            const errorData = await response.json().catch(() => ({}));
+            throw new Error(errorData.error || "Verification failed");
+        }
+
+        const data: TwoFactorLoginResponse = await response.json();
+        storeTokens(data);
+
+        const user = getUserFromToken(data.token) ?? undefined;
+        if (user)
+        {
+            setCurrentUser(user);
+            setIsAuthenticated(true);
+        }
+
+        return {user, recoveryCodes: data.recovery_codes};
+    }, []);
+
+    const beginTwoFactorSetup = useCallback(async (challengeToken: string): Promise<TotpSetup> =>
+    {
+        const response = await fetch(`/api/auth/login/two-factor/setup`, {
+            method: "POST",
+            headers: {
+                "Content-Type": "application/json"
+            },
+            body: JSON.stringify({challenge_token: challengeToken})
+        });
+
+        if (!response.ok)
+        {
+            const errorData = await response.json().catch(() => ({}));
+            throw new Error(errorData.error || "Failed to start two-factor setup");
+        }
+
+        return await response.json();
+    }, []);
+
+    const register = useCallback(async (userData: UserRegistrationRequest): Promise<RegistrationResult> =>
     {
 
         try
@@ -239,17 +488,18 @@ export function AuthenticationProvider({children}: { children: ReactNode })
                     last_name: userData.lastName,
                     email: userData.email,
                     password: userData.password,
-                    role: userData.role
+                    invite_token: userData.inviteToken
                 })
             });
 
             if (!response.ok)
             {
                 const errorData = await response.json().catch(() => ({}));
-                throw new Error(errorData.message || "Registration failed");
+                throw new Error(errorData.error || errorData.message || "Registration failed");
             }
 
-            // Registration successful, user must confirm email before logging in
+            // Registration successful, user must confirm email and wait for approval unless invited
+            return await response.json();
         } catch (error)
         {
             console.error("Registration error:", error);
@@ -257,17 +507,62 @@ export function AuthenticationProvider({children}: { children: ReactNode })
         }
     }, []);
 
-    const logout = useCallback((): void =>
+    const clearAuthState = useCallback((): void =>
     {
         clearStoredToken();
         setCurrentUser(null);
         setIsAuthenticated(false);
     }, []);
 
+    const logout = useCallback(async (): Promise<void> =>
+    {
+        const refreshToken = getStoredRefreshToken();
+        clearAuthState();
+        if (!refreshToken) return;
+
+        try
+        {
+            await fetch(`/api/auth/logout`, {
+                method: "POST",
+                headers: {
+                    "Content-Type": "application/json"
+                },
+                body: JSON.stringify({refresh_token: refreshToken})
+            });
+        } catch (error)
+        {
+            console.error("Logout error:", error);
+        }
+    }, [clearAuthState]);
+
+    const logoutEverywhere = useCallback(async (): Promise<void> =>
+    {
+        const token = await getValidToken();
+        if (token)
+        {
+            const response = await fetch(`/api/auth/logout-all`, {
+                method: "POST",
+                headers: {
+                    "Authorization": `Bearer ${token}`
+                }
+            });
+
+            if (!response.ok && response.status !== 401)
+            {
+                throw new Error("Failed to log out everywhere");
+            }
+        }
+        clearAuthState();
+    }, [clearAuthState]);
+
     const me = useCallback(async (): Promise<User | undefined> =>
     {
-        const token = getStoredToken();
-        if (!token) return undefined;
+        const token = await getValidToken();
+        if (!token)
+        {
+            clearAuthState();
+            return undefined;
+        }
 
         try
         {
@@ -281,10 +576,8 @@ export function AuthenticationProvider({children}: { children: ReactNode })
 
             if (response.status === 401)
             {
-                // Token is invalid, clear auth state
-                clearStoredToken();
-                setCurrentUser(null);
-                setIsAuthenticated(false);
+                // Session was ended server-side, clear auth state
+                clearAuthState();
                 return undefined;
             }
 
@@ -309,11 +602,11 @@ export function AuthenticationProvider({children}: { children: ReactNode })
             console.error("Me error:", error);
             throw error;
         }
-    }, []);
+    }, [clearAuthState]);
 
-    const getToken = useCallback((): string | null =>
+    const getToken = useCallback((): Promise<string | null> =>
     {
-        return getStoredToken();
+        return getValidToken();
     }, []);
 
     return (
@@ -322,8 +615,16 @@ export function AuthenticationProvider({children}: { children: ReactNode })
             isLoading,
             currentUser,
             login,
+            loginWithDirectory,
+            getLoginProviders,
+            getPasswordPolicy,
+            beginOidcLogin,
+            loginWithOidc,
+            completeTwoFactorLogin,
+            beginTwoFactorSetup,
             register,
             logout,
+            logoutEverywhere,
             me,
             getToken
         }}>